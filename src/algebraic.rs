@@ -1,8 +1,28 @@
 //! Algebraic (infix) expression parser and evaluator
 
-use crate::functions::{AngleMode, CalcError, Func, Op};
+use crate::constants;
+use crate::display;
+use crate::functions::{AngleMode, CalcError, ComplexMode, Func, IntFlags, NumberBase, Op, Span, WordSize};
+use crate::memory::Variables;
+use crate::quantity::Quantity;
+use crate::units::{self, UNITS};
+use crate::userfunc::{UserFunctions, MAX_CALL_DEPTH};
+use crate::value::Value;
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::cell::Cell;
+
+/// Precedence of unary minus and of functions applied without parentheses,
+/// between `Op::ImplicitMul` and `Op::Pow`
+const PREFIX_PRECEDENCE: u8 = 13;
+
+/// Precedence of `not`, between `and` and the comparisons, so that
+/// `not x<0` is `not (x<0)`
+const NOT_PRECEDENCE: u8 = 3;
+
+/// User function calls allowed while previewing, so that a runaway
+/// recursion cannot stall typing
+const PREVIEW_CALL_BUDGET: usize = 256;
 
 /// Token for expression parsing
 #[derive(Clone, Debug)]
@@ -12,7 +32,209 @@ pub enum Token {
     Function(Func),
     OpenParen,
     CloseParen,
+    /// Argument separator in function calls and matrix literals
+    Comma,
+    /// `[` opening a matrix or vector literal
+    OpenBracket,
+    CloseBracket,
+    /// Matrix literal of an explicit item count (postfix only)
+    Bracket(usize),
+    /// Function applied to an explicit argument count (postfix only)
+    Call(Func, usize),
     Ans,
+    /// User variable, resolved when the expression is evaluated
+    Variable(String),
+    /// User-defined function (applied to one argument in postfix)
+    UserFunction(String),
+    /// User-defined function applied to an explicit argument count (postfix only)
+    UserCall(String, usize),
+    /// Unit name given to `convert`, by index into `UNITS`
+    Unit(usize),
+    /// `if` branches (postfix only): `cond Then a Else b EndIf`. `Then`
+    /// pops the condition and skips to its `Else` when it is zero; `Else`
+    /// skips to its `EndIf`.
+    Then,
+    Else,
+    EndIf,
+}
+
+/// Characters of an input line, counting them as they are read so that a
+/// span's ends cost nothing to find
+#[derive(Clone)]
+struct Cursor<'a> {
+    chars: core::iter::Peekable<core::str::Chars<'a>>,
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { chars: input.chars().peekable(), position: 0 }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    /// Index of the next character
+    fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.position += 1;
+        Some(c)
+    }
+}
+
+/// A token with the characters of the input it was read from
+#[derive(Clone, Debug)]
+pub struct Spanned {
+    pub token: Token,
+    pub span: Span,
+}
+
+/// Everything an expression can refer to besides its own literals
+pub struct EvalContext<'a> {
+    pub ans: Value,
+    pub angle_mode: AngleMode,
+    pub variables: &'a Variables,
+    pub functions: &'a UserFunctions,
+    /// Parameters bound by the user function being evaluated
+    pub locals: &'a [(&'a str, Value)],
+    /// Nesting depth of user function calls
+    pub depth: usize,
+    /// Remaining user function calls, if limited
+    pub call_budget: Option<&'a Cell<usize>>,
+    /// Base for literals written without a prefix
+    pub base: NumberBase,
+    /// Integer word every value is wrapped to, if any
+    pub word: Option<WordSize>,
+    /// Carry and overflow raised by integer word arithmetic, if tracked
+    pub flags: Option<&'a Cell<IntFlags>>,
+    /// Whether real arguments may give complex results
+    pub complex: ComplexMode,
+}
+
+impl<'a> EvalContext<'a> {
+    pub fn new(
+        ans: impl Into<Value>,
+        angle_mode: AngleMode,
+        variables: &'a Variables,
+        functions: &'a UserFunctions,
+    ) -> Self {
+        Self {
+            ans: ans.into(),
+            angle_mode,
+            variables,
+            functions,
+            locals: &[],
+            depth: 0,
+            call_budget: None,
+            base: NumberBase::Decimal,
+            word: None,
+            flags: None,
+            complex: ComplexMode::Real,
+        }
+    }
+
+    /// Wrap a value to the integer word, if there is one
+    fn fit(&self, x: f64) -> Result<f64, CalcError> {
+        match self.word {
+            Some(word) => word.wrap(x),
+            None => Ok(x),
+        }
+    }
+
+    /// Wrap a quantity to the integer word; only plain numbers fit in one
+    fn fit_quantity(&self, q: Quantity) -> Result<Quantity, CalcError> {
+        match self.word {
+            Some(word) => Ok(word.wrap(q.plain()?)?.into()),
+            None => Ok(q),
+        }
+    }
+
+    /// Wrap a value to the integer word; matrices never fit in one
+    fn fit_value(&self, v: Value) -> Result<Value, CalcError> {
+        match (self.word, v) {
+            (Some(_), Value::Matrix(_)) => Err(CalcError::DomainError("no matrices in integer mode")),
+            (_, Value::Number(q)) => self.fit_quantity(q).map(Value::from),
+            (None, v) => Ok(v),
+        }
+    }
+
+    /// Fit a computed result to the integer word, noting any overflow
+    fn fit_result(&self, x: f64) -> Result<f64, CalcError> {
+        match self.word {
+            Some(word) => self.raised(word.fit_result(x)?),
+            None => Ok(x),
+        }
+    }
+
+    /// Value of a constant (`π`, `_c`); integer words hold plain numbers,
+    /// so physical constants lose their units there
+    fn constant(&self, func: &Func) -> Result<Value, CalcError> {
+        let q = func.evaluate_quantity(Quantity::default(), self.angle_mode, self.complex)?;
+        match self.word {
+            Some(_) => self.fit_quantity(Quantity::from(q.complex())).map(Value::from),
+            None => Ok(q.into()),
+        }
+    }
+
+    /// Record the flags an integer operation raised and pass its value on
+    fn raised(&self, (value, flags): (f64, IntFlags)) -> Result<f64, CalcError> {
+        if let Some(cell) = self.flags {
+            cell.set(cell.get().union(flags));
+        }
+        Ok(value)
+    }
+
+    /// Look up a name: parameters shadow global variables
+    fn lookup(&self, name: &str) -> Option<Value> {
+        self.locals
+            .iter()
+            .find(|(local, _)| local.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+            .or_else(|| self.variables.get(name))
+    }
+
+    /// Whether a name has a value here: a parameter, a variable, a unit or
+    /// a constant
+    pub fn is_bound(&self, name: &str) -> bool {
+        self.lookup(name).is_some() || AlgebraicParser::named_value(name, self).is_ok()
+    }
+}
+
+/// What evaluating the input line produced
+#[derive(Debug, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum Outcome {
+    /// A value, which also becomes Ans
+    Value(Value),
+    /// A user function was defined
+    Defined,
+}
+
+/// A line of algebraic input, split at its top-level `=` or `→`
+#[derive(Debug, PartialEq)]
+pub enum Statement<'a> {
+    Expression(&'a str),
+    /// `name = expr` or `expr → name`
+    Assign { name: &'a str, expr: &'a str },
+    /// `name(params) = body`
+    Define {
+        name: &'a str,
+        params: Vec<&'a str>,
+        body: &'a str,
+    },
+    /// `expr → unit`: the quantity `expr` shown in a unit (an index into
+    /// `UNITS`). A plain value is not stored under the unit's name, where
+    /// it would hide the unit; `m = 5` still does that.
+    Convert { expr: &'a str, to: usize },
 }
 
 /// Algebraic expression parser using shunting-yard algorithm
@@ -20,82 +242,320 @@ pub struct AlgebraicParser;
 
 impl AlgebraicParser {
     /// Parse expression string into tokens
-    pub fn tokenize(input: &str) -> Result<Vec<Token>, CalcError> {
-        let mut tokens = Vec::new();
-        let mut chars = input.chars().peekable();
+    pub fn tokenize(input: &str) -> Result<Vec<Spanned>, CalcError> {
+        Self::tokenize_with(input, NumberBase::Decimal, &|_| false)
+    }
+
+    /// Parse expression string into tokens, reading unprefixed literals in
+    /// `base` and treating names for which `is_user_function` holds as calls
+    /// to user-defined functions
+    pub fn tokenize_with(
+        input: &str,
+        base: NumberBase,
+        is_user_function: &dyn Fn(&str) -> bool,
+    ) -> Result<Vec<Spanned>, CalcError> {
+        let mut tokens: Vec<Spanned> = Vec::new();
+        let mut chars = Cursor::new(input);
 
         while let Some(&c) = chars.peek() {
-            match c {
+            let start = chars.position();
+            let token = match c {
                 ' ' | '\t' => {
                     chars.next();
+                    continue;
                 }
                 '0'..='9' | '.' => {
-                    let num = Self::parse_number(&mut chars)?;
-                    tokens.push(Token::Number(num));
+                    let num = Self::parse_literal(&mut chars, base)
+                        .map_err(|e| e.at(Span::new(start, chars.position().max(start + 1))))?;
+                    Token::Number(num)
                 }
                 '+' => {
                     chars.next();
-                    tokens.push(Token::Operator(Op::Add));
+                    Token::Operator(Op::Add)
                 }
                 '-' => {
                     chars.next();
-                    // Disambiguate unary minus vs subtraction
+                    // Disambiguate unary minus vs subtraction. Unary minus is
+                    // never folded into the literal so that `-2^2` is `-(2^2)`.
                     if Self::should_be_unary(&tokens) {
-                        // Parse as negative number or unary function
-                        if chars.peek().map_or(false, |c| c.is_ascii_digit() || *c == '.') {
-                            let num = Self::parse_number(&mut chars)?;
-                            tokens.push(Token::Number(-num));
-                        } else {
-                            tokens.push(Token::Function(Func::Negate));
-                        }
+                        Token::Function(Func::Negate)
                     } else {
-                        tokens.push(Token::Operator(Op::Sub));
+                        Token::Operator(Op::Sub)
                     }
                 }
                 '*' | '×' => {
                     chars.next();
-                    tokens.push(Token::Operator(Op::Mul));
+                    Token::Operator(Op::Mul)
                 }
                 '/' | '÷' => {
                     chars.next();
-                    tokens.push(Token::Operator(Op::Div));
+                    Token::Operator(Op::Div)
                 }
                 '^' => {
                     chars.next();
-                    tokens.push(Token::Operator(Op::Pow));
+                    Token::Operator(Op::Pow)
+                }
+                '∠' => {
+                    chars.next();
+                    Token::Operator(Op::Polar)
+                }
+                '&' => {
+                    chars.next();
+                    Token::Operator(Op::And)
+                }
+                '|' => {
+                    chars.next();
+                    Token::Operator(Op::Or)
+                }
+                '~' => {
+                    chars.next();
+                    Token::Function(Func::Not)
+                }
+                '<' | '>' => {
+                    chars.next();
+                    let op = match (c, chars.peek()) {
+                        ('<', Some('<')) => Op::Shl,
+                        ('>', Some('>')) => Op::Shr,
+                        ('<', Some('=')) => Op::LessEq,
+                        ('>', Some('=')) => Op::GreaterEq,
+                        ('<', _) => Op::Less,
+                        _ => Op::Greater,
+                    };
+                    if !matches!(op, Op::Less | Op::Greater) {
+                        chars.next();
+                    }
+                    Token::Operator(op)
+                }
+                '=' => {
+                    chars.next();
+                    if chars.peek() != Some(&'=') {
+                        return Err(CalcError::ParseError(alloc::format!(
+                            "Unknown character: {}",
+                            c
+                        ))
+                        .at(Span::new(start, start + 1)));
+                    }
+                    chars.next();
+                    Token::Operator(Op::Equal)
+                }
+                '≤' | '≥' | '≠' => {
+                    chars.next();
+                    Token::Operator(match c {
+                        '≤' => Op::LessEq,
+                        '≥' => Op::GreaterEq,
+                        _ => Op::NotEqual,
+                    })
                 }
                 '%' => {
                     chars.next();
-                    tokens.push(Token::Operator(Op::Mod));
+                    // Percent after an operand unless another operand follows
+                    // (`50%+1` vs `7%3`)
+                    let after_operand = tokens.last().is_some_and(|t| Self::ends_operand(&t.token));
+                    if after_operand && !Self::operand_follows(&chars) {
+                        Token::Function(Func::Percent)
+                    } else {
+                        Token::Operator(Op::Mod)
+                    }
+                }
+                '!' => {
+                    chars.next();
+                    if chars.peek() == Some(&'=') {
+                        chars.next();
+                        Token::Operator(Op::NotEqual)
+                    } else if chars.peek() == Some(&'!') {
+                        chars.next();
+                        Token::Function(Func::DoubleFactorial)
+                    } else {
+                        Token::Function(Func::Factorial)
+                    }
+                }
+                '²' => {
+                    chars.next();
+                    Token::Function(Func::Square)
+                }
+                '³' => {
+                    chars.next();
+                    Token::Function(Func::Cube)
                 }
                 '(' => {
                     chars.next();
-                    tokens.push(Token::OpenParen);
+                    Token::OpenParen
                 }
                 ')' => {
                     chars.next();
-                    tokens.push(Token::CloseParen);
+                    Token::CloseParen
+                }
+                ',' => {
+                    chars.next();
+                    Token::Comma
+                }
+                '[' => {
+                    chars.next();
+                    Token::OpenBracket
+                }
+                ']' => {
+                    chars.next();
+                    Token::CloseBracket
                 }
-                'a'..='z' | 'A'..='Z' | 'π' => {
+                c if Self::starts_name(c) => {
                     let name = Self::parse_identifier(&mut chars);
-                    let token = Self::match_function_or_constant(&name)?;
-                    tokens.push(token);
+                    if Self::in_unit_argument(&tokens) {
+                        match units::index_of(&name) {
+                            Some(i) => Token::Unit(i),
+                            None => {
+                                return Err(CalcError::ParseError(alloc::format!(
+                                    "Unknown unit: {}",
+                                    name
+                                ))
+                                .at(Span::new(start, chars.position())));
+                            }
+                        }
+                    // In HEX, a run of hex digits is a number (`ff`, `e`)
+                    // unless it names a user function
+                    } else if base == NumberBase::Hexadecimal
+                        && name.chars().all(|c| c.is_ascii_hexdigit())
+                        && !is_user_function(&name.to_lowercase())
+                    {
+                        Token::Number(
+                            Self::parse_radix(&name, 16).map_err(|e| e.at(Span::new(start, chars.position())))?,
+                        )
+                    } else {
+                        let call = chars.clone().find(|c| !c.is_whitespace()) == Some('(');
+                        Self::match_function_or_constant(&name, call, is_user_function)
+                            .map_err(|e| e.at(Span::new(start, chars.position())))?
+                    }
                 }
                 _ => {
                     return Err(CalcError::ParseError(alloc::format!(
                         "Unknown character: {}",
                         c
-                    )));
+                    ))
+                    .at(Span::new(start, start + 1)));
+                }
+            };
+
+            let token = Spanned {
+                token,
+                span: Span::new(start, chars.position()),
+            };
+            // Units given to `convert` stand alone between commas
+            if Self::in_unit_argument(&tokens) {
+                let alone = match tokens.last().map(|t| &t.token) {
+                    Some(Token::Comma) => matches!(token.token, Token::Unit(_)),
+                    _ => matches!(token.token, Token::Comma | Token::CloseParen),
+                };
+                if !alone {
+                    return Err(CalcError::SyntaxError("convert: unit expected".into()).at(token.span));
                 }
             }
+            let starts_operand = matches!(c, '0'..='9' | '.' | '(' | '[') || Self::starts_name(c);
+            // Word operators (`xor`) are spelled like names but are not operands
+            if starts_operand && !matches!(token.token, Token::Operator(_)) {
+                Self::push_operand(&mut tokens, token)?;
+            } else {
+                tokens.push(token);
+            }
         }
 
         Ok(tokens)
     }
 
+    /// Is the next token in a unit argument of `convert`, i.e. after the
+    /// first comma of its argument list?
+    fn in_unit_argument(tokens: &[Spanned]) -> bool {
+        let mut depth = 0;
+        let mut commas = 0;
+        for (i, spanned) in tokens.iter().enumerate().rev() {
+            match spanned.token {
+                Token::CloseParen | Token::CloseBracket => depth += 1,
+                Token::OpenParen | Token::OpenBracket if depth > 0 => depth -= 1,
+                Token::OpenBracket => return false,
+                Token::OpenParen => {
+                    return commas > 0
+                        && i > 0
+                        && matches!(tokens[i - 1].token, Token::Function(Func::Convert));
+                }
+                Token::Comma if depth == 0 => commas += 1,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Parse a literal: prefixed (`0x1F`, `0o17`, `0b101`) in any base,
+    /// otherwise in `base`
+    fn parse_literal(
+        chars: &mut Cursor,
+        base: NumberBase,
+    ) -> Result<f64, CalcError> {
+        if let Some(radix) = Self::radix_prefix(chars) {
+            let mut text = String::new();
+            text.extend(chars.by_ref().take(2));
+            while let Some(&c) = chars.peek() {
+                if !c.is_digit(radix) {
+                    break;
+                }
+                text.push(c);
+                chars.next();
+            }
+            return display::parse_number(&text)
+                .ok_or_else(|| CalcError::ParseError(alloc::format!("Invalid number: {}", text)));
+        }
+
+        match base {
+            NumberBase::Decimal => Self::parse_number(chars),
+            _ => Self::parse_integer(chars, base),
+        }
+    }
+
+    /// Radix of a `0x`/`0o`/`0b` prefix under the cursor, if a digit follows
+    fn radix_prefix(chars: &Cursor) -> Option<u32> {
+        let mut ahead = chars.clone();
+        if ahead.next() != Some('0') {
+            return None;
+        }
+        let radix = match ahead.next()? {
+            'x' | 'X' => 16,
+            'o' | 'O' => 8,
+            'b' | 'B' => 2,
+            _ => return None,
+        };
+        ahead.next()?.is_digit(radix).then_some(radix)
+    }
+
+    /// Parse an unprefixed integer in a non-decimal base
+    fn parse_integer(
+        chars: &mut Cursor,
+        base: NumberBase,
+    ) -> Result<f64, CalcError> {
+        // A hex number may run into letters (`2ff`), but not into a name
+        // (`2abs`), in which case only its decimal digits are taken
+        let run: String = chars.clone().take_while(|c| c.is_ascii_alphanumeric()).collect();
+        let len = if base == NumberBase::Hexadecimal && run.chars().all(|c| c.is_ascii_hexdigit()) {
+            run.len()
+        } else {
+            run.chars().take_while(|c| c.is_ascii_digit()).count()
+        };
+        if len == 0 {
+            return Err(CalcError::ParseError(alloc::format!(
+                "No fractions in {}",
+                base.label()
+            )));
+        }
+        chars.nth(len - 1);
+        Self::parse_radix(&run[..len], base.radix())
+    }
+
+    /// Parse digits in the given radix as an unsigned integer
+    fn parse_radix(digits: &str, radix: u32) -> Result<f64, CalcError> {
+        u64::from_str_radix(digits, radix)
+            .map(|n| n as f64)
+            .map_err(|_| CalcError::ParseError(alloc::format!("Invalid number: {}", digits)))
+    }
+
     /// Parse a number from the character stream
     fn parse_number(
-        chars: &mut core::iter::Peekable<core::str::Chars>,
+        chars: &mut Cursor,
     ) -> Result<f64, CalcError> {
         let mut num_str = String::new();
         let mut has_decimal = false;
@@ -112,7 +572,7 @@ impl AlgebraicParser {
                     has_decimal = true;
                     chars.next();
                 }
-                'e' | 'E' if !has_exponent => {
+                'e' | 'E' if !has_exponent && Self::exponent_follows(chars) => {
                     num_str.push(c);
                     has_exponent = true;
                     chars.next();
@@ -133,12 +593,61 @@ impl AlgebraicParser {
             .map_err(|_| CalcError::ParseError(alloc::format!("Invalid number: {}", num_str)))
     }
 
-    /// Parse an identifier (function name or constant)
-    fn parse_identifier(chars: &mut core::iter::Peekable<core::str::Chars>) -> String {
+    /// Is the `e` under the cursor an exponent marker (`2e3`, `2e-3`) rather
+    /// than the constant e multiplied implicitly (`2e`, `2e-1`)?
+    fn exponent_follows(chars: &Cursor) -> bool {
+        let mut ahead = chars.clone();
+        ahead.next();
+        match ahead.next() {
+            Some(c) if c.is_ascii_digit() => true,
+            Some('+') | Some('-') => matches!(ahead.next(), Some(c) if c.is_ascii_digit()),
+            _ => false,
+        }
+    }
+
+    /// Does the next non-blank character start an operand?
+    fn operand_follows(chars: &Cursor) -> bool {
+        let mut ahead = chars.clone();
+        while ahead.peek().is_some_and(|c| c.is_whitespace()) {
+            ahead.next();
+        }
+        matches!(ahead.peek(), Some(c) if c.is_ascii_alphanumeric() || matches!(c, '.' | 'π' | '('))
+    }
+
+    /// Can a name start with `c`? Greek letters and `ħ` spell constant
+    /// symbols (`ε0`, `ħ`).
+    fn starts_name(c: char) -> bool {
+        c.is_ascii_alphabetic() || matches!(c, '_' | 'ħ' | 'α'..='ω')
+    }
+
+    /// Parse an identifier (function name or constant). A leading `_`
+    /// marks a physical constant (`_c`); one inside a name joins a
+    /// subscript (`k_B`).
+    fn parse_identifier(chars: &mut Cursor) -> String {
         let mut name = String::new();
 
+        // π is a symbol on its own, so `πr` and `2π` split naturally
+        if chars.peek() == Some(&'π') {
+            chars.next();
+            name.push('π');
+            return name;
+        }
+
+        if chars.peek() == Some(&'_') {
+            chars.next();
+            name.push('_');
+        }
+
+        let subscript_follows = |chars: &Cursor| {
+            chars.clone().nth(1).is_some_and(|c| c.is_ascii_alphanumeric())
+        };
         while let Some(&c) = chars.peek() {
-            if c.is_ascii_alphabetic() || c == 'π' || (c.is_ascii_digit() && !name.is_empty()) {
+            let letters = name.chars().any(|c| c.is_alphabetic());
+            if c.is_ascii_alphabetic()
+                || (matches!(c, 'ħ' | 'α'..='ω') && c != 'π')
+                || (matches!(c, '0'..='9' | '∞') && letters)
+                || (c == '_' && letters && subscript_follows(chars))
+            {
                 name.push(c);
                 chars.next();
             } else {
@@ -149,8 +658,14 @@ impl AlgebraicParser {
         name
     }
 
-    /// Match identifier to function or constant
-    fn match_function_or_constant(name: &str) -> Result<Token, CalcError> {
+    /// Match identifier to function or constant. A function named like a
+    /// unit is only called when `call`, an argument list, follows: `min(a,b)`
+    /// but `5 min`.
+    fn match_function_or_constant(
+        name: &str,
+        call: bool,
+        is_user_function: &dyn Fn(&str) -> bool,
+    ) -> Result<Token, CalcError> {
         let lower = name.to_lowercase();
 
         // Check for Ans
@@ -158,95 +673,366 @@ impl AlgebraicParser {
             return Ok(Token::Ans);
         }
 
-        // Check for π
+        // Constants stay symbolic so `2 3` is still rejected while `2π` is not
         if name == "π" || lower == "pi" {
-            return Ok(Token::Number(core::f64::consts::PI));
+            return Ok(Token::Function(Func::Pi));
         }
 
-        // Check for e constant
         if lower == "e" && name.len() == 1 {
-            return Ok(Token::Number(core::f64::consts::E));
+            return Ok(Token::Function(Func::E));
+        }
+
+        if name.starts_with('_') {
+            return match constants::index_of(name) {
+                Some(i) => Ok(Token::Function(Func::Constant(i))),
+                None => Err(CalcError::ParseError(alloc::format!("Unknown constant: {}", name))),
+            };
         }
 
         // Check for function
-        if let Some(func) = Func::from_name(&lower) {
+        if let Some(func) = Func::from_name(&lower).filter(|_| call || units::index_of(name).is_none()) {
             return Ok(Token::Function(func));
         }
 
-        Err(CalcError::ParseError(alloc::format!(
-            "Unknown identifier: {}",
-            name
+        if let Some(op) = Op::from_name(&lower) {
+            return Ok(Token::Operator(op));
+        }
+
+        if is_user_function(&lower) {
+            return Ok(Token::UserFunction(lower));
+        }
+
+        // Anything else names a user variable, or else a unit or a constant
+        // symbol, whose case matters
+        Ok(Token::Variable(String::from(name)))
+    }
+
+    /// Split a line at its top-level `=` or `→` into a statement.
+    ///
+    /// An `=` that is part of a comparison operator is not an assignment.
+    pub fn parse_statement(input: &str) -> Result<Statement<'_>, CalcError> {
+        let (target, expr) = if let Some(pos) = input.rfind('→') {
+            (input[pos + '→'.len_utf8()..].trim(), &input[..pos])
+        } else if let Some(pos) = Self::find_assign_equals(input) {
+            (input[..pos].trim(), &input[pos + 1..])
+        } else {
+            return Ok(Statement::Expression(input));
+        };
+
+        if expr.trim().is_empty() {
+            return Err(CalcError::SyntaxError("Missing expression".into()));
+        }
+        // `5ft→m` converts when the arrow points at a unit
+        if input.contains('→') {
+            if let Some(to) = units::index_of(target) {
+                return Ok(Statement::Convert { expr, to });
+            }
+        }
+        if Variables::is_valid_name(target) {
+            return Ok(Statement::Assign { name: target, expr });
+        }
+        // Only `=` can introduce a function definition
+        if !input.contains('→') {
+            if let Some((name, params)) = Self::parse_header(target) {
+                return Ok(Statement::Define {
+                    name,
+                    params,
+                    body: expr,
+                });
+            }
+        }
+        Err(CalcError::SyntaxError(alloc::format!(
+            "Invalid assignment target: {}",
+            target
         )))
     }
 
+    /// Split `expr unit` at a unit name ending it that converts a plain
+    /// value rather than a quantity: a temperature scale, whose zero is
+    /// shifted, or any unit in an integer word, where there are no
+    /// quantities
+    fn split_plain_unit(expr: &str, word: Option<WordSize>) -> Option<(&str, usize)> {
+        let expr = expr.trim_end();
+        let end = expr.trim_end_matches(|c: char| c.is_ascii_alphabetic()).len();
+        let unit = units::index_of(&expr[end..])?;
+        if word.is_none() && UNITS[unit].offset == 0.0 {
+            return None;
+        }
+        Some((&expr[..end], unit))
+    }
+
+    /// Parse a function header `name(p1,p2,...)` with distinct parameters
+    fn parse_header(header: &str) -> Option<(&str, Vec<&str>)> {
+        let open = header.find('(')?;
+        let inner = header[open + 1..].strip_suffix(')')?;
+        let name = header[..open].trim();
+        if !Variables::is_valid_name(name) {
+            return None;
+        }
+
+        let params: Vec<&str> = if inner.trim().is_empty() {
+            Vec::new()
+        } else {
+            inner.split(',').map(|p| p.trim()).collect()
+        };
+        let valid = params.iter().enumerate().all(|(i, p)| {
+            Variables::is_valid_name(p) && !params[..i].iter().any(|q| q.eq_ignore_ascii_case(p))
+        });
+        if valid {
+            Some((name, params))
+        } else {
+            None
+        }
+    }
+
+    /// Position of a lone `=` (not part of `==`, `<=`, `>=` or `!=`)
+    pub fn find_assign_equals(input: &str) -> Option<usize> {
+        let bytes = input.as_bytes();
+        (0..bytes.len()).find(|&i| {
+            bytes[i] == b'='
+                && !matches!(i.checked_sub(1).map(|j| bytes[j]), Some(b'=' | b'<' | b'>' | b'!'))
+                && bytes.get(i + 1) != Some(&b'=')
+        })
+    }
+
+    /// Is the input a bare name or function header that `=` would assign to?
+    pub fn is_assignment_target(input: &str) -> bool {
+        let input = input.trim();
+        Variables::is_valid_name(input) || Self::parse_header(input).is_some()
+    }
+
     /// Should the next minus be treated as unary?
-    fn should_be_unary(tokens: &[Token]) -> bool {
-        match tokens.last() {
+    fn should_be_unary(tokens: &[Spanned]) -> bool {
+        match tokens.last().map(|t| &t.token) {
             None => true,
             Some(Token::Operator(_)) => true,
-            Some(Token::OpenParen) => true,
-            Some(Token::Function(_)) => true,
+            Some(Token::OpenParen) | Some(Token::OpenBracket) | Some(Token::Comma) => true,
+            Some(Token::Function(f)) => !f.is_constant() && !f.is_postfix(),
+            _ => false,
+        }
+    }
+
+    /// Does this token complete an operand, so that a following operand is
+    /// juxtaposed with it?
+    fn ends_operand(token: &Token) -> bool {
+        match token {
+            Token::Number(_) | Token::Ans | Token::Variable(_) | Token::CloseParen | Token::CloseBracket => true,
+            Token::Function(f) => f.is_constant() || f.is_postfix(),
             _ => false,
         }
     }
 
+    /// Push a token that starts an operand, inserting an implicit
+    /// multiplication if it directly follows another operand.
+    ///
+    /// Two bare literals (`2 3`) are rejected.
+    fn push_operand(tokens: &mut Vec<Spanned>, token: Spanned) -> Result<(), CalcError> {
+        if let Some(last) = tokens.last() {
+            if matches!((&last.token, &token.token), (Token::Number(_), Token::Number(_))) {
+                return Err(CalcError::SyntaxError("Missing operator".into()).at(token.span));
+            }
+            if Self::ends_operand(&last.token) {
+                // The implicit operator sits where the second operand starts
+                tokens.push(Spanned {
+                    token: Token::Operator(Op::ImplicitMul),
+                    span: Span::new(token.span.start, token.span.start),
+                });
+            }
+        }
+        tokens.push(token);
+        Ok(())
+    }
+
     /// Convert infix tokens to postfix using shunting-yard algorithm
-    pub fn to_postfix(tokens: Vec<Token>) -> Result<Vec<Token>, CalcError> {
-        let mut output: Vec<Token> = Vec::new();
-        let mut op_stack: Vec<Token> = Vec::new();
-
-        for token in tokens {
-            match token {
-                Token::Number(_) | Token::Ans => output.push(token),
-                Token::Function(_) => op_stack.push(token),
+    ///
+    /// Function calls with parenthesised argument lists are emitted as
+    /// `Token::Function` for a single argument and `Token::Call` otherwise,
+    /// spanning the whole call. `if(c,a,b)` is emitted as
+    /// `c Then a Else b EndIf` so that only one branch is evaluated, and
+    /// `[a,b]` as `a b Bracket(2)`.
+    pub fn to_postfix(tokens: Vec<Spanned>) -> Result<Vec<Spanned>, CalcError> {
+        let mut output: Vec<Spanned> = Vec::new();
+        let mut op_stack: Vec<Spanned> = Vec::new();
+        // One entry per open paren or bracket: the argument count if it
+        // opens a call or a matrix literal
+        let mut arg_counts: Vec<Option<usize>> = Vec::new();
+        // Previous token was `(`, `[` or `,`, so no argument has started yet
+        let mut at_group_start = false;
+
+        for spanned in tokens {
+            let group_start = at_group_start;
+            at_group_start = matches!(spanned.token, Token::OpenParen | Token::OpenBracket | Token::Comma);
+            let span = spanned.span;
+
+            match spanned.token {
+                Token::Number(_) | Token::Unit(_) | Token::Ans | Token::Variable(_) => {
+                    output.push(spanned)
+                }
+                // Postfix operators apply to the operand just completed
+                Token::Function(f) if f.is_constant() || f.is_postfix() => output.push(spanned),
+                Token::Function(_) | Token::UserFunction(_) => op_stack.push(spanned),
                 Token::Operator(op) => {
                     while let Some(top) = op_stack.last() {
-                        match top {
+                        let pop = match top.token {
                             Token::Operator(top_op) => {
-                                if (op.is_left_assoc() && op.precedence() <= top_op.precedence())
+                                (op.is_left_assoc() && op.precedence() <= top_op.precedence())
                                     || op.precedence() < top_op.precedence()
-                                {
-                                    output.push(op_stack.pop().unwrap());
-                                } else {
-                                    break;
-                                }
                             }
-                            Token::Function(_) => {
-                                // Functions have higher precedence
-                                break;
+                            // A function still on the stack here was written
+                            // without parentheses (`-x`, `sin 30`): it binds
+                            // tighter than everything except `^`
+                            Token::Function(Func::LogicalNot) => op.precedence() < NOT_PRECEDENCE,
+                            Token::Function(_) | Token::UserFunction(_) => {
+                                op.precedence() < PREFIX_PRECEDENCE
                             }
-                            _ => break,
+                            _ => false,
+                        };
+                        if !pop {
+                            break;
                         }
+                        output.push(op_stack.pop().unwrap());
+                    }
+                    op_stack.push(spanned);
+                }
+                Token::OpenParen => {
+                    let is_call = matches!(
+                        op_stack.last().map(|t| &t.token),
+                        Some(Token::Function(_)) | Some(Token::UserFunction(_))
+                    );
+                    arg_counts.push(if is_call { Some(1) } else { None });
+                    op_stack.push(spanned);
+                }
+                Token::Comma => {
+                    if group_start {
+                        return Err(CalcError::SyntaxError("Missing argument".into()).at(span));
+                    }
+                    while let Some(top) = op_stack.last() {
+                        if matches!(top.token, Token::OpenParen | Token::OpenBracket) {
+                            break;
+                        }
+                        output.push(op_stack.pop().unwrap());
+                    }
+                    let count = match arg_counts.last_mut() {
+                        Some(Some(count)) => {
+                            *count += 1;
+                            *count
+                        }
+                        _ => {
+                            return Err(CalcError::SyntaxError("Unexpected comma".into()).at(span));
+                        }
+                    };
+                    // The open paren is on top, the function beneath it
+                    let in_if = op_stack.len() >= 2
+                        && matches!(op_stack[op_stack.len() - 1].token, Token::OpenParen)
+                        && matches!(op_stack[op_stack.len() - 2].token, Token::Function(Func::If));
+                    if in_if {
+                        let token = match count {
+                            2 => Token::Then,
+                            3 => Token::Else,
+                            _ => {
+                                return Err(CalcError::SyntaxError(
+                                    "if: wrong number of arguments".into(),
+                                )
+                                .at(span));
+                            }
+                        };
+                        output.push(Spanned { token, span });
                     }
-                    op_stack.push(token);
                 }
-                Token::OpenParen => op_stack.push(token),
                 Token::CloseParen => {
                     let mut found_paren = false;
                     while let Some(top) = op_stack.pop() {
-                        if matches!(top, Token::OpenParen) {
-                            found_paren = true;
-                            break;
+                        match top.token {
+                            Token::OpenParen => {
+                                found_paren = true;
+                                break;
+                            }
+                            Token::OpenBracket => {
+                                return Err(CalcError::SyntaxError("Mismatched brackets".into()).at(span));
+                            }
+                            _ => output.push(top),
                         }
-                        output.push(top);
                     }
                     if !found_paren {
-                        return Err(CalcError::SyntaxError("Mismatched parentheses".into()));
+                        return Err(CalcError::SyntaxError("Mismatched parentheses".into()).at(span));
                     }
+                    let arg_count = match arg_counts.pop().flatten() {
+                        Some(1) if group_start => 0,
+                        Some(_) if group_start => {
+                            return Err(CalcError::SyntaxError("Missing argument".into()).at(span));
+                        }
+                        Some(n) => n,
+                        None => 1,
+                    };
                     // Pop function if present after paren
-                    if let Some(Token::Function(_)) = op_stack.last() {
-                        output.push(op_stack.pop().unwrap());
+                    if matches!(
+                        op_stack.last().map(|t| &t.token),
+                        Some(Token::Function(_)) | Some(Token::UserFunction(_))
+                    ) {
+                        let func = op_stack.pop().unwrap();
+                        let token = match func.token {
+                            Token::Function(Func::If) if arg_count == 3 => Token::EndIf,
+                            Token::Function(Func::If) => {
+                                return Err(CalcError::SyntaxError(
+                                    "if: wrong number of arguments".into(),
+                                )
+                                .at(func.span.to(span)));
+                            }
+                            Token::Function(f) if arg_count != 1 => Token::Call(f, arg_count),
+                            Token::UserFunction(name) if arg_count != 1 => {
+                                Token::UserCall(name, arg_count)
+                            }
+                            token => token,
+                        };
+                        output.push(Spanned {
+                            token,
+                            span: func.span.to(span),
+                        });
+                    }
+                }
+                Token::OpenBracket => {
+                    arg_counts.push(Some(1));
+                    op_stack.push(spanned);
+                }
+                Token::CloseBracket => {
+                    let open = loop {
+                        match op_stack.pop() {
+                            Some(Spanned { token: Token::OpenBracket, span }) => break span,
+                            Some(top) if !matches!(top.token, Token::OpenParen) => output.push(top),
+                            _ => {
+                                return Err(CalcError::SyntaxError("Mismatched brackets".into()).at(span));
+                            }
+                        }
+                    };
+                    if group_start {
+                        return Err(CalcError::SyntaxError("Missing argument".into()).at(span));
                     }
+                    let count = arg_counts.pop().flatten().unwrap_or(1);
+                    output.push(Spanned {
+                        token: Token::Bracket(count),
+                        span: open.to(span),
+                    });
                 }
+                Token::Call(..)
+                | Token::UserCall(..)
+                | Token::Bracket(_)
+                | Token::Then
+                | Token::Else
+                | Token::EndIf => output.push(spanned),
             }
         }
 
         // Pop remaining operators
         while let Some(top) = op_stack.pop() {
-            if matches!(top, Token::OpenParen) {
-                return Err(CalcError::SyntaxError("Mismatched parentheses".into()));
+            match top.token {
+                Token::OpenParen => {
+                    return Err(CalcError::SyntaxError("Mismatched parentheses".into()).at(top.span));
+                }
+                Token::OpenBracket => {
+                    return Err(CalcError::SyntaxError("Mismatched brackets".into()).at(top.span));
+                }
+                _ => output.push(top),
             }
-            output.push(top);
         }
 
         Ok(output)
@@ -254,43 +1040,40 @@ impl AlgebraicParser {
 
     /// Evaluate postfix expression
     pub fn evaluate(
-        postfix: Vec<Token>,
+        postfix: Vec<Spanned>,
         ans: f64,
         angle_mode: AngleMode,
-    ) -> Result<f64, CalcError> {
-        let mut stack: Vec<f64> = Vec::new();
+    ) -> Result<Value, CalcError> {
+        let variables = Variables::new();
+        let functions = UserFunctions::new();
+        let ctx = EvalContext::new(ans, angle_mode, &variables, &functions);
+        Self::evaluate_in(&postfix, &ctx)
+    }
 
-        for token in postfix {
-            match token {
-                Token::Number(n) => stack.push(n),
-                Token::Ans => stack.push(ans),
-                Token::Operator(op) => {
-                    if stack.len() < 2 {
-                        return Err(CalcError::SyntaxError("Not enough operands".into()));
-                    }
-                    let b = stack.pop().unwrap();
-                    let a = stack.pop().unwrap();
-                    let result = op.evaluate(a, b)?;
-                    stack.push(result);
-                }
-                Token::Function(func) => {
-                    if func.is_constant() {
-                        let result = func.evaluate(0.0, angle_mode)?;
-                        stack.push(result);
-                    } else {
-                        if stack.is_empty() {
-                            return Err(CalcError::SyntaxError("Not enough operands".into()));
-                        }
-                        let x = stack.pop().unwrap();
-                        let result = func.evaluate(x, angle_mode)?;
-                        stack.push(result);
+    /// Evaluate postfix expression against a context
+    ///
+    /// Errors are located at the token that raised them; errors inside a
+    /// user function are reported at its call.
+    pub fn evaluate_in(postfix: &[Spanned], ctx: &EvalContext) -> Result<Value, CalcError> {
+        let mut stack: Vec<Value> = Vec::new();
+
+        let mut i = 0;
+        while i < postfix.len() {
+            let spanned = &postfix[i];
+            match spanned.token {
+                Token::Then => {
+                    let cond = stack.pop().ok_or_else(|| {
+                        CalcError::SyntaxError("Missing operand".into()).at(spanned.span)
+                    })?;
+                    if cond.is_zero() {
+                        i = Self::skip_branch(postfix, i);
                     }
                 }
-                Token::OpenParen | Token::CloseParen => {
-                    // Should not appear in postfix
-                    return Err(CalcError::SyntaxError("Unexpected parenthesis".into()));
-                }
+                Token::Else => i = Self::skip_branch(postfix, i),
+                _ => Self::apply_token(&spanned.token, &mut stack, ctx)
+                    .map_err(|e| e.at(spanned.span))?,
             }
+            i += 1;
         }
 
         if stack.len() != 1 {
@@ -300,11 +1083,180 @@ impl AlgebraicParser {
         Ok(stack.pop().unwrap())
     }
 
-    /// Parse and evaluate an expression in one step
+    /// Index of the `Else` or `EndIf` closing the branch that starts at
+    /// `from`, skipping over nested `if`s
+    fn skip_branch(postfix: &[Spanned], from: usize) -> usize {
+        let mut depth = 0;
+        for (i, spanned) in postfix.iter().enumerate().skip(from + 1) {
+            match spanned.token {
+                Token::Then => depth += 1,
+                Token::Else | Token::EndIf if depth == 0 => return i,
+                Token::EndIf => depth -= 1,
+                _ => {}
+            }
+        }
+        postfix.len()
+    }
+
+    /// Apply one postfix token to the value stack
+    fn apply_token(token: &Token, stack: &mut Vec<Value>, ctx: &EvalContext) -> Result<(), CalcError> {
+        let angle_mode = ctx.angle_mode;
+        match token {
+            Token::Number(n) => stack.push(ctx.fit(*n)?.into()),
+            Token::Ans => stack.push(ctx.fit_value(ctx.ans)?),
+            Token::Unit(i) => stack.push((*i as f64).into()),
+            Token::Variable(name) => match ctx.lookup(name) {
+                Some(value) => stack.push(ctx.fit_value(value)?),
+                None => stack.push(Self::named_value(name, ctx)?),
+            },
+            Token::Operator(_) if stack.len() < 2 => {
+                return Err(CalcError::SyntaxError("Missing operand".into()));
+            }
+            Token::Operator(op) => {
+                let b = stack.pop().unwrap();
+                let a = stack.pop().unwrap();
+                let result = match ctx.word {
+                    Some(word) => ctx
+                        .raised(op.evaluate_word(a.number()?.plain()?, b.number()?.plain()?, word)?)?
+                        .into(),
+                    None => op.evaluate_value(a, b, angle_mode, ctx.complex)?,
+                };
+                stack.push(result);
+            }
+            Token::Function(func) if func.is_constant() => stack.push(ctx.constant(func)?),
+            Token::Function(func) => {
+                let x = stack
+                    .pop()
+                    .ok_or_else(|| CalcError::SyntaxError("Missing operand".into()))?;
+                let result = match ctx.word {
+                    Some(word) => ctx.raised(func.evaluate_word(x.number()?.plain()?, angle_mode, word)?)?.into(),
+                    None => func.evaluate_value(x, angle_mode, ctx.complex)?,
+                };
+                stack.push(result);
+            }
+            Token::Call(func, n) => {
+                if stack.len() < *n {
+                    return Err(CalcError::SyntaxError("Missing operand".into()));
+                }
+                let args = stack.split_off(stack.len() - n);
+                let result = match ctx.word {
+                    Some(_) => {
+                        let args = args
+                            .iter()
+                            .map(|v| v.number()?.plain())
+                            .collect::<Result<Vec<f64>, CalcError>>()?;
+                        ctx.fit_result(func.evaluate_args(&args, angle_mode)?)?.into()
+                    }
+                    None => func.evaluate_args_value(&args, angle_mode, ctx.complex)?,
+                };
+                stack.push(result);
+            }
+            Token::Bracket(n) => {
+                if stack.len() < *n {
+                    return Err(CalcError::SyntaxError("Missing operand".into()));
+                }
+                let items = stack.split_off(stack.len() - n);
+                stack.push(ctx.fit_value(Value::bracket(&items)?)?);
+            }
+            Token::UserFunction(name) => {
+                let x = stack
+                    .pop()
+                    .ok_or_else(|| CalcError::SyntaxError("Missing operand".into()))?;
+                stack.push(Self::call_user_function(name, &[x], ctx)?);
+            }
+            Token::UserCall(name, n) => {
+                if stack.len() < *n {
+                    return Err(CalcError::SyntaxError("Missing operand".into()));
+                }
+                let args = stack.split_off(stack.len() - n);
+                stack.push(Self::call_user_function(name, &args, ctx)?);
+            }
+            // Branches are taken in evaluate_in
+            Token::Then | Token::Else | Token::EndIf => {}
+            Token::OpenParen | Token::CloseParen | Token::OpenBracket | Token::CloseBracket | Token::Comma => {
+                // Should not appear in postfix
+                return Err(CalcError::SyntaxError("Unexpected parenthesis".into()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Value of a name that is not a variable: one of a unit, or a physical
+    /// constant by its symbol. A unit spelled exactly wins (`g` is a gram),
+    /// then a constant (`G`, `k_B`), then a unit in any case (`KM`).
+    /// Integer words have no units; constants are plain numbers there.
+    fn named_value(name: &str, ctx: &EvalContext) -> Result<Value, CalcError> {
+        let unit = units::index_of(name);
+        let exact = unit.filter(|&i| UNITS[i].name == name);
+        match (exact, constants::by_symbol(name), unit) {
+            (Some(unit), _, _) | (None, None, Some(unit)) if ctx.word.is_none() => {
+                Ok(Quantity::of_unit(unit)?.into())
+            }
+            (None, Some(i), _) => ctx.constant(&Func::Constant(i)),
+            _ => Err(CalcError::ParseError(alloc::format!("Unknown identifier: {}", name))),
+        }
+    }
+
+    /// Parse and evaluate an expression in one step, giving the value of a
+    /// quantity in SI units
     pub fn calculate(input: &str, ans: f64, angle_mode: AngleMode) -> Result<f64, CalcError> {
         let tokens = Self::tokenize(input)?;
         let postfix = Self::to_postfix(tokens)?;
-        Self::evaluate(postfix, ans, angle_mode)
+        Self::evaluate(postfix, ans, angle_mode).and_then(|v| v.number()).map(|q| q.value)
+    }
+
+    /// Call a user-defined function with its parameters bound to `args`
+    pub fn call_user_function(
+        name: &str,
+        args: &[Value],
+        ctx: &EvalContext,
+    ) -> Result<Value, CalcError> {
+        let func = ctx.functions.get(name).ok_or_else(|| {
+            CalcError::ParseError(alloc::format!("Unknown function: {}", name))
+        })?;
+        if func.params.len() != args.len() {
+            return Err(CalcError::SyntaxError(alloc::format!(
+                "{}: wrong number of arguments",
+                name
+            )));
+        }
+        if ctx.depth >= MAX_CALL_DEPTH {
+            return Err(CalcError::RecursionLimit);
+        }
+        if let Some(budget) = ctx.call_budget {
+            if budget.get() == 0 {
+                return Err(CalcError::RecursionLimit);
+            }
+            budget.set(budget.get() - 1);
+        }
+
+        let locals: Vec<(&str, Value)> = func
+            .params
+            .iter()
+            .map(|p| p.as_str())
+            .zip(args.iter().copied())
+            .collect();
+        let inner = EvalContext {
+            ans: ctx.ans,
+            angle_mode: ctx.angle_mode,
+            variables: ctx.variables,
+            functions: ctx.functions,
+            locals: &locals,
+            depth: ctx.depth + 1,
+            call_budget: ctx.call_budget,
+            base: ctx.base,
+            word: ctx.word,
+            flags: ctx.flags,
+            complex: ctx.complex,
+        };
+        Self::evaluate_in(&func.postfix, &inner)
+    }
+
+    /// Parse and evaluate an expression against a context
+    pub fn calculate_in(input: &str, ctx: &EvalContext) -> Result<Value, CalcError> {
+        let tokens = Self::tokenize_with(input, ctx.base, &|name| ctx.functions.contains(name))?;
+        let postfix = Self::to_postfix(tokens)?;
+        Self::evaluate_in(&postfix, ctx)
     }
 }
 
@@ -312,10 +1264,20 @@ impl AlgebraicParser {
 pub struct AlgebraicState {
     /// Current input buffer
     input: String,
+    /// Insertion point, in characters from the start of the input
+    cursor: usize,
     /// Last result (Ans)
-    ans: f64,
-    /// Error message if any
-    error: Option<String>,
+    ans: Value,
+    /// Base for unprefixed literals
+    base: NumberBase,
+    /// Integer word results wrap to, if any
+    word: Option<WordSize>,
+    /// Whether real arguments may give complex results
+    complex: ComplexMode,
+    /// Carry and overflow from the last evaluation
+    flags: IntFlags,
+    /// Error from the last evaluation, if any
+    error: Option<CalcError>,
 }
 
 impl Default for AlgebraicState {
@@ -328,7 +1290,12 @@ impl AlgebraicState {
     pub fn new() -> Self {
         Self {
             input: String::new(),
-            ans: 0.0,
+            cursor: 0,
+            ans: Value::default(),
+            base: NumberBase::Decimal,
+            word: None,
+            complex: ComplexMode::Real,
+            flags: IntFlags::default(),
             error: None,
         }
     }
@@ -339,13 +1306,18 @@ impl AlgebraicState {
     }
 
     /// Get last answer
-    pub fn ans(&self) -> f64 {
+    pub fn ans(&self) -> Value {
         self.ans
     }
 
-    /// Get error if any
+    /// Get error message if any
     pub fn error(&self) -> Option<&str> {
-        self.error.as_deref()
+        self.error.as_ref().map(|e| e.detail())
+    }
+
+    /// Characters of the input the error refers to, if known
+    pub fn error_span(&self) -> Option<Span> {
+        self.error.as_ref().and_then(|e| e.span())
     }
 
     /// Clear error
@@ -353,56 +1325,282 @@ impl AlgebraicState {
         self.error = None;
     }
 
-    /// Add character to input
+    /// Get cursor position (in characters)
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Byte offset of a character position in the input
+    fn byte_index(&self, pos: usize) -> usize {
+        self.input
+            .char_indices()
+            .nth(pos)
+            .map_or(self.input.len(), |(i, _)| i)
+    }
+
+    /// Insert character at the cursor
     pub fn push(&mut self, c: char) {
         self.error = None;
-        self.input.push(c);
+        let at = self.byte_index(self.cursor);
+        self.input.insert(at, c);
+        self.cursor += 1;
     }
 
-    /// Add string to input
+    /// Insert string at the cursor
     pub fn push_str(&mut self, s: &str) {
         self.error = None;
-        self.input.push_str(s);
+        let at = self.byte_index(self.cursor);
+        self.input.insert_str(at, s);
+        self.cursor += s.chars().count();
     }
 
-    /// Remove last character
+    /// Remove the character before the cursor
     pub fn backspace(&mut self) {
         self.error = None;
-        self.input.pop();
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            let at = self.byte_index(self.cursor);
+            self.input.remove(at);
+        }
+    }
+
+    /// Remove the character under the cursor
+    pub fn delete(&mut self) {
+        self.error = None;
+        if self.cursor < self.input.chars().count() {
+            let at = self.byte_index(self.cursor);
+            self.input.remove(at);
+        }
+    }
+
+    /// Move the cursor one character left
+    pub fn cursor_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    /// Move the cursor one character right
+    pub fn cursor_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.input.chars().count());
+    }
+
+    /// Move the cursor to the start of the input
+    pub fn cursor_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Move the cursor to the end of the input
+    pub fn cursor_end(&mut self) {
+        self.cursor = self.input.chars().count();
     }
 
     /// Clear input
     pub fn clear(&mut self) {
         self.input.clear();
+        self.cursor = 0;
         self.error = None;
     }
 
     /// Clear all (input and ans)
     pub fn clear_all(&mut self) {
         self.input.clear();
-        self.ans = 0.0;
+        self.cursor = 0;
+        self.ans = Value::default();
         self.error = None;
     }
 
     /// Set ans directly (for memory recall etc)
-    pub fn set_ans(&mut self, value: f64) {
-        self.ans = value;
+    pub fn set_ans(&mut self, value: impl Into<Value>) {
+        self.ans = value.into();
+    }
+
+    /// Set the base for unprefixed literals (follows the display base)
+    pub fn set_base(&mut self, base: NumberBase) {
+        self.base = base;
+    }
+
+    /// Set the integer word results wrap to (`None` for real arithmetic)
+    pub fn set_word(&mut self, word: Option<WordSize>) {
+        self.word = word;
+    }
+
+    /// Set whether real arguments may give complex results
+    pub fn set_complex(&mut self, complex: ComplexMode) {
+        self.complex = complex;
+    }
+
+    /// Carry and overflow raised by the last evaluation in an integer word
+    pub fn flags(&self) -> IntFlags {
+        self.flags
+    }
+
+    /// Replace the input (for editing a stored definition etc)
+    pub fn set_input(&mut self, input: &str) {
+        self.input.clear();
+        self.input.push_str(input);
+        self.cursor = self.input.chars().count();
+        self.error = None;
+    }
+
+    /// Value the input would evaluate to, for a preview while typing
+    ///
+    /// Open parentheses and brackets are closed automatically. Nothing is
+    /// assigned or defined and `ans` is left alone; a lone number has no
+    /// preview.
+    pub fn preview(
+        &self,
+        angle_mode: AngleMode,
+        variables: &Variables,
+        functions: &UserFunctions,
+    ) -> Option<Value> {
+        let preview = |expr, skip_literal| {
+            self.preview_expression(expr, angle_mode, variables, functions, skip_literal)
+        };
+        match AlgebraicParser::parse_statement(&self.input).ok()? {
+            Statement::Expression(expr) | Statement::Assign { expr, .. } => preview(expr, true),
+            Statement::Convert { expr, to } => match AlgebraicParser::split_plain_unit(expr, self.word) {
+                Some((head, from)) => {
+                    let value = match head.trim() {
+                        "" => 1.0,
+                        _ => preview(head, false)?.number().ok()?.plain().ok()?,
+                    };
+                    match self.word {
+                        Some(_) => units::convert(value, from, to).ok().map(Value::from),
+                        None => Quantity::in_unit(value, from).and_then(|q| q.express_in(to)).ok().map(Value::from),
+                    }
+                }
+                None => match preview(expr, false)? {
+                    Value::Number(q) if !q.is_plain() => q.express_in(to).ok().map(Value::from),
+                    value => Some(value),
+                },
+            },
+            Statement::Define { .. } => None,
+        }
     }
 
-    /// Evaluate current expression
-    pub fn evaluate(&mut self, angle_mode: AngleMode) -> Option<f64> {
+    /// Preview one expression; `skip_literal` leaves a bare number unpreviewed
+    fn preview_expression(
+        &self,
+        expr: &str,
+        angle_mode: AngleMode,
+        variables: &Variables,
+        functions: &UserFunctions,
+        skip_literal: bool,
+    ) -> Option<Value> {
+        let mut open = Vec::new();
+        for c in expr.chars() {
+            match c {
+                '(' => open.push(')'),
+                '[' => open.push(']'),
+                ')' | ']' if open.pop() != Some(c) => return None,
+                _ => {}
+            }
+        }
+        let mut closed = String::from(expr);
+        closed.extend(open.iter().rev());
+
+        let tokens =
+            AlgebraicParser::tokenize_with(&closed, self.base, &|name| functions.contains(name))
+                .ok()?;
+        if skip_literal && matches!(tokens.as_slice(), [Spanned { token: Token::Number(_), .. }]) {
+            return None;
+        }
+        let postfix = AlgebraicParser::to_postfix(tokens).ok()?;
+        let budget = Cell::new(PREVIEW_CALL_BUDGET);
+        let mut ctx = EvalContext::new(self.ans, angle_mode, variables, functions);
+        ctx.call_budget = Some(&budget);
+        ctx.base = self.base;
+        ctx.word = self.word;
+        ctx.complex = self.complex;
+        AlgebraicParser::evaluate_in(&postfix, &ctx)
+            .ok()
+            .filter(|value| value.number().map_or(true, |q| q.complex().is_finite()))
+    }
+
+    /// Evaluate current input, performing any assignment or definition it
+    /// contains
+    pub fn evaluate(
+        &mut self,
+        angle_mode: AngleMode,
+        variables: &mut Variables,
+        functions: &mut UserFunctions,
+    ) -> Option<Outcome> {
         if self.input.is_empty() {
-            return Some(self.ans);
+            return Some(Outcome::Value(self.ans));
         }
 
-        match AlgebraicParser::calculate(&self.input, self.ans, angle_mode) {
-            Ok(result) => {
-                self.ans = result;
+        let ans = self.ans;
+        let base = self.base;
+        let word = self.word;
+        let complex = self.complex;
+        let flags = Cell::new(IntFlags::default());
+        let input = self.input.as_str();
+        // Error spans are relative to the part of the line being parsed
+        let locate = |part: &str, e: CalcError| match e.span() {
+            Some(span) => {
+                let offset = input[..part.as_ptr() as usize - input.as_ptr() as usize]
+                    .chars()
+                    .count();
+                e.at(span.shift(offset))
+            }
+            None => e,
+        };
+        let result = AlgebraicParser::parse_statement(input).and_then(|statement| {
+            let mut ctx = EvalContext::new(ans, angle_mode, variables, functions);
+            ctx.base = base;
+            ctx.word = word;
+            ctx.complex = complex;
+            ctx.flags = Some(&flags);
+            match statement {
+                Statement::Expression(expr) => AlgebraicParser::calculate_in(expr, &ctx)
+                    .map(Outcome::Value)
+                    .map_err(|e| locate(expr, e)),
+                Statement::Assign { name, expr } => {
+                    let value =
+                        AlgebraicParser::calculate_in(expr, &ctx).map_err(|e| locate(expr, e))?;
+                    variables.set(name, value)?;
+                    Ok(Outcome::Value(value))
+                }
+                Statement::Convert { expr, to } => {
+                    let calculate = |expr| AlgebraicParser::calculate_in(expr, &ctx).map_err(|e| locate(expr, e));
+                    if let Some((head, from)) = AlgebraicParser::split_plain_unit(expr, word) {
+                        let value = match head.trim() {
+                            "" => 1.0,
+                            _ => calculate(head)?.number()?.plain()?,
+                        };
+                        return match word {
+                            Some(_) => ctx.fit_result(units::convert(value, from, to)?).map(|v| Outcome::Value(v.into())),
+                            None => Ok(Outcome::Value(Quantity::in_unit(value, from)?.express_in(to)?.into())),
+                        };
+                    }
+                    match calculate(expr)? {
+                        Value::Number(q) if !q.is_plain() => q.express_in(to).map(|q| Outcome::Value(q.into())),
+                        _ => Err(CalcError::SyntaxError(alloc::format!(
+                            "{} is a unit: use = to assign",
+                            UNITS[to].name
+                        ))),
+                    }
+                }
+                Statement::Define { name, params, body } => {
+                    // The table stores the body trimmed
+                    functions
+                        .define(name, &params, body)
+                        .map_err(|e| locate(body.trim_start(), e))?;
+                    Ok(Outcome::Defined)
+                }
+            }
+        });
+
+        self.flags = flags.get();
+        match result {
+            Ok(outcome) => {
+                if let Outcome::Value(value) = outcome {
+                    self.ans = value;
+                }
                 self.error = None;
-                Some(result)
+                Some(outcome)
             }
             Err(e) => {
-                self.error = Some(String::from(e.message()));
+                self.error = Some(e);
                 None
             }
         }
@@ -464,4 +1662,674 @@ mod tests {
         let result = AlgebraicParser::calculate("ans+10", 5.0, AngleMode::Degrees).unwrap();
         assert_eq!(result, 15.0);
     }
+
+    #[test]
+    fn test_implicit_multiplication() {
+        use core::f64::consts::{E, PI};
+        let cases: &[(&str, f64)] = &[
+            ("2π", 2.0 * PI),
+            ("2pi", 2.0 * PI),
+            ("3(4+5)", 27.0),
+            ("(1+2)(3+4)", 21.0),
+            ("(2)3", 6.0),
+            ("2sin(30)", 1.0),
+            ("4ans", 20.0),
+            ("ans(2)", 10.0),
+            ("2e", 2.0 * E),
+            ("2e3", 2000.0),
+            ("2e-3", 0.002),
+            ("πe", PI * E),
+            // Binds tighter than explicit * and /
+            ("1/2π", 1.0 / (2.0 * PI)),
+            ("6/2(1+2)", 1.0),
+            ("6/2*(1+2)", 9.0),
+            // ...but looser than ^
+            ("2^3π", 8.0 * PI),
+            ("2π^2", 2.0 * PI * PI),
+            ("2(3)^2", 18.0),
+            // Unary minus sits between the two
+            ("-2^2", -4.0),
+            ("2^-1", 0.5),
+            ("-2π", -2.0 * PI),
+            ("3-2π", 3.0 - 2.0 * PI),
+            ("π-1", PI - 1.0),
+        ];
+        for &(expr, expected) in cases {
+            let result = AlgebraicParser::calculate(expr, 5.0, AngleMode::Degrees).unwrap();
+            assert!((result - expected).abs() < 1e-10, "{} = {}, expected {}", expr, result, expected);
+        }
+    }
+
+    #[test]
+    fn test_multi_argument_functions() {
+        let cases: &[(&str, f64)] = &[
+            ("atan2(1,1)", 45.0),
+            ("max(3,7,5)", 7.0),
+            ("min(3,7,5)", 3.0),
+            ("max(4)", 4.0),
+            ("log(8,2)", 3.0),
+            ("log(100)", 2.0),
+            ("root(27,3)", 3.0),
+            ("nCr(10,3)", 120.0),
+            ("nPr(5,2)", 20.0),
+            ("hypot(3,4)", 5.0),
+            ("max(1+2,2*2)", 4.0),
+            ("2max(1,3)", 6.0),
+        ];
+        for &(expr, expected) in cases {
+            let result = AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees).unwrap();
+            assert!((result - expected).abs() < 1e-10, "{} = {}, expected {}", expr, result, expected);
+        }
+
+        let errors = ["atan2(1)", "max()", "max(1,)", "hypot(,1)", "2(3,4)", "1,2", "2max(1,(2,3)"];
+        for expr in errors {
+            assert!(AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees).is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_postfix_operators() {
+        let cases: &[(&str, f64)] = &[
+            ("5!", 120.0),
+            ("3!+1", 7.0),
+            ("(1+2)!", 6.0),
+            ("2^3!", 64.0),
+            ("-3!", -6.0),
+            ("3!2", 12.0),
+            ("7!!", 105.0),
+            ("50%", 0.5),
+            ("50%+1", 1.5),
+            ("200*15%", 30.0),
+            ("(50)%", 0.5),
+            ("max(50%,1%)", 0.5),
+            ("7%3", 1.0),
+            ("7 % 3", 1.0),
+            ("7%(3)", 1.0),
+            ("3²", 9.0),
+            ("2³+1", 9.0),
+        ];
+        for &(expr, expected) in cases {
+            let result = AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees).unwrap();
+            assert!((result - expected).abs() < 1e-10, "{} = {}, expected {}", expr, result, expected);
+        }
+
+        assert!(AlgebraicParser::calculate("!5", 0.0, AngleMode::Degrees).is_err());
+    }
+
+    #[test]
+    fn test_variables_and_assignment() {
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        let mut run = |state: &mut AlgebraicState, input: &str| {
+            state.set_input(input);
+            state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs)
+        };
+
+        assert_eq!(run(&mut state, "r=2.5"), Some(Outcome::Value(2.5.into())));
+        let Some(Outcome::Value(area)) = run(&mut state, "πr^2") else {
+            panic!("πr^2 did not evaluate");
+        };
+        assert!((area.number().unwrap().value - core::f64::consts::PI * 6.25).abs() < 1e-10);
+
+        assert_eq!(run(&mut state, "5→A"), Some(Outcome::Value(5.0.into())));
+        assert_eq!(run(&mut state, "2a+r"), Some(Outcome::Value(12.5.into())));
+
+        // Unknown names and bad targets are errors; nothing is stored
+        assert_eq!(run(&mut state, "q+1"), None);
+        assert_eq!(run(&mut state, "sin=3"), None);
+        assert_eq!(vars.get("sin"), None);
+    }
+
+    #[test]
+    fn test_user_functions() {
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        let mut run = |state: &mut AlgebraicState, input: &str| {
+            state.set_input(input);
+            state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs)
+        };
+
+        assert_eq!(run(&mut state, "f(x)=x^2+3x-1"), Some(Outcome::Defined));
+        assert_eq!(run(&mut state, "area(w,h)=w*h"), Some(Outcome::Defined));
+        assert_eq!(run(&mut state, "f(2)"), Some(Outcome::Value(9.0.into())));
+        assert_eq!(run(&mut state, "area(3,4)+f(0)"), Some(Outcome::Value(11.0.into())));
+        assert_eq!(run(&mut state, "2f(1)"), Some(Outcome::Value(6.0.into())));
+
+        // Parameters shadow globals; other names see the globals
+        assert_eq!(run(&mut state, "x=10"), Some(Outcome::Value(10.0.into())));
+        assert_eq!(run(&mut state, "g(y)=x+y"), Some(Outcome::Defined));
+        assert_eq!(run(&mut state, "g(1)+f(1)"), Some(Outcome::Value(14.0.into())));
+
+        // Definitions may refer to functions defined later
+        assert_eq!(run(&mut state, "h(t)=k(t)+1"), Some(Outcome::Defined));
+        assert_eq!(run(&mut state, "k(t)=2t"), Some(Outcome::Defined));
+        assert_eq!(run(&mut state, "h(3)"), Some(Outcome::Value(7.0.into())));
+
+        assert_eq!(run(&mut state, "area(1)"), None);
+        assert_eq!(run(&mut state, "p(a,b,c,d,e)=a"), None);
+        assert_eq!(run(&mut state, "loop(n)=loop(n+1)"), Some(Outcome::Defined));
+        assert_eq!(run(&mut state, "loop(1)"), None);
+        assert_eq!(state.error(), Some(CalcError::RecursionLimit.message()));
+    }
+
+    #[test]
+    fn test_parse_statement() {
+        use Statement::*;
+        let parse = AlgebraicParser::parse_statement;
+        assert_eq!(parse("x = 2+3").unwrap(), Assign { name: "x", expr: " 2+3" });
+        assert_eq!(parse("2+3→x").unwrap(), Assign { name: "x", expr: "2+3" });
+        assert_eq!(parse("2+3").unwrap(), Expression("2+3"));
+        assert_eq!(
+            parse("f(x, y)=x*y").unwrap(),
+            Define { name: "f", params: alloc::vec!["x", "y"], body: "x*y" }
+        );
+        assert!(parse("x=").is_err());
+        assert!(parse("2+x=3").is_err());
+        assert!(parse("f(x,x)=x").is_err());
+        assert!(parse("sin(x)=x").is_err());
+        assert!(parse("2→f(x)").is_err());
+    }
+
+    #[test]
+    fn test_juxtaposed_literals_rejected() {
+        assert!(AlgebraicParser::calculate("2 3", 0.0, AngleMode::Degrees).is_err());
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let span_of = |expr: &str| {
+            AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees)
+                .unwrap_err()
+                .span()
+                .map(|span| (span.start, span.end))
+        };
+        assert_eq!(span_of("1+foo*2"), Some((2, 5)));
+        assert_eq!(span_of("2*(3+4"), Some((2, 3)));
+        assert_eq!(span_of("(1+2))"), Some((5, 6)));
+        assert_eq!(span_of("3+"), Some((1, 2)));
+        assert_eq!(span_of("max(1,,2)"), Some((6, 7)));
+        assert_eq!(span_of("2 3"), Some((2, 3)));
+        assert_eq!(span_of("1+2$"), Some((3, 4)));
+        assert_eq!(span_of("π+sqrt(-1)"), Some((2, 10)));
+
+        let err = AlgebraicParser::calculate("1+foo", 0.0, AngleMode::Degrees).unwrap_err();
+        assert_eq!(err.message(), "ERR: PARSE");
+        assert_eq!(err.detail(), "Unknown identifier: foo");
+
+        // Spans point into the whole line, past any assignment target
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        state.set_input("x = 1+y");
+        assert_eq!(state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs), None);
+        assert_eq!(state.error_span(), Some(Span::new(6, 7)));
+        state.set_input("f(a)= a+)");
+        assert_eq!(state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs), None);
+        assert_eq!(state.error_span(), Some(Span::new(8, 9)));
+    }
+
+    #[test]
+    fn test_cursor_editing() {
+        let mut state = AlgebraicState::new();
+        state.push_str("2+3");
+        state.cursor_home();
+        state.push('(');
+        state.cursor_end();
+        state.push(')');
+        state.push_str("π");
+        assert_eq!(state.input(), "(2+3)π");
+        assert_eq!(state.cursor(), 6);
+
+        // Edit around the multi-byte π
+        state.cursor_left();
+        state.backspace();
+        state.push('×');
+        assert_eq!(state.input(), "(2+3×π");
+        state.cursor_right();
+        state.cursor_right();
+        assert_eq!(state.cursor(), 6);
+
+        state.cursor_home();
+        state.delete();
+        state.cursor_right();
+        state.delete();
+        state.push('*');
+        assert_eq!(state.input(), "2*3×π");
+
+        state.backspace();
+        state.backspace();
+        state.backspace();
+        assert_eq!(state.input(), "3×π");
+        assert_eq!(state.cursor(), 0);
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        state.set_ans(10.0);
+        let preview = |state: &mut AlgebraicState, input: &str| {
+            state.set_input(input);
+            state.preview(AngleMode::Degrees, &vars, &funcs)
+        };
+
+        assert_eq!(preview(&mut state, ""), None);
+        assert_eq!(preview(&mut state, "42"), None);
+        assert_eq!(preview(&mut state, "2+3"), Some(5.0.into()));
+        assert_eq!(preview(&mut state, "2*(3+4"), Some(14.0.into()));
+        assert_eq!(preview(&mut state, "sqrt(sqrt(16"), Some(2.0.into()));
+        assert_eq!(preview(&mut state, "2+"), None);
+        assert_eq!(preview(&mut state, "(1+2))"), None);
+        assert_eq!(preview(&mut state, "1/0"), None);
+        assert_eq!(preview(&mut state, "ans/4"), Some(2.5.into()));
+        assert_eq!(preview(&mut state, "r=2*3"), Some(6.0.into()));
+        assert_eq!(preview(&mut state, "r"), None);
+
+        // Previewing has no side effects
+        assert_eq!(state.ans(), 10.0.into());
+        assert_eq!(vars.get("r"), None);
+        state.set_input("f(x)=x+1");
+        assert_eq!(state.preview(AngleMode::Degrees, &vars, &funcs), None);
+        assert!(funcs.is_empty());
+
+        // Runaway recursion is cut short by the call budget
+        state.set_input("fib(n)=fib(n-1)+fib(n-2)");
+        state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs);
+        state.set_input("fib(20");
+        assert_eq!(state.preview(AngleMode::Degrees, &vars, &funcs), None);
+    }
+
+    #[test]
+    fn test_base_literals() {
+        let vars = Variables::new();
+        let funcs = UserFunctions::new();
+        let calc = |base: NumberBase, expr: &str| {
+            let mut ctx = EvalContext::new(0.0, AngleMode::Degrees, &vars, &funcs);
+            ctx.base = base;
+            AlgebraicParser::calculate_in(expr, &ctx).ok().map(|v| v.number().unwrap().value)
+        };
+        use NumberBase::*;
+        let cases: &[(NumberBase, &str, Option<f64>)] = &[
+            // Prefixes work in any base
+            (Decimal, "0xFF+0b1010", Some(265.0)),
+            (Decimal, "0o17", Some(15.0)),
+            (Decimal, "2*0x10", Some(32.0)),
+            (Binary, "0xff", Some(255.0)),
+            (Hexadecimal, "0b11", Some(3.0)),
+            (Decimal, "0xFFFFFFFFFFFFFFFF", Some(u64::MAX as f64)),
+            // Bare digits follow the base
+            (Hexadecimal, "FF+1", Some(256.0)),
+            (Hexadecimal, "ff", Some(255.0)),
+            (Hexadecimal, "10", Some(16.0)),
+            (Hexadecimal, "2e", Some(46.0)),
+            (Hexadecimal, "2abs(-1)", Some(2.0)),
+            (Hexadecimal, "1.5", None),
+            (Octal, "17", Some(15.0)),
+            (Octal, "19", None),
+            (Binary, "101*11", Some(15.0)),
+            (Binary, "2", None),
+            (Decimal, "2e", Some(2.0 * core::f64::consts::E)),
+        ];
+        for &(base, expr, expected) in cases {
+            assert_eq!(calc(base, expr), expected, "{:?} {}", base, expr);
+        }
+
+        // A bare hex run too long for 64 bits is marked like other literals
+        let err = AlgebraicParser::tokenize_with("1+fffffffffffffffff", Hexadecimal, &|_| false).unwrap_err();
+        assert_eq!(err.span(), Some(Span::new(2, 19)));
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let cases: &[(&str, f64)] = &[
+            ("0xFF & 0b1010", 10.0),
+            ("1|2&3", 3.0),
+            ("1|6 xor 3", 5.0),
+            ("1<<4+1", 32.0),
+            ("2*3 & 7", 6.0),
+            ("6 XOR 3", 5.0),
+            ("~0", -1.0),
+            ("~5&0xF", 10.0),
+            ("-16 asr 2", -4.0),
+            ("-16>>60", 15.0),
+            ("1 rol 65", 2.0),
+            ("0xFFFFFFFFFFFFFFFF & 1", 1.0),
+        ];
+        for &(expr, expected) in cases {
+            let result = AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees);
+            assert_eq!(result.ok(), Some(expected), "{}", expr);
+        }
+        for expr in ["2.5&1", "1=2", "xor 3", "xor=1"] {
+            assert!(AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees).is_err(), "{}", expr);
+        }
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let cases: &[(&str, f64)] = &[
+            ("1<2", 1.0),
+            ("2<=1", 0.0),
+            ("3>=3", 1.0),
+            ("1+1==2", 1.0),
+            ("2≠2", 0.0),
+            // `!=` is always not-equal; factorial needs parentheses
+            ("4!=24", 1.0),
+            ("(4!)==24", 1.0),
+            ("1<2 and 2<1", 0.0),
+            ("0 or 3", 1.0),
+            ("not 1<0", 1.0),
+            ("not 0 and 0", 0.0),
+            ("1<<2>3", 1.0),
+            ("if(-2<0, 0, -2^2)", 0.0),
+            ("if(3<0, 0, 3^2)+1", 10.0),
+            ("2if(1, if(0, 5, 6), 7)", 12.0),
+            // Only the branch taken is evaluated
+            ("if(1, 1, 1/0)", 1.0),
+            ("if(0, sqrt(-1), 2)", 2.0),
+        ];
+        for &(expr, expected) in cases {
+            let result = AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees);
+            assert_eq!(result.ok(), Some(expected), "{}", expr);
+        }
+        for expr in ["if(1,2)", "if(1,2,3,4)", "if 1", "1<", "not"] {
+            assert!(AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees).is_err(), "{}", expr);
+        }
+
+        // `if` ends recursion in user functions
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        state.set_input("fact(n)=if(n<=1, 1, n*fact(n-1))");
+        assert_eq!(state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs), Some(Outcome::Defined));
+        state.set_input("fact(6)");
+        assert_eq!(state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs), Some(Outcome::Value(720.0.into())));
+    }
+
+    #[test]
+    fn test_physical_constants() {
+        let calc = |expr| AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees);
+        assert_eq!(calc("_c").ok(), Some(299_792_458.0));
+        assert_eq!(calc("2_g").ok(), Some(2.0 * 9.806_65));
+        assert_eq!(calc("_h/(2π)").ok(), Some(6.626_070_15e-34 / (2.0 * core::f64::consts::PI)));
+        assert!((calc("_kB _NA").unwrap() - 8.314_462_618).abs() < 1e-9);
+        assert_ne!(calc("_G").ok(), calc("_g").ok());
+        assert!(matches!(calc("_eps0*_mu0*_c^2"), Ok(x) if (x - 1.0).abs() < 1e-9));
+
+        // By symbol, where no unit is spelled the same
+        assert_eq!(calc("c").ok(), calc("_c").ok());
+        assert_eq!(calc("k_B N_A").ok(), calc("_kB _NA").ok());
+        assert_eq!(calc("G").ok(), calc("_G").ok());
+        assert_eq!(calc("ħ").ok(), calc("_hbar").ok());
+        assert_eq!(calc("ε0 μ0 c^2").ok(), calc("_eps0 _mu0 _c^2").ok());
+        assert_eq!(calc("2 g").ok(), Some(0.002));
+        assert_eq!(calc("h").ok(), Some(3600.0));
+        assert_eq!(calc("e").ok(), Some(core::f64::consts::E));
+        assert!(calc("k_").is_err());
+        // and no variable
+        let mut vars = Variables::new();
+        vars.set("c", 3.0).unwrap();
+        let funcs = UserFunctions::new();
+        let ctx = EvalContext::new(0.0, AngleMode::Degrees, &vars, &funcs);
+        assert_eq!(AlgebraicParser::calculate_in("2c", &ctx).ok(), Some(6.0.into()));
+
+        // Unknown constants are located
+        let err = AlgebraicParser::tokenize("1+_kx").unwrap_err();
+        assert_eq!(err.span(), Some(Span::new(2, 5)));
+        assert!(calc("_").is_err());
+
+        // Not read as hex digits in HEX
+        let tokens = AlgebraicParser::tokenize_with("_e", NumberBase::Hexadecimal, &|_| false).unwrap();
+        assert!(matches!(tokens[0].token, Token::Function(Func::Constant(_))));
+    }
+
+    #[test]
+    fn test_unit_conversion() {
+        let calc = |expr| AlgebraicParser::calculate(expr, 0.0, AngleMode::Degrees);
+        let near = |expr, expected: f64| {
+            let value = calc(expr).unwrap();
+            assert!((value - expected).abs() < 1e-9 * expected.abs(), "{}: {}", expr, value);
+        };
+        near("convert(100, degF, degC)", 37.777_777_777_8);
+        near("2convert(1, ft, in)+1", 25.0);
+        near("convert(convert(1, mi, ft), ft, m)", 1609.344);
+        near("convert(1, MIN, s)", 60.0);
+        for expr in ["convert(1, ft, kg)", "convert(1, ft)", "convert(1, 2, m)", "convert(1, ft*2, m)", "convert(1, ft, furlong)"] {
+            assert!(calc(expr).is_err(), "{}", expr);
+        }
+
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        let mut run = |state: &mut AlgebraicState, input: &str| {
+            state.set_input(input);
+            state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs)
+        };
+        let mut near = |state: &mut AlgebraicState, input: &str, expected: f64| match run(state, input) {
+            Some(Outcome::Value(v)) => {
+                assert!((v.number().unwrap().shown() - expected).abs() < 1e-9 * expected, "{}: {:?}", input, v)
+            }
+            outcome => panic!("{}: {:?}", input, outcome),
+        };
+        near(&mut state, "5ft→m", 1.524);
+        near(&mut state, "(2+3) kg → g", 5000.0);
+        near(&mut state, "ans→kg", 5.0);
+        near(&mut state, "atm→psi", 14.695_948_775_5);
+        assert_eq!(run(&mut state, "1m→s"), None);
+        // A plain value arrowed to a unit name would hide the unit
+        assert_eq!(run(&mut state, "5→m"), None);
+        assert_eq!(state.error(), Some("m is a unit: use = to assign"));
+        assert_eq!(run(&mut state, "[[1,2],[3,4]]→m"), None);
+        assert_eq!(state.error(), Some("m is a unit: use = to assign"));
+        assert!(matches!(run(&mut state, "3 m→x"), Some(Outcome::Value(Value::Number(q))) if q.value == 3.0 && !q.is_plain()));
+
+        state.set_input("100degC→degF");
+        let preview = state.preview(AngleMode::Degrees, &Variables::new(), &UserFunctions::new());
+        assert!(preview.is_some_and(|v| (v.number().unwrap().shown() - 212.0).abs() < 1e-9));
+        assert!(vars.get("m").is_none());
+    }
+
+    #[test]
+    fn test_quantities() {
+        use crate::quantity::Dims;
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        let mut run = |input: &str| {
+            state.set_input(input);
+            match state.evaluate(AngleMode::Radians, &mut vars, &mut funcs) {
+                Some(Outcome::Value(v)) => Ok(v.number().unwrap()),
+                Some(Outcome::Defined) => Ok(Quantity::default()),
+                None => Err(String::from(state.error().unwrap_or_default())),
+            }
+        };
+        let mut check = |input: &str, shown: f64, label: &str| {
+            let q = run(input).unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert!((q.shown() - shown).abs() < 1e-9 * shown.abs().max(1.0), "{}: {:?}", input, q);
+            assert_eq!(q.label(), label, "{}", input);
+        };
+
+        check("3 m * 2 s^-1", 6.0, "m/s");
+        check("6 m / 2 s", 3.0, "m/s");
+        check("3 ft + 2 ft", 5.0, "ft");
+        check("2 * 3 ft", 6.0, "ft");
+        check("1 ft + 12 in", 0.6096, "m");
+        check("10 kg * 9.81 m/s^2", 98.1, "N");
+        check("2 kg * (3 m/s)^2 / 2", 9.0, "J");
+        check("sqrt(16 m^2)", 4.0, "m");
+        check("(2 m)^3", 8.0, "m³");
+        check("1/(2 s)", 0.5, "1/s");
+        check("6 m / 2 m", 3.0, "");
+        check("sin(90 deg)", 1.0, "");
+        check("-(3 ft)", -3.0, "ft");
+        check("6 m / 2 s → kmh", 10.8, "kmh");
+        check("si(5 ft)", 1.524, "m");
+        check("1 kg > 500 g", 1.0, "");
+        check("if(2 m < 1 yd, 1 m, 2 m)", 2.0, "m");
+        // `min` is a minute unless called
+        check("5min→s", 300.0, "s");
+        check("1 min→s", 60.0, "s");
+        check("min(3, 4)", 3.0, "");
+        check("min (3, 2)", 2.0, "");
+
+        // Constants carry their units
+        check("10 kg * _g", 98.0665, "N");
+        check("1 kg*_c^2", 8.987_551_787_368_176e16, "J");
+        check("_e", 1.602_176_634e-19, "C");
+        check("k_B N_A / _R", 1.0, "");
+
+        // Readings on a shifted scale do not survive arithmetic
+        check("100 degC→degF", 212.0, "degF");
+        check("ans+ans", 746.3, "K");
+        check("100 degC→degF", 212.0, "degF");
+        check("ans*2", 746.3, "K");
+
+        // Variables and user function parameters carry quantities
+        check("d = 100 m", 100.0, "m");
+        check("d / 10 s", 10.0, "m/s");
+        check("v(x, t) = x/t", 0.0, "");
+        check("v(1 mi, 1 h) → mph", 1.0, "mph");
+        check("ans→kn", 0.868_976_241_9, "kn");
+
+        for bad in ["5 m + 3 s", "1 kg > 1 m", "2^(1 s)", "(2 m)^0.5", "max(1 m, 2 m)", "ln(2 s)", "2 degC", "3 m → s", "5 m & 1"] {
+            let err = run(bad).expect_err(bad);
+            assert!(!err.is_empty(), "{}", bad);
+        }
+        assert_eq!(run("5 m + 3 s"), Err(String::from("dimension mismatch")));
+
+        // Plain values are untouched by the machinery
+        assert_eq!(run("2+3"), Ok(Quantity::new(5.0, Dims::NONE)));
+    }
+
+    #[test]
+    fn test_complex_numbers() {
+        use crate::complex::Complex;
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        let mut run = |input: &str, complex: ComplexMode| {
+            state.set_input(input);
+            state.set_complex(complex);
+            match state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs) {
+                Some(Outcome::Value(v)) => Ok(v.number().unwrap().complex()),
+                Some(Outcome::Defined) => Ok(Complex::ZERO),
+                None => Err(String::from(state.error().unwrap_or_default())),
+            }
+        };
+        let rect = ComplexMode::Rectangular;
+        let cases: &[(&str, f64, f64)] = &[
+            ("sqrt(-4)", 0.0, 2.0),
+            ("3+4i", 3.0, 4.0),
+            ("2i*i", -2.0, 0.0),
+            ("(1+i)^2", 0.0, 2.0),
+            ("abs(3+4i)", 5.0, 0.0),
+            ("(3+4i)/(1+2i)", 2.2, -0.4),
+            ("2∠90", 0.0, 2.0),
+            ("1∠45 * 1∠45", 0.0, 1.0),
+            ("2∠0.5 turn", -2.0, 0.0),
+            ("arg(-1)", 180.0, 0.0),
+            ("ln(-1)", 0.0, core::f64::consts::PI),
+            ("z = 1-i", 1.0, -1.0),
+            ("conj(z) + im(z)", 0.0, 1.0),
+        ];
+        for &(input, re, im) in cases {
+            let z = run(input, rect).unwrap_or_else(|e| panic!("{}: {}", input, e));
+            assert!((z.re - re).abs() < 1e-9 && (z.im - im).abs() < 1e-9, "{}: {:?}", input, z);
+        }
+
+        // Real only mode keeps real results real, but accepts entered complex values
+        assert_eq!(run("sqrt(-4)", ComplexMode::Real), Err(String::from("sqrt domain [0,∞)")));
+        assert_eq!(run("sqrt(2i)", ComplexMode::Real).map(|z| z.re.round()), Ok(1.0));
+        assert!(run("i!", rect).is_err());
+        assert!(run("i < 1", rect).is_err());
+        assert!(run("i = 2", rect).is_err());
+    }
+
+    #[test]
+    fn test_matrices() {
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        let mut run = |input: &str| {
+            state.set_input(input);
+            match state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs) {
+                Some(Outcome::Value(v)) => Ok(v.to_source()),
+                Some(Outcome::Defined) => Ok(String::new()),
+                None => Err(String::from(state.error().unwrap_or_default())),
+            }
+        };
+        let cases: &[(&str, &str)] = &[
+            ("[[1,2],[3,4]]", "[[1,2],[3,4]]"),
+            ("[1, 2+1, sqrt(16)]", "[[1,3,4]]"),
+            ("[[1,2],[3,4]]*[[5,6],[7,8]]", "[[19,22],[43,50]]"),
+            ("[[1,2],[3,4]] + 2*identity(2)", "[[3,2],[3,6]]"),
+            ("2[[1,2],[3,4]]", "[[2,4],[6,8]]"),
+            ("1/[[2,0],[0,4]]", "[[0.5,0],[0,0.25]]"),
+            ("[[1,2],[3,4]]/2 - [[0,1],[1,2]]", "[[0.5,0],[0.5,0]]"),
+            ("det([[1,2],[3,4]])", "-2"),
+            ("inv([[2,0],[0,4]])", "[[0.5,0],[0,0.25]]"),
+            ("[[2,0],[0,4]]^-1", "[[0.5,0],[0,0.25]]"),
+            ("transpose([1,2,3])", "[[1],[2],[3]]"),
+            ("[[1,2],[3,4]]*[1,1]", "[[3,7]]"),
+            ("dot([1,2,3],[4,5,6])", "32"),
+            ("cross([1,0,0],[0,1,0])", "[[0,0,1]]"),
+            ("norm([3,4])", "5"),
+            ("m = [[1,2],[2,4]]", "[[1,2],[2,4]]"),
+            ("rref(m)", "[[1,2],[0,0]]"),
+            ("det(ans) + 1", "1"),
+        ];
+        for &(input, expected) in cases {
+            assert_eq!(run(input).as_deref(), Ok(expected), "{}", input);
+        }
+
+        assert_eq!(run("[[1,2],[3]]"), Err(String::from("rows differ in length")));
+        assert_eq!(run("[1,2)"), Err(String::from("Mismatched brackets")));
+        assert_eq!(run("(1+2]"), Err(String::from("Mismatched brackets")));
+        assert_eq!(run("[]"), Err(String::from("Missing argument")));
+        assert_eq!(run("inv(m)"), Err(String::from("singular matrix")));
+        assert_eq!(run("sin(m)"), Err(String::from("not defined for matrices")));
+        assert_eq!(run("[1,2]+[1,2,3]"), Err(String::from("matrix sizes differ")));
+    }
+
+    #[test]
+    fn test_word_wrapping() {
+        use crate::functions::SignMode;
+        let mut vars = Variables::new();
+        let mut funcs = UserFunctions::new();
+        let mut state = AlgebraicState::new();
+        state.set_base(NumberBase::Hexadecimal);
+        let mut calc = |word: WordSize, expr: &str| {
+            state.set_word(Some(word));
+            state.set_input(expr);
+            match state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs) {
+                Some(Outcome::Value(v)) => Some(v.number().unwrap().value),
+                _ => None,
+            }
+        };
+        let u8w = WordSize::new(8, SignMode::Unsigned);
+        let s8 = WordSize::new(8, SignMode::TwosComplement);
+        assert_eq!(calc(u8w, "FF+1"), Some(0.0));
+        assert_eq!(calc(u8w, "0-1"), Some(255.0));
+        assert_eq!(calc(u8w, "~0"), Some(255.0));
+        assert_eq!(calc(u8w, "80 rol 1"), Some(1.0));
+        assert_eq!(calc(u8w, "7/2"), Some(3.0));
+        assert_eq!(calc(s8, "FF"), Some(-1.0));
+        assert_eq!(calc(s8, "7F+1"), Some(-128.0));
+        assert_eq!(calc(s8, "-80 >> 4"), Some(8.0));
+        assert_eq!(calc(WordSize::new(16, SignMode::Unsigned), "FF+1"), Some(256.0));
+
+        // Flags collect over the whole expression
+        let mut flagged = |expr: &str| {
+            state.set_word(Some(s8));
+            state.set_input(expr);
+            state.evaluate(AngleMode::Degrees, &mut vars, &mut funcs);
+            (state.ans().number().unwrap().value, state.flags())
+        };
+        let flags = |carry, overflow| IntFlags { carry, overflow };
+        assert_eq!(flagged("7/2*2"), (6.0, flags(true, false)));
+        assert_eq!(flagged("40*2+1"), (-127.0, flags(false, true)));
+        assert_eq!(flagged("8/2"), (4.0, flags(false, false)));
+    }
 }
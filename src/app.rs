@@ -1,13 +1,23 @@
 //! CalcApp - main application state and mode dispatch
 
-use crate::algebraic::AlgebraicState;
-use crate::display::{format_number, format_stack_number, History, HistoryEntry};
-use crate::functions::{AngleMode, Func, NumberBase, Op};
+use crate::algebraic::{AlgebraicParser, AlgebraicState, EvalContext, Outcome};
+use crate::bitfield::{BitInspector, Reading, GRID_ROW_BITS};
+use crate::constants::CONSTANTS;
+use crate::display::{binary_rows, format_complex, format_float_fields, format_cell, format_number, format_stack_number, format_value, History, HistoryEntry, BINARY_ROW_BITS};
+use crate::equation::{EquationSolver, Field};
+use crate::functions::{AngleMode, CalcError, ComplexMode, Func, IntFlags, NumberBase, Op, SignMode, WordSize};
 use crate::keymap::{get_menu_items, KeyAction, KeyState};
-use crate::memory::Memory;
+use crate::linsys::LinearSystem;
+use crate::matrix::{Matrix, MatrixEditor};
+use crate::memory::{Memory, Variables};
+use crate::poly::PolySolver;
+use crate::quantity::Quantity;
 use crate::rpn::RpnStack;
-use crate::storage::{Settings, Storage};
+use crate::storage::{LegacyValues, Settings, Storage, VERSION};
+use crate::units::{self, Dimension, UNITS};
 use crate::ui;
+use crate::userfunc::UserFunctions;
+use crate::value::Value;
 
 use alloc::string::String;
 use alloc::vec::Vec;
@@ -28,14 +38,78 @@ pub enum CalcState {
     FnMenu(u8),
     WaitingStore,
     WaitingRecall,
+    /// Typing a variable name after STO
+    StoreName,
+    /// Typing a variable name after RCL
+    RecallName,
+    /// Browsing user-defined functions (page of ten)
+    UserFnMenu(u8),
+    /// Choosing what to do with the selected user function
+    UserFnAction(u8),
+    /// Browsing the history tape (0 = newest entry)
+    History(usize),
+    /// Full bit pattern of the current value, scrolled to a row
+    BinaryView(usize),
+    /// Editing the bits of the current value
+    BitInspector,
+    /// Browsing physical constants (page of ten)
+    ConstMenu(u8),
+    /// Description of the selected constant
+    ConstInfo(u8),
+    /// Picking the unit to convert from, then to (indices into `UNITS`)
+    UnitPicker(Dimension, Option<usize>),
+    /// Editing a matrix in the grid; true if it goes back to X (RPN)
+    MatrixEditor(bool),
+    /// Current matrix full-screen, scrolled to a column
+    MatrixView(usize),
+    /// Choosing the size of a linear system or polynomial
+    SizePrompt(Solver),
+    /// Filling in a linear system, or reading its solution
+    LinearSystem,
+    /// Filling in a polynomial's coefficients, or browsing its roots
+    Polynomial,
+    /// Typing an equation and where to start, or reading its root
+    Equation,
 }
 
+/// Solver screens that open by asking for a size
+#[derive(Clone, Copy, PartialEq)]
+pub enum Solver {
+    /// Number of unknowns, 2-6
+    LinearSystem,
+    /// Degree, 1-9 or 0 for 10
+    Polynomial,
+}
+
+/// User functions listed per page of the browser (keys 1-9, 0)
+const USER_FN_PAGE: usize = 10;
+
+/// Constants listed per page of the CONST menu (keys 1-9, 0)
+const CONST_PAGE: usize = 10;
+
+/// History entries shown on the tape
+const HISTORY_LINES: usize = 10;
+
+/// Bits per row of the full binary view
+const BINARY_VIEW_ROW_BITS: u32 = 8;
+
+/// Characters a matrix may take in the algebraic result area before it is
+/// shown as its size
+const RESULT_WIDTH: usize = 30;
+
 /// Main calculator application
 pub struct CalcApp {
     // Mode and settings
     mode: CalcMode,
     angle_mode: AngleMode,
+    /// Whether real arguments may give complex results, and how complex
+    /// values are shown
+    complex_mode: ComplexMode,
     number_base: NumberBase,
+    /// Integer word used in the non-decimal bases
+    word: WordSize,
+    /// Carry and overflow from the last RPN integer operation
+    rpn_flags: IntFlags,
 
     // State
     state: CalcState,
@@ -47,8 +121,25 @@ pub struct CalcApp {
 
     // Shared
     memory: Memory,
+    variables: Variables,
+    functions: UserFunctions,
+    /// Variable name being typed after STO/RCL
+    name_buffer: String,
+    /// Value open in the bit-field inspector
+    inspector: Option<BitInspector>,
+    /// Matrix open in the grid editor
+    editor: Option<MatrixEditor>,
+    /// System open in the linear solver
+    system: Option<LinearSystem>,
+    /// Polynomial open in the root finder
+    poly: Option<PolySolver>,
+    /// Equation open in the solver
+    equation: Option<EquationSolver>,
     history: History,
     error: Option<String>,
+    /// Confirmation shown on a solver screen, e.g. which register was
+    /// written
+    notice: Option<String>,
 
     // Storage
     storage: Storage,
@@ -57,7 +148,7 @@ pub struct CalcApp {
 impl CalcApp {
     pub fn new() -> Self {
         let storage = Storage::new();
-        let settings = storage.load();
+        let mut settings = storage.load();
 
         let mode = if settings.is_rpn() {
             CalcMode::Rpn
@@ -66,22 +157,48 @@ impl CalcApp {
         };
 
         let mut memory = Memory::new();
-        memory.set_all(settings.memory);
+        memory.set_all(settings.registers);
+
+        let mut variables = Variables::new();
+        variables.set_all(core::mem::take(&mut settings.variable_values));
 
+        let mut functions = UserFunctions::new();
+        functions.set_defs(core::mem::take(&mut settings.functions));
+
+        let number_base = settings.get_number_base();
+        let complex_mode = settings.get_complex_mode();
         let mut algebraic = AlgebraicState::new();
-        algebraic.set_ans(settings.ans);
+        algebraic.set_ans(settings.ans_value);
+        algebraic.set_base(number_base);
+        algebraic.set_complex(complex_mode);
+        let word = settings.get_word_size();
+        if number_base != NumberBase::Decimal {
+            algebraic.set_word(Some(word));
+        }
 
         Self {
             mode,
             angle_mode: settings.get_angle_mode(),
-            number_base: settings.get_number_base(),
+            complex_mode,
+            number_base,
+            word,
+            rpn_flags: IntFlags::default(),
             state: CalcState::Normal,
             key_state: KeyState::new(),
             algebraic,
             rpn: RpnStack::new(),
             memory,
+            variables,
+            functions,
+            name_buffer: String::new(),
+            inspector: None,
+            editor: None,
+            system: None,
+            poly: None,
+            equation: None,
             history: History::new(50),
             error: None,
+            notice: None,
             storage,
         }
     }
@@ -89,11 +206,18 @@ impl CalcApp {
     /// Save current state to PDDB
     pub fn save_state(&self) {
         let settings = Settings {
+            version: VERSION,
             mode: if self.mode == CalcMode::Rpn { 1 } else { 0 },
             angle_mode: self.angle_mode.to_u8(),
             number_base: self.number_base.to_u8(),
-            memory: *self.memory.get_all(),
-            ans: self.algebraic.ans(),
+            registers: *self.memory.get_all(),
+            ans_value: self.algebraic.ans(),
+            variable_values: self.variables.get_all().clone(),
+            legacy: LegacyValues::default(),
+            functions: self.functions.to_defs(),
+            word_bits: self.word.bits as u8,
+            sign_mode: self.word.sign.to_u8(),
+            complex_mode: self.complex_mode.to_u8(),
         };
         self.storage.save(&settings);
     }
@@ -104,10 +228,23 @@ impl CalcApp {
         match self.state {
             CalcState::WaitingStore => {
                 if let Some(digit) = c.to_digit(10) {
-                    let value = self.current_value();
-                    self.memory.store(digit as usize, value);
+                    // Registers hold plain numbers or matrices; variables
+                    // also take quantities
+                    let value = match self.current_result() {
+                        Value::Number(q) => q.plain_complex().map(Value::from),
+                        m => Ok(m),
+                    };
+                    match value {
+                        Ok(value) => {
+                            self.memory.store(digit as usize, value);
+                        }
+                        Err(e) => self.error = Some(String::from(e.message())),
+                    }
                     self.state = CalcState::Normal;
                     return true;
+                } else if c.is_ascii_alphabetic() {
+                    self.start_name_entry(c, CalcState::StoreName);
+                    return true;
                 } else {
                     self.state = CalcState::Normal;
                     return true;
@@ -120,11 +257,74 @@ impl CalcApp {
                     }
                     self.state = CalcState::Normal;
                     return true;
+                } else if c.is_ascii_alphabetic() {
+                    self.start_name_entry(c, CalcState::RecallName);
+                    return true;
                 } else {
                     self.state = CalcState::Normal;
                     return true;
                 }
             }
+            CalcState::StoreName | CalcState::RecallName => {
+                self.handle_name_key(c);
+                return true;
+            }
+            CalcState::UserFnMenu(page) => {
+                self.handle_user_fn_menu_key(page, c);
+                return true;
+            }
+            CalcState::UserFnAction(index) => {
+                self.handle_user_fn_action_key(index as usize, c);
+                return true;
+            }
+            CalcState::ConstMenu(page) => {
+                self.handle_const_menu_key(page, c);
+                return true;
+            }
+            CalcState::ConstInfo(index) => {
+                self.handle_const_info_key(index as usize, c);
+                return true;
+            }
+            CalcState::UnitPicker(dimension, from) => {
+                self.handle_unit_picker_key(dimension, from, c);
+                return true;
+            }
+            CalcState::History(age) => {
+                self.handle_history_key(age, c);
+                return true;
+            }
+            CalcState::BinaryView(first) => {
+                self.handle_binary_view_key(first, c);
+                return true;
+            }
+            CalcState::BitInspector => {
+                self.handle_bit_inspector_key(c);
+                return true;
+            }
+            CalcState::MatrixEditor(to_x) => {
+                self.handle_matrix_editor_key(to_x, c);
+                return true;
+            }
+            CalcState::MatrixView(first) => {
+                self.handle_matrix_view_key(first, c);
+                return true;
+            }
+            CalcState::SizePrompt(solver) => {
+                self.handle_size_key(solver, c);
+                return true;
+            }
+            CalcState::LinearSystem => {
+                self.handle_linear_system_key(c);
+                return true;
+            }
+            CalcState::Polynomial => {
+                self.handle_polynomial_key(c);
+                return true;
+            }
+            CalcState::Equation => {
+                self.handle_equation_key(c);
+                return true;
+            }
             CalcState::FnMenu(menu) => {
                 if let Some(digit) = c.to_digit(10) {
                     let action = crate::keymap::map_fn_menu_key(menu, digit as u8);
@@ -183,10 +383,61 @@ impl CalcApp {
                 }
                 true
             }
+            KeyAction::OpenBracket => {
+                if self.mode == CalcMode::Algebraic {
+                    self.algebraic.push('[');
+                }
+                true
+            }
+            KeyAction::CloseBracket => {
+                if self.mode == CalcMode::Algebraic {
+                    self.algebraic.push(']');
+                }
+                true
+            }
+            KeyAction::Comma => {
+                if self.mode == CalcMode::Algebraic {
+                    self.algebraic.push(',');
+                }
+                true
+            }
             KeyAction::Execute => {
                 self.execute();
                 true
             }
+            KeyAction::Equals => {
+                // `=` after `<`, `>`, `!` or `=` completes a comparison
+                let before_cursor = self
+                    .algebraic
+                    .cursor()
+                    .checked_sub(1)
+                    .and_then(|i| self.algebraic.input().chars().nth(i));
+                if self.mode == CalcMode::Algebraic
+                    && (AlgebraicParser::is_assignment_target(self.algebraic.input())
+                        || matches!(before_cursor, Some('<' | '>' | '!' | '=')))
+                {
+                    self.algebraic.push('=');
+                } else {
+                    self.execute();
+                }
+                true
+            }
+            KeyAction::Delete
+            | KeyAction::CursorLeft
+            | KeyAction::CursorRight
+            | KeyAction::CursorHome
+            | KeyAction::CursorEnd => {
+                if self.mode == CalcMode::Algebraic {
+                    self.edit_input(action);
+                }
+                true
+            }
+            KeyAction::Assign => {
+                if self.mode == CalcMode::Algebraic {
+                    self.algebraic.push('→');
+                }
+                true
+            }
             KeyAction::Backspace => {
                 self.backspace();
                 true
@@ -215,8 +466,25 @@ impl CalcApp {
                 self.angle_mode = self.angle_mode.cycle();
                 true
             }
+            KeyAction::CycleComplex => {
+                self.complex_mode = self.complex_mode.cycle();
+                self.algebraic.set_complex(self.complex_mode);
+                true
+            }
             KeyAction::CycleBase => {
                 self.number_base = self.number_base.cycle();
+                self.algebraic.set_base(self.number_base);
+                self.algebraic.set_word(self.integer_word());
+                true
+            }
+            KeyAction::CycleWordSize => {
+                self.word = self.word.cycle_bits();
+                self.algebraic.set_word(self.integer_word());
+                true
+            }
+            KeyAction::CycleSignMode => {
+                self.word.sign = self.word.sign.cycle();
+                self.algebraic.set_word(self.integer_word());
                 true
             }
             KeyAction::SwapXY => {
@@ -255,6 +523,78 @@ impl CalcApp {
                 self.state = CalcState::FnMenu(n);
                 true
             }
+            KeyAction::UserFunctions => {
+                self.state = CalcState::UserFnMenu(0);
+                true
+            }
+            KeyAction::Constants => {
+                self.state = CalcState::ConstMenu(0);
+                true
+            }
+            KeyAction::ConvertUnits(dimension) => {
+                self.state = CalcState::UnitPicker(dimension, None);
+                true
+            }
+            KeyAction::BrowseHistory => {
+                if !self.history.is_empty() {
+                    self.state = CalcState::History(0);
+                }
+                true
+            }
+            KeyAction::BinaryView | KeyAction::BitInspector if self.current_result().matrix().is_some() => {
+                if let Err(e) = self.current_result().number() {
+                    self.error = Some(String::from(e.message()));
+                }
+                true
+            }
+            KeyAction::BinaryView => {
+                if self.word.to_pattern(self.current_value().trunc()).is_ok() {
+                    self.state = CalcState::BinaryView(0);
+                } else {
+                    self.error = Some(String::from(CalcError::Overflow.message()));
+                }
+                true
+            }
+            KeyAction::BitInspector => {
+                self.inspector = Some(BitInspector::new(self.current_value(), self.word));
+                self.state = CalcState::BitInspector;
+                true
+            }
+            KeyAction::MatrixEditor => {
+                // Edit the current matrix, or start a new 2×2 one
+                let opened = match self.current_result() {
+                    Value::Matrix(m) => Ok((m, self.mode == CalcMode::Rpn)),
+                    Value::Number(_) => Matrix::zeros(2, 2).map(|m| (m, false)),
+                };
+                match opened {
+                    Ok((matrix, to_x)) => {
+                        self.editor = Some(MatrixEditor::new(matrix));
+                        self.state = CalcState::MatrixEditor(to_x);
+                    }
+                    Err(e) => self.error = Some(String::from(e.message())),
+                }
+                true
+            }
+            KeyAction::MatrixView => {
+                match self.current_result() {
+                    Value::Matrix(_) => self.state = CalcState::MatrixView(0),
+                    Value::Number(_) => self.error = Some(String::from(CalcError::DomainError("needs a matrix").message())),
+                }
+                true
+            }
+            KeyAction::LinearSystem => {
+                self.state = CalcState::SizePrompt(Solver::LinearSystem);
+                true
+            }
+            KeyAction::Polynomial => {
+                self.state = CalcState::SizePrompt(Solver::Polynomial);
+                true
+            }
+            KeyAction::Equation => {
+                self.equation = Some(EquationSolver::new());
+                self.state = CalcState::Equation;
+                true
+            }
             KeyAction::Cancel => {
                 self.state = CalcState::Normal;
                 self.key_state.reset();
@@ -265,21 +605,645 @@ impl CalcApp {
         }
     }
 
-    /// Get current display value
+    /// Begin typing a variable name for STO/RCL
+    fn start_name_entry(&mut self, first: char, state: CalcState) {
+        self.name_buffer.clear();
+        self.name_buffer.push(first);
+        self.state = state;
+    }
+
+    /// Handle a key while typing a variable name
+    fn handle_name_key(&mut self, c: char) {
+        match c {
+            '\r' | '\n' => {
+                let name = core::mem::take(&mut self.name_buffer);
+                if self.state == CalcState::StoreName {
+                    let value = self.current_result();
+                    if let Err(e) = self.variables.set(&name, value) {
+                        self.error = Some(String::from(e.message()));
+                    }
+                } else if let Some(value) = self.variables.get(&name) {
+                    self.insert_value(value);
+                } else {
+                    self.error = Some(String::from("ERR: UNDEFINED"));
+                }
+                self.state = CalcState::Normal;
+            }
+            '\u{0008}' => {
+                self.name_buffer.pop();
+                if self.name_buffer.is_empty() {
+                    self.state = CalcState::Normal;
+                }
+            }
+            c if c.is_ascii_alphanumeric() => self.name_buffer.push(c),
+            _ => {
+                // ESC or anything else cancels
+                self.name_buffer.clear();
+                self.state = CalcState::Normal;
+            }
+        }
+    }
+
+    /// Handle a key in the user function browser
+    fn handle_user_fn_menu_key(&mut self, page: u8, c: char) {
+        let pages = self.functions.len().div_ceil(USER_FN_PAGE).max(1);
+        match c {
+            '↓' | '→' if (page as usize) + 1 < pages => {
+                self.state = CalcState::UserFnMenu(page + 1);
+            }
+            '↑' | '←' if page > 0 => {
+                self.state = CalcState::UserFnMenu(page - 1);
+            }
+            _ => {
+                if let Some(digit) = c.to_digit(10) {
+                    // Keys 1-9 then 0 select the entries on this page
+                    let slot = if digit == 0 { 9 } else { digit as usize - 1 };
+                    let index = page as usize * USER_FN_PAGE + slot;
+                    if index < self.functions.len() {
+                        self.state = CalcState::UserFnAction(index as u8);
+                    }
+                } else if c == '\u{001B}' || c == '∴' {
+                    self.state = CalcState::Normal;
+                }
+            }
+        }
+    }
+
+    /// Handle a key after selecting a user function
+    fn handle_user_fn_action_key(&mut self, index: usize, c: char) {
+        let page = (index / USER_FN_PAGE) as u8;
+        let Some(func) = self.functions.iter().nth(index) else {
+            self.state = CalcState::UserFnMenu(0);
+            return;
+        };
+        let name = func.name.clone();
+        match c {
+            '1' | '\r' | '\n' => {
+                self.state = CalcState::Normal;
+                self.apply_user_function(&name);
+            }
+            '2' => {
+                // Editing happens on the algebraic input line
+                let definition = func.definition();
+                self.mode = CalcMode::Algebraic;
+                self.algebraic.set_input(&definition);
+                self.state = CalcState::Normal;
+            }
+            '3' => {
+                self.functions.remove(&name);
+                self.state = CalcState::UserFnMenu(page);
+            }
+            '\u{001B}' | '∴' => self.state = CalcState::UserFnMenu(page),
+            _ => {}
+        }
+    }
+
+    /// Handle a key in the constants browser
+    fn handle_const_menu_key(&mut self, page: u8, c: char) {
+        let pages = CONSTANTS.len().div_ceil(CONST_PAGE);
+        match c {
+            '↓' | '→' if (page as usize) + 1 < pages => {
+                self.state = CalcState::ConstMenu(page + 1);
+            }
+            '↑' | '←' if page > 0 => {
+                self.state = CalcState::ConstMenu(page - 1);
+            }
+            _ => {
+                if let Some(digit) = c.to_digit(10) {
+                    let slot = if digit == 0 { 9 } else { digit as usize - 1 };
+                    let index = page as usize * CONST_PAGE + slot;
+                    if index < CONSTANTS.len() {
+                        self.state = CalcState::ConstInfo(index as u8);
+                    }
+                } else if c == '\u{001B}' || c == '∴' {
+                    self.state = CalcState::Normal;
+                }
+            }
+        }
+    }
+
+    /// Handle a key while a constant's description is shown: insert its
+    /// name (algebraic) or push its value (RPN)
+    fn handle_const_info_key(&mut self, index: usize, c: char) {
+        match c {
+            '1' | '\r' | '\n' => {
+                self.state = CalcState::Normal;
+                self.apply_function(Func::Constant(index));
+            }
+            '\u{001B}' | '∴' => self.state = CalcState::ConstMenu((index / CONST_PAGE) as u8),
+            _ => {}
+        }
+    }
+
+    /// Handle a key in the unit picker: keys 1-9, 0 pick the unit to
+    /// convert from, then the unit to convert to
+    fn handle_unit_picker_key(&mut self, dimension: Dimension, from: Option<usize>, c: char) {
+        if let Some(digit) = c.to_digit(10) {
+            let slot = if digit == 0 { 9 } else { digit as usize - 1 };
+            let Some(unit) = units::of_dimension(dimension).nth(slot) else {
+                return;
+            };
+            match from {
+                None => self.state = CalcState::UnitPicker(dimension, Some(unit)),
+                Some(from) => {
+                    self.state = CalcState::Normal;
+                    self.convert_units(from, unit);
+                }
+            }
+        } else if c == '\r' || c == '\n' {
+            self.state = CalcState::Normal;
+            match from {
+                // Enter picking the first unit shows the value in SI units
+                None => self.apply_function(Func::Si),
+                // ... and picking the second, tags the value with the first
+                Some(from) => self.tag_unit(from),
+            }
+        } else if c == '\u{001B}' || c == '∴' {
+            self.state = match from {
+                Some(_) => CalcState::UnitPicker(dimension, None),
+                None => CalcState::Normal,
+            };
+        }
+    }
+
+    /// Insert `from→to` after the value being typed (algebraic), or
+    /// convert X (RPN): a quantity is shown in `to`, a plain number is read
+    /// in `from`
+    fn convert_units(&mut self, from: usize, to: usize) {
+        match self.mode {
+            CalcMode::Algebraic => {
+                self.algebraic
+                    .push_str(&alloc::format!("{}→{}", UNITS[from].name, UNITS[to].name));
+            }
+            CalcMode::Rpn if matches!(self.rpn.get_stack()[0], Value::Number(q) if !q.is_plain()) => {
+                if let Err(e) = self.rpn.apply_quantities(1, |args| args[0].express_in(to)) {
+                    self.error = Some(String::from(e.message()));
+                }
+            }
+            CalcMode::Rpn => {
+                let word = self.integer_word();
+                let mut flags = IntFlags::default();
+                let result = self.rpn.apply_with(1, |args| {
+                    let value = units::convert(args[0], from, to)?;
+                    match word {
+                        Some(word) => with_flags(&mut flags, word.fit_result(value)),
+                        None => Ok(value),
+                    }
+                });
+                match result {
+                    Ok(()) => self.rpn_flags = flags,
+                    Err(e) => self.error = Some(String::from(e.message())),
+                }
+            }
+        }
+    }
+
+    /// Give the value being typed (algebraic) or X (RPN) a unit
+    fn tag_unit(&mut self, unit: usize) {
+        match self.mode {
+            CalcMode::Algebraic => self.algebraic.push_str(UNITS[unit].name),
+            // Integer words hold plain numbers only
+            CalcMode::Rpn if self.integer_word().is_some() => {
+                self.error = Some(String::from("ERR: DIMENSION"));
+            }
+            CalcMode::Rpn => {
+                let (angle_mode, complex_mode) = (self.angle_mode, self.complex_mode);
+                // A plain number is a reading on the unit's scale
+                let result = self.rpn.apply_quantities(1, |args| match args[0].plain() {
+                    Ok(x) => Quantity::in_unit(x, unit),
+                    Err(_) => Op::Mul.evaluate_quantity(args[0], Quantity::of_unit(unit)?, angle_mode, complex_mode),
+                });
+                if let Err(e) = result {
+                    self.error = Some(String::from(e.message()));
+                }
+            }
+        }
+    }
+
+    /// Handle a key while browsing the history tape
+    fn handle_history_key(&mut self, age: usize, c: char) {
+        let Some(entry) = self.history.recent(age).cloned() else {
+            self.state = CalcState::Normal;
+            return;
+        };
+        match c {
+            '↑' if age + 1 < self.history.len() => self.state = CalcState::History(age + 1),
+            '↓' if age > 0 => self.state = CalcState::History(age - 1),
+            // Enter: edit the expression again (algebraic), or push the result (RPN)
+            '\r' | '\n' => {
+                self.state = CalcState::Normal;
+                match self.mode {
+                    CalcMode::Algebraic => self.algebraic.set_input(&entry.expression),
+                    CalcMode::Rpn => self.insert_value(entry.result),
+                }
+            }
+            // `=`: just the result, at the cursor or onto the stack
+            '=' => {
+                self.state = CalcState::Normal;
+                self.insert_value(entry.result);
+            }
+            '\u{001B}' | '∴' | '↓' => self.state = CalcState::Normal,
+            _ => {}
+        }
+    }
+
+    /// Scroll the full binary view; any other key closes it
+    fn handle_binary_view_key(&mut self, first: usize, c: char) {
+        let rows = (self.word.bits / BINARY_VIEW_ROW_BITS) as usize;
+        let last = rows.saturating_sub(ui::BINARY_VIEW_ROWS);
+        self.state = match c {
+            '↑' => CalcState::BinaryView(first.saturating_sub(1)),
+            '↓' => CalcState::BinaryView((first + 1).min(last)),
+            '\u{001B}' | '∴' | '\u{F707}' | '\r' | '\n' => CalcState::Normal,
+            _ => CalcState::BinaryView(first),
+        };
+    }
+
+    /// Edit bits in the inspector; Enter writes the value back, `x` the
+    /// selected field, `i` reads the bits as an integer or a float
+    fn handle_bit_inspector_key(&mut self, c: char) {
+        let Some(inspector) = self.inspector.as_mut() else {
+            self.state = CalcState::Normal;
+            return;
+        };
+        self.error = None;
+        match c {
+            '←' => inspector.move_cursor(1),
+            '→' => inspector.move_cursor(-1),
+            '↑' => inspector.move_cursor(GRID_ROW_BITS as i32),
+            '↓' => inspector.move_cursor(-(GRID_ROW_BITS as i32)),
+            ' ' => inspector.toggle(),
+            'h' | 'H' => inspector.set_high(),
+            'l' | 'L' => inspector.set_low(),
+            'p' | 'P' => inspector.select_part(),
+            'i' | 'I' => inspector.cycle_reading(),
+            '\u{0008}' => inspector.backspace(),
+            '\r' | '\n' if !inspector.entry().is_empty() => {
+                if let Err(e) = inspector.deposit(self.number_base) {
+                    self.error = Some(String::from(e.message()));
+                }
+            }
+            '\r' | '\n' => {
+                let value = inspector.value();
+                self.close_inspector();
+                self.insert_value(value);
+            }
+            'x' | 'X' => {
+                let field = inspector.field() as f64;
+                self.close_inspector();
+                self.insert_value(field);
+            }
+            '\u{001B}' | '∴' => self.close_inspector(),
+            c => {
+                inspector.push_digit(c, self.number_base);
+            }
+        }
+    }
+
+    fn close_inspector(&mut self) {
+        self.inspector = None;
+        self.state = CalcState::Normal;
+    }
+
+    /// Edit cells in the matrix editor; Enter writes the entry and steps to
+    /// the next cell, `r`/`c` add a row/column, `R`/`C` drop one, and `=`
+    /// inserts the matrix (or pushes it, or writes it back to X)
+    fn handle_matrix_editor_key(&mut self, to_x: bool, c: char) {
+        let Some(editor) = self.editor.as_mut() else {
+            self.state = CalcState::Normal;
+            return;
+        };
+        self.error = None;
+        let result = match c {
+            '←' => editor.move_cursor(0, -1),
+            '→' => editor.move_cursor(0, 1),
+            '↑' => editor.move_cursor(-1, 0),
+            '↓' => editor.move_cursor(1, 0),
+            '\r' | '\n' => editor.advance(),
+            '\u{0008}' => {
+                editor.backspace();
+                Ok(())
+            }
+            'r' => editor.resize(1, 0),
+            'c' => editor.resize(0, 1),
+            'R' => editor.resize(-1, 0),
+            'C' => editor.resize(0, -1),
+            '=' => match editor.finish() {
+                Ok(matrix) => {
+                    self.close_editor();
+                    if to_x {
+                        self.rpn.set_x(matrix);
+                    } else {
+                        self.insert_value(matrix);
+                    }
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            },
+            '\u{001B}' | '∴' => {
+                self.close_editor();
+                Ok(())
+            }
+            _ => {
+                editor.push(c);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            self.error = Some(String::from(e.message()));
+        }
+    }
+
+    fn close_editor(&mut self) {
+        self.editor = None;
+        self.state = CalcState::Normal;
+    }
+
+    /// Handle a key while choosing the size of a solver's problem: a digit
+    /// opens the solver, or shows why that size is not allowed
+    fn handle_size_key(&mut self, solver: Solver, c: char) {
+        self.error = None;
+        if let Some(digit) = c.to_digit(10) {
+            let opened = match solver {
+                Solver::LinearSystem => LinearSystem::new(digit as usize).map(|system| {
+                    self.system = Some(system);
+                    CalcState::LinearSystem
+                }),
+                Solver::Polynomial => {
+                    let degree = if digit == 0 { 10 } else { digit as usize };
+                    PolySolver::new(degree).map(|poly| {
+                        self.poly = Some(poly);
+                        CalcState::Polynomial
+                    })
+                }
+            };
+            match opened {
+                Ok(state) => self.state = state,
+                Err(e) => self.error = Some(String::from(e.detail())),
+            }
+        } else if c == '\u{001B}' || c == '∴' {
+            self.state = CalcState::Normal;
+        }
+    }
+
+    /// Fill in the augmented matrix `[A | b]` like the matrix editor and
+    /// solve with `=`; then `Enter` inserts or pushes the solution vector,
+    /// 0-9 store it in a register, and `ESC` goes back to the coefficients
+    fn handle_linear_system_key(&mut self, c: char) {
+        let Some(system) = self.system.as_mut() else {
+            self.state = CalcState::Normal;
+            return;
+        };
+        self.error = None;
+        self.notice = None;
+        if let Some(solution) = system.solution() {
+            let x = solution.x;
+            match c {
+                '\r' | '\n' => {
+                    self.close_system();
+                    self.insert_value(x);
+                }
+                '0'..='9' => {
+                    let digit = c.to_digit(10).unwrap_or(0) as usize;
+                    if self.memory.store(digit, x) {
+                        self.notice = Some(alloc::format!("Stored M{}", digit));
+                    }
+                }
+                '\u{001B}' => system.edit(),
+                '∴' => self.close_system(),
+                _ => {}
+            }
+            return;
+        }
+        let editor = system.editor_mut();
+        let result = match c {
+            '←' => editor.move_cursor(0, -1),
+            '→' => editor.move_cursor(0, 1),
+            '↑' => editor.move_cursor(-1, 0),
+            '↓' => editor.move_cursor(1, 0),
+            '\r' | '\n' => editor.advance(),
+            '\u{0008}' => {
+                editor.backspace();
+                Ok(())
+            }
+            '=' => system.solve().map(|_| ()),
+            '\u{001B}' | '∴' => {
+                self.close_system();
+                Ok(())
+            }
+            _ => {
+                editor.push(c);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            self.error = Some(String::from(e.detail()));
+        }
+    }
+
+    fn close_system(&mut self) {
+        self.system = None;
+        self.notice = None;
+        self.state = CalcState::Normal;
+    }
+
+    /// Fill in coefficients from the highest power down, `↑`/`↓` moving
+    /// between them, and find the roots with `=`; then `↑`/`↓` pick a root,
+    /// `Enter` recalls it and `ESC` goes back to the coefficients
+    fn handle_polynomial_key(&mut self, c: char) {
+        let Some(poly) = self.poly.as_mut() else {
+            self.state = CalcState::Normal;
+            return;
+        };
+        self.error = None;
+        if poly.roots().is_some() {
+            match c {
+                '↑' => poly.select(-1),
+                '↓' => poly.select(1),
+                '\r' | '\n' => {
+                    if let Some(root) = poly.selected_root() {
+                        self.close_poly();
+                        self.insert_value(root);
+                    }
+                }
+                '\u{001B}' => poly.edit(),
+                '∴' => self.close_poly(),
+                _ => {}
+            }
+            return;
+        }
+        let result = match c {
+            '↑' => poly.move_cursor(-1),
+            '↓' => poly.move_cursor(1),
+            '\r' | '\n' => poly.advance(),
+            '\u{0008}' => {
+                poly.backspace();
+                Ok(())
+            }
+            '=' => poly.solve().map(|_| ()),
+            '\u{001B}' | '∴' => {
+                self.close_poly();
+                Ok(())
+            }
+            _ => {
+                poly.push(c);
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            self.error = Some(String::from(e.detail()));
+        }
+    }
+
+    fn close_poly(&mut self) {
+        self.poly = None;
+        self.state = CalcState::Normal;
+    }
+
+    /// Type the equation, then `Enter` to type the guess or bracket and
+    /// `Enter` again to solve; then `Enter` recalls the root and `ESC` goes
+    /// back to the equation
+    fn handle_equation_key(&mut self, c: char) {
+        let Some(solver) = self.equation.as_mut() else {
+            self.state = CalcState::Normal;
+            return;
+        };
+        self.error = None;
+        if let Some(root) = solver.root() {
+            match c {
+                '\r' | '\n' => {
+                    let x = root.x;
+                    self.close_equation();
+                    self.insert_value(x);
+                }
+                '\u{001B}' => solver.edit(),
+                '∴' => self.close_equation(),
+                _ => {}
+            }
+            return;
+        }
+        match c {
+            '↑' | '↓' => solver.switch_field(),
+            '\r' | '\n' if solver.field() == Field::Equation => solver.switch_field(),
+            '\r' | '\n' => {
+                let mut ctx = EvalContext::new(
+                    self.algebraic.ans(),
+                    self.angle_mode,
+                    &self.variables,
+                    &self.functions,
+                );
+                ctx.complex = self.complex_mode;
+                if let Err(e) = solver.solve(&ctx) {
+                    // The span is within the line the solver moved to
+                    self.error = Some(match e.span() {
+                        Some(span) => alloc::format!("{} (col {})", e.detail(), span.start + 1),
+                        None => String::from(e.detail()),
+                    });
+                }
+            }
+            '\u{0008}' => solver.backspace(),
+            '\u{001B}' | '∴' => self.close_equation(),
+            _ => {
+                solver.push(c);
+            }
+        }
+    }
+
+    fn close_equation(&mut self) {
+        self.equation = None;
+        self.state = CalcState::Normal;
+    }
+
+    /// Scroll the matrix viewer across columns; any other key closes it
+    fn handle_matrix_view_key(&mut self, first: usize, c: char) {
+        let cols = self.current_result().matrix().map_or(0, |m| m.cols());
+        let last = cols.saturating_sub(ui::MATRIX_VIEW_COLS);
+        self.state = match c {
+            '←' => CalcState::MatrixView(first.saturating_sub(1)),
+            '→' => CalcState::MatrixView((first + 1).min(last)),
+            _ => CalcState::Normal,
+        };
+    }
+
+    /// Call a user function: insert a call in algebraic mode, evaluate on
+    /// the stack in RPN mode
+    fn apply_user_function(&mut self, name: &str) {
+        match self.mode {
+            CalcMode::Algebraic => {
+                self.algebraic.push_str(name);
+                self.algebraic.push('(');
+            }
+            CalcMode::Rpn => {
+                let n = self.functions.get(name).map_or(0, |f| f.params.len());
+                let mut ctx = EvalContext::new(
+                    self.algebraic.ans(),
+                    self.angle_mode,
+                    &self.variables,
+                    &self.functions,
+                );
+                ctx.complex = self.complex_mode;
+                let result = if n == 0 {
+                    AlgebraicParser::call_user_function(name, &[], &ctx)
+                        .map(|value| self.rpn.push(value))
+                } else {
+                    self.rpn
+                        .apply_values(n, |args| AlgebraicParser::call_user_function(name, args, &ctx))
+                };
+                if let Err(e) = result {
+                    self.error = Some(String::from(e.message()));
+                }
+            }
+        }
+    }
+
+    /// Get current display value; NaN for a matrix
     fn current_value(&self) -> f64 {
+        self.current_result().number().map_or(f64::NAN, |q| q.value)
+    }
+
+    /// Get current display value with its dimensions, or matrix
+    fn current_result(&self) -> Value {
         match self.mode {
             CalcMode::Algebraic => self.algebraic.ans(),
-            CalcMode::Rpn => self.rpn.x(),
+            CalcMode::Rpn => self.rpn.get_stack()[0],
+        }
+    }
+
+    /// Grouped binary rows for the result area or X register, in BIN only
+    fn inline_binary_rows(&self, value: f64) -> Vec<String> {
+        match self.number_base {
+            NumberBase::Binary => binary_rows(value, self.word, BINARY_ROW_BITS).unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Angle unit complex values are shown in, if shown in polar form
+    fn polar(&self) -> Option<AngleMode> {
+        match self.complex_mode {
+            ComplexMode::Polar => Some(self.angle_mode),
+            _ => None,
+        }
+    }
+
+    /// Integer word arithmetic wraps to, in the non-decimal bases
+    fn integer_word(&self) -> Option<WordSize> {
+        match self.number_base {
+            NumberBase::Decimal => None,
+            _ => Some(self.word),
         }
     }
 
     /// Insert a value (for memory recall, etc)
-    fn insert_value(&mut self, value: f64) {
+    fn insert_value(&mut self, value: impl Into<Value>) {
+        let value = value.into();
         match self.mode {
             CalcMode::Algebraic => {
-                use core::fmt::Write;
-                let mut buf = String::new();
-                write!(buf, "{}", value).ok();
+                // Non-decimal values carry their prefix so they read back
+                // the same whatever the base
+                let buf = match (value, self.number_base) {
+                    (Value::Number(q), base) if base != NumberBase::Decimal => format_number(q.value, base, self.word),
+                    _ => value.to_source(),
+                };
                 self.algebraic.push_str(&buf);
             }
             CalcMode::Rpn => {
@@ -316,11 +1280,19 @@ impl CalcApp {
     fn apply_operator(&mut self, op: Op) {
         match self.mode {
             CalcMode::Algebraic => {
-                self.algebraic.push(op.symbol());
+                self.algebraic.push_str(op.symbol());
             }
             CalcMode::Rpn => {
-                if let Err(e) = self.rpn.apply_binary(op) {
-                    self.error = Some(String::from(e.message()));
+                let mut flags = IntFlags::default();
+                let result = match self.integer_word() {
+                    Some(word) => self.rpn.apply_with(2, |args| {
+                        with_flags(&mut flags, op.evaluate_word(args[0], args[1], word))
+                    }),
+                    None => self.rpn.apply_binary(op, self.angle_mode, self.complex_mode),
+                };
+                match result {
+                    Ok(()) => self.rpn_flags = flags,
+                    Err(e) => self.error = Some(String::from(e.message())),
                 }
             }
         }
@@ -330,15 +1302,46 @@ impl CalcApp {
     fn apply_function(&mut self, func: Func) {
         match self.mode {
             CalcMode::Algebraic => {
-                // Insert function name with open paren
-                self.algebraic.push_str(func.name());
-                if !func.is_constant() {
-                    self.algebraic.push('(');
+                if let Some(symbol) = func.postfix_symbol() {
+                    // Postfix operators follow the operand already typed
+                    self.algebraic.push_str(symbol);
+                } else {
+                    // Insert function name with open paren
+                    self.algebraic.push_str(func.name());
+                    if !func.is_constant() {
+                        self.algebraic.push('(');
+                    }
                 }
             }
+            // Constants are pushed, not applied to X; integer words hold
+            // plain numbers only
+            CalcMode::Rpn if func.is_constant() => match func.evaluate_quantity(
+                Quantity::default(),
+                self.angle_mode,
+                self.complex_mode,
+            ) {
+                Ok(value) if self.integer_word().is_some() => self.insert_value(value.complex()),
+                Ok(value) => self.insert_value(value),
+                Err(e) => self.error = Some(String::from(e.message())),
+            },
             CalcMode::Rpn => {
-                if let Err(e) = self.rpn.apply_unary(func, self.angle_mode) {
-                    self.error = Some(String::from(e.message()));
+                let n = func.stack_arity();
+                let (angle_mode, complex_mode) = (self.angle_mode, self.complex_mode);
+                let mut flags = IntFlags::default();
+                let result = match self.integer_word() {
+                    Some(word) if n > 1 => self.rpn.apply_with(n, |args| {
+                        let result = func.evaluate_args(args, angle_mode)?;
+                        with_flags(&mut flags, word.fit_result(result))
+                    }),
+                    Some(word) => self.rpn.apply_with(1, |args| {
+                        with_flags(&mut flags, func.evaluate_word(args[0], angle_mode, word))
+                    }),
+                    None if n > 1 => self.rpn.apply_nary(func, n, angle_mode, complex_mode),
+                    None => self.rpn.apply_unary(func, angle_mode, complex_mode),
+                };
+                match result {
+                    Ok(()) => self.rpn_flags = flags,
+                    Err(e) => self.error = Some(String::from(e.message())),
                 }
             }
         }
@@ -349,13 +1352,16 @@ impl CalcApp {
         match self.mode {
             CalcMode::Algebraic => {
                 let expr = self.algebraic.input().to_string();
-                if let Some(result) = self.algebraic.evaluate(self.angle_mode) {
-                    if !expr.is_empty() {
-                        self.history.add(HistoryEntry::new(expr, result));
+                match self.algebraic.evaluate(self.angle_mode, &mut self.variables, &mut self.functions) {
+                    Some(Outcome::Value(result)) => {
+                        if !expr.is_empty() {
+                            self.history.add(HistoryEntry::new(expr, result));
+                        }
+                        self.algebraic.clear();
                     }
-                    self.algebraic.clear();
-                } else if let Some(err) = self.algebraic.error() {
-                    self.error = Some(String::from(err));
+                    Some(Outcome::Defined) => self.algebraic.clear(),
+                    // The error stays with the input so it can point into it
+                    None => {}
                 }
             }
             CalcMode::Rpn => {
@@ -364,6 +1370,18 @@ impl CalcApp {
         }
     }
 
+    /// Move the cursor or delete within the algebraic input line
+    fn edit_input(&mut self, action: KeyAction) {
+        match action {
+            KeyAction::Delete => self.algebraic.delete(),
+            KeyAction::CursorLeft => self.algebraic.cursor_left(),
+            KeyAction::CursorRight => self.algebraic.cursor_right(),
+            KeyAction::CursorHome => self.algebraic.cursor_home(),
+            KeyAction::CursorEnd => self.algebraic.cursor_end(),
+            _ => {}
+        }
+    }
+
     /// Backspace
     fn backspace(&mut self) {
         match self.mode {
@@ -436,7 +1454,7 @@ impl CalcApp {
     /// Toggle between algebraic and RPN modes
     fn toggle_mode(&mut self) {
         // Transfer current value between modes
-        let value = self.current_value();
+        let value = self.current_result();
 
         self.mode = match self.mode {
             CalcMode::Algebraic => CalcMode::Rpn,
@@ -454,6 +1472,139 @@ impl CalcApp {
         }
     }
 
+    /// The augmented matrix of a linear system being filled in, or its
+    /// solution
+    fn draw_linear_system(&self, gam: &Gam, gid: gam::Gid, system: &LinearSystem) {
+        let n = system.unknowns();
+        let Some(solution) = system.solution() else {
+            let editor = system.editor();
+            let (row, col) = editor.cursor();
+            let mut cells = matrix_cells(editor.matrix());
+            if !editor.entry().is_empty() {
+                cells[row][col] = alloc::format!("{}_", editor.entry());
+            }
+            let column = if col == n { String::from("b") } else { alloc::format!("x{}", col + 1) };
+            let title = alloc::format!("Ax=b, n={}: row {}, {}", n, row + 1, column);
+            let lines: Vec<&str> = self.error.as_deref().into_iter().collect();
+            ui::draw_matrix(
+                gam,
+                gid,
+                &title,
+                &cells,
+                col.saturating_sub(ui::MATRIX_VIEW_COLS - 1),
+                Some((row, col)),
+                &lines,
+                "Last column is b; Enter next, = solve",
+            );
+            return;
+        };
+        let mut lines: Vec<(String, String)> = solution
+            .x
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (alloc::format!("x{}", i + 1), format_number(x, NumberBase::Decimal, self.word)))
+            .collect();
+        lines.push((String::from("cond"), format_number(solution.condition, NumberBase::Decimal, self.word)));
+        if solution.is_ill_conditioned() {
+            let warning = alloc::format!("Ill-conditioned: ~{} digits reliable", solution.reliable_digits());
+            lines.push((String::new(), warning));
+        }
+        if let Some(notice) = &self.notice {
+            lines.push((String::new(), notice.clone()));
+        }
+        let items: Vec<(&str, &str)> = lines.iter().map(|(key, line)| (key.as_str(), line.as_str())).collect();
+        let hint = match self.mode {
+            CalcMode::Algebraic => "Enter insert, 0-9 store, ESC edit",
+            CalcMode::Rpn => "Enter push, 0-9 store, ESC edit",
+        };
+        ui::draw_list_menu(gam, gid, "Solution", &items, hint);
+    }
+
+    /// The coefficients of a polynomial being filled in, or its roots
+    fn draw_polynomial(&self, gam: &Gam, gid: gam::Gid, poly: &PolySolver) {
+        let degree = poly.degree();
+        let (title, lines, selected, hint): (String, Vec<(String, String)>, usize, &str) = match poly.roots() {
+            None => {
+                let lines = poly
+                    .coefficients()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &a)| {
+                        let power = match degree - i {
+                            0 => String::from("1"),
+                            1 => String::from("x"),
+                            n => alloc::format!("x^{}", n),
+                        };
+                        let value = if i == poly.cursor() && !poly.entry().is_empty() {
+                            alloc::format!("{}_", poly.entry())
+                        } else {
+                            format_number(a, NumberBase::Decimal, self.word)
+                        };
+                        (power, value)
+                    })
+                    .collect();
+                let title = alloc::format!("Degree {} coefficients", degree);
+                (title, lines, poly.cursor(), "↑↓ move, Enter next, = roots")
+            }
+            Some(roots) => {
+                let lines = roots
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &z)| {
+                        let value = format_complex(z, NumberBase::Decimal, self.word, self.polar());
+                        (alloc::format!("r{}", i + 1), value)
+                    })
+                    .collect();
+                let hint = match self.mode {
+                    CalcMode::Algebraic => "↑↓ pick, Enter insert, ESC edit",
+                    CalcMode::Rpn => "↑↓ pick, Enter push, ESC edit",
+                };
+                (String::from("Roots"), lines, poly.selected(), hint)
+            }
+        };
+        let mut lines = lines;
+        lines[selected].0.insert(0, '▶');
+        if let Some(err) = &self.error {
+            lines.push((String::new(), err.clone()));
+        }
+        let items: Vec<(&str, &str)> = lines.iter().map(|(key, line)| (key.as_str(), line.as_str())).collect();
+        ui::draw_list_menu(gam, gid, &title, &items, hint);
+    }
+
+    /// The equation being typed, or the root found
+    fn draw_equation(&self, gam: &Gam, gid: gam::Gid, solver: &EquationSolver) {
+        let mut lines: Vec<(String, String)> = match solver.root() {
+            None => {
+                let line = |text: &str, field| {
+                    if solver.field() == field {
+                        alloc::format!("{}_", text)
+                    } else {
+                        String::from(text)
+                    }
+                };
+                alloc::vec![
+                    (String::from("f(x)"), line(solver.equation(), Field::Equation)),
+                    (String::from("start"), line(solver.start(), Field::Start)),
+                ]
+            }
+            Some(root) => alloc::vec![
+                (String::from(solver.unknown()), format_number(root.x, NumberBase::Decimal, self.word)),
+                (String::from("iter"), alloc::format!("{}", root.iterations)),
+                (String::from("resid"), format_number(root.residual, NumberBase::Decimal, self.word)),
+            ],
+        };
+        if let Some(err) = &self.error {
+            lines.push((String::new(), err.clone()));
+        }
+        let items: Vec<(&str, &str)> = lines.iter().map(|(key, line)| (key.as_str(), line.as_str())).collect();
+        let (title, hint) = match (solver.root(), self.mode) {
+            (None, _) => ("Solve f(x)=0", "guess or a,b; Enter next/solve"),
+            (Some(_), CalcMode::Algebraic) => ("Root", "Enter insert, ESC edit"),
+            (Some(_), CalcMode::Rpn) => ("Root", "Enter push, ESC edit"),
+        };
+        ui::draw_list_menu(gam, gid, title, &items, hint);
+    }
+
     /// Draw the calculator UI
     pub fn draw(&self, gam: &Gam, gid: gam::Gid) {
         ui::clear_screen(gam, gid);
@@ -463,36 +1614,57 @@ impl CalcApp {
             CalcMode::Algebraic => "ALG",
             CalcMode::Rpn => "RPN",
         };
+        let word_label = self.integer_word().map(|word| word.label());
+        let flags = match self.mode {
+            CalcMode::Algebraic => self.algebraic.flags(),
+            CalcMode::Rpn => self.rpn_flags,
+        };
         ui::draw_status_bar(
             gam,
             gid,
             mode_label,
             self.angle_mode.label(),
             self.number_base.label(),
+            word_label.as_deref(),
+            self.complex_mode.is_complex().then(|| self.complex_mode.label()),
+            flags,
             self.memory.has_stored_value(),
         );
 
         // Main display based on mode
         match self.mode {
             CalcMode::Algebraic => {
-                let result = format_number(self.algebraic.ans(), self.number_base);
+                let result = format_value(self.algebraic.ans(), self.number_base, self.word, self.polar(), RESULT_WIDTH);
+                let result_rows = self.inline_binary_rows(self.current_value());
+                let (error, error_span) = match self.error.as_deref() {
+                    Some(err) => (Some(err), None),
+                    None => (self.algebraic.error(), self.algebraic.error_span()),
+                };
+                let preview = self
+                    .algebraic
+                    .preview(self.angle_mode, &self.variables, &self.functions)
+                    .map(|value| format_value(value, self.number_base, self.word, self.polar(), RESULT_WIDTH));
                 ui::draw_algebraic_display(
                     gam,
                     gid,
                     self.algebraic.input(),
+                    self.algebraic.cursor(),
                     &result,
-                    self.error.as_deref().or(self.algebraic.error()),
+                    &result_rows,
+                    preview.as_deref(),
+                    error,
+                    error_span,
                 );
             }
             CalcMode::Rpn => {
                 let stack = self.rpn.get_stack();
                 let stack_strs: [String; 4] = [
-                    format_stack_number(stack[0], self.number_base),
-                    format_stack_number(stack[1], self.number_base),
-                    format_stack_number(stack[2], self.number_base),
-                    format_stack_number(stack[3], self.number_base),
+                    format_stack_number(stack[0], self.number_base, self.word, self.polar()),
+                    format_stack_number(stack[1], self.number_base, self.word, self.polar()),
+                    format_stack_number(stack[2], self.number_base, self.word, self.polar()),
+                    format_stack_number(stack[3], self.number_base, self.word, self.polar()),
                 ];
-                let last_x = format_stack_number(self.rpn.last_x(), self.number_base);
+                let last_x = format_stack_number(self.rpn.last_x_value(), self.number_base, self.word, self.polar());
 
                 let entry = if self.rpn.is_entering() {
                     self.rpn.entry_buffer()
@@ -504,6 +1676,7 @@ impl CalcApp {
                     gam,
                     gid,
                     [&stack_strs[0], &stack_strs[1], &stack_strs[2], &stack_strs[3]],
+                    &self.inline_binary_rows(self.current_value()),
                     entry,
                     self.rpn.is_entering(),
                     &last_x,
@@ -512,15 +1685,21 @@ impl CalcApp {
             }
         }
 
-        // History
+        // History, scrolled back far enough to show the selected entry
+        let age = match self.state {
+            CalcState::History(age) => Some(age),
+            _ => None,
+        };
+        let skip = age.map_or(0, |age| (age + 1).saturating_sub(HISTORY_LINES));
         let history_entries: Vec<String> = self
             .history
-            .last_n(10)
+            .window(skip, HISTORY_LINES)
             .iter()
-            .map(|e| e.format(self.number_base))
+            .map(|e| e.format(self.number_base, self.word, self.polar()))
             .collect();
         let history_refs: Vec<&str> = history_entries.iter().map(|s| s.as_str()).collect();
-        ui::draw_history(gam, gid, &history_refs);
+        let selected = age.map(|age| history_refs.len() - 1 - (age - skip));
+        ui::draw_history(gam, gid, &history_refs, selected);
 
         // Menu bar
         ui::draw_menu_bar(gam, gid);
@@ -532,6 +1711,12 @@ impl CalcApp {
                 2 => "TRIG Menu",
                 3 => "MODE Menu",
                 4 => "MEM Menu",
+                5 => "FUNC Menu",
+                6 => "BIT Menu",
+                7 => "UNIT Menu",
+                8 => "CPLX Menu",
+                9 => "MTRX Menu",
+                10 => "SOLVE Menu",
                 _ => "Menu",
             };
             let items = get_menu_items(menu);
@@ -541,10 +1726,224 @@ impl CalcApp {
         // Store/Recall prompt
         match self.state {
             CalcState::WaitingStore => {
-                ui::draw_fn_menu(gam, gid, "Store to M#", &[("0-9", "Select register")]);
+                ui::draw_fn_menu(
+                    gam,
+                    gid,
+                    "Store to M#",
+                    &[("0-9", "Select register"), ("a-z", "Name a variable")],
+                );
             }
             CalcState::WaitingRecall => {
-                ui::draw_fn_menu(gam, gid, "Recall M#", &[("0-9", "Select register")]);
+                ui::draw_fn_menu(
+                    gam,
+                    gid,
+                    "Recall M#",
+                    &[("0-9", "Select register"), ("a-z", "Variable name")],
+                );
+            }
+            CalcState::UserFnMenu(page) => {
+                let definitions: Vec<String> = self
+                    .functions
+                    .iter()
+                    .skip(page as usize * USER_FN_PAGE)
+                    .take(USER_FN_PAGE)
+                    .map(|f| f.definition())
+                    .collect();
+                const KEYS: [&str; USER_FN_PAGE] = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "0"];
+                let items: Vec<(&str, &str)> = KEYS
+                    .iter()
+                    .zip(definitions.iter())
+                    .map(|(key, def)| (*key, def.as_str()))
+                    .collect();
+                ui::draw_list_menu(gam, gid, "User Functions", &items, "0-9 select, ↑↓ page, ESC close");
+            }
+            CalcState::UserFnAction(index) => {
+                let title = self
+                    .functions
+                    .iter()
+                    .nth(index as usize)
+                    .map(|f| f.definition())
+                    .unwrap_or_default();
+                let apply = if self.mode == CalcMode::Rpn { "Apply to stack" } else { "Insert call" };
+                ui::draw_list_menu(
+                    gam,
+                    gid,
+                    &title,
+                    &[("1", apply), ("2", "Edit"), ("3", "Delete")],
+                    "ESC back",
+                );
+            }
+            CalcState::UnitPicker(dimension, from) => {
+                let title = match from {
+                    None => alloc::format!("{}: from (Enter: SI)", dimension.label()),
+                    Some(from) => {
+                        alloc::format!("{}: {} to (Enter: tag)", dimension.label(), UNITS[from].name)
+                    }
+                };
+                const KEYS: [&str; 10] = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "0"];
+                let items: Vec<(&str, &str)> = KEYS
+                    .iter()
+                    .zip(units::of_dimension(dimension))
+                    .map(|(key, unit)| (*key, UNITS[unit].name))
+                    .collect();
+                ui::draw_fn_menu(gam, gid, &title, &items);
+            }
+            CalcState::ConstMenu(page) => {
+                let lines: Vec<String> = CONSTANTS
+                    .iter()
+                    .skip(page as usize * CONST_PAGE)
+                    .take(CONST_PAGE)
+                    .map(|c| {
+                        let value = format_number(c.value, NumberBase::Decimal, self.word);
+                        alloc::format!("{} = {} {}", c.symbol, value, c.unit)
+                    })
+                    .collect();
+                const KEYS: [&str; CONST_PAGE] = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "0"];
+                let items: Vec<(&str, &str)> = KEYS
+                    .iter()
+                    .zip(lines.iter())
+                    .map(|(key, line)| (*key, line.as_str()))
+                    .collect();
+                ui::draw_list_menu(gam, gid, "Constants", &items, "0-9 select, ↑↓ page, ESC close");
+            }
+            CalcState::ConstInfo(index) => {
+                let constant = &CONSTANTS[index as usize];
+                // Every digit of the CODATA value
+                let value = alloc::format!("{} = {:e}", constant.name, constant.value);
+                let unit = alloc::format!("Unit: {}", if constant.unit.is_empty() { "none" } else { constant.unit });
+                let apply = if self.mode == CalcMode::Rpn { "Push" } else { "Insert" };
+                ui::draw_list_menu(
+                    gam,
+                    gid,
+                    constant.symbol,
+                    &[("", constant.description), ("", &value), ("", &unit), ("1", apply)],
+                    "ESC back",
+                );
+            }
+            CalcState::BitInspector => {
+                if let Some(inspector) = &self.inspector {
+                    // Floats read in decimal whatever the base
+                    let value = match inspector.reading() {
+                        Reading::Integer => format_number(inspector.value(), self.number_base, inspector.word()),
+                        Reading::Float(_) => format_number(inspector.value(), NumberBase::Decimal, inspector.word()),
+                    };
+                    let title = alloc::format!("Bits {} = {}", inspector.label(), value);
+                    let (hi, lo) = inspector.field_range();
+                    let field = alloc::format!(
+                        "Field {}..{} = {}",
+                        hi,
+                        lo,
+                        format_number(inspector.field() as f64, self.number_base, WordSize::new(64, SignMode::Unsigned))
+                    );
+                    let mut lines = alloc::vec![field];
+                    if let Reading::Float(format) = inspector.reading() {
+                        let (fields, scaled) = format_float_fields(inspector.pattern(), format);
+                        lines.push(fields);
+                        lines.push(scaled);
+                    }
+                    if !inspector.entry().is_empty() {
+                        lines.push(alloc::format!("New field: {}_", inspector.entry()));
+                    }
+                    if let Some(err) = &self.error {
+                        lines.push(err.clone());
+                    }
+                    let lines: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+                    ui::draw_bit_inspector(gam, gid, &title, &inspector.grid_rows(), &lines);
+                }
+            }
+            CalcState::MatrixEditor(_) => {
+                if let Some(editor) = &self.editor {
+                    let matrix = editor.matrix();
+                    let (row, col) = editor.cursor();
+                    let mut cells = matrix_cells(matrix);
+                    if !editor.entry().is_empty() {
+                        cells[row][col] = alloc::format!("{}_", editor.entry());
+                    }
+                    let title = alloc::format!("Edit {}×{} (r{} c{})", matrix.rows(), matrix.cols(), row + 1, col + 1);
+                    let lines: Vec<&str> = self.error.as_deref().into_iter().collect();
+                    ui::draw_matrix(
+                        gam,
+                        gid,
+                        &title,
+                        &cells,
+                        col.saturating_sub(ui::MATRIX_VIEW_COLS - 1),
+                        Some((row, col)),
+                        &lines,
+                        "Enter next, r/c add, R/C drop, = done",
+                    );
+                }
+            }
+            CalcState::MatrixView(first) => {
+                if let Value::Matrix(matrix) = self.current_result() {
+                    let kind = if matrix.is_vector() { "vector" } else { "matrix" };
+                    let title = alloc::format!("{}×{} {}", matrix.rows(), matrix.cols(), kind);
+                    ui::draw_matrix(gam, gid, &title, &matrix_cells(&matrix), first, None, &[], "←→ scroll, ESC close");
+                }
+            }
+            CalcState::SizePrompt(solver) => {
+                let (title, mut items) = match solver {
+                    Solver::LinearSystem => ("Linear system Ax=b", alloc::vec![("2-6", "Number of unknowns")]),
+                    Solver::Polynomial => ("Polynomial roots", alloc::vec![("1-9", "Degree"), ("0", "Degree 10")]),
+                };
+                if let Some(err) = &self.error {
+                    items.push(("", err.as_str()));
+                }
+                ui::draw_list_menu(gam, gid, title, &items, "Digit to choose, ESC cancel");
+            }
+            CalcState::LinearSystem => {
+                if let Some(system) = &self.system {
+                    self.draw_linear_system(gam, gid, system);
+                }
+            }
+            CalcState::Polynomial => {
+                if let Some(poly) = &self.poly {
+                    self.draw_polynomial(gam, gid, poly);
+                }
+            }
+            CalcState::Equation => {
+                if let Some(solver) = &self.equation {
+                    self.draw_equation(gam, gid, solver);
+                }
+            }
+            CalcState::BinaryView(first) => {
+                let value = self.current_value();
+                let pattern = self.word.to_pattern(value.trunc()).unwrap_or(0);
+                let rows: Vec<(String, String)> =
+                    binary_rows(value, self.word, BINARY_VIEW_ROW_BITS)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .zip((0..self.word.bits / BINARY_VIEW_ROW_BITS).rev())
+                        .map(|(bits, row)| {
+                            let byte = pattern >> (row * BINARY_VIEW_ROW_BITS) & 0xFF;
+                            (bits, alloc::format!("{:02X}", byte))
+                        })
+                        .collect();
+                let title = alloc::format!(
+                    "{} = {}",
+                    self.word.label(),
+                    format_number(value.trunc(), NumberBase::Decimal, self.word)
+                );
+                ui::draw_binary_view(gam, gid, &title, &rows, first);
+            }
+            CalcState::StoreName | CalcState::RecallName => {
+                let mut title = String::new();
+                {
+                    use core::fmt::Write;
+                    let verb = if self.state == CalcState::StoreName { "STO" } else { "RCL" };
+                    write!(title, "{} {}_", verb, self.name_buffer).ok();
+                }
+                // Offer existing names that match what has been typed
+                let prefix = self.name_buffer.to_lowercase();
+                let matches: Vec<(&str, String)> = self
+                    .variables
+                    .iter()
+                    .filter(|(name, _)| name.starts_with(prefix.as_str()))
+                    .take(12)
+                    .map(|(name, value)| (name, format_stack_number(value, self.number_base, self.word, self.polar())))
+                    .collect();
+                let items: Vec<(&str, &str)> =
+                    matches.iter().map(|(name, value)| (*name, value.as_str())).collect();
+                ui::draw_fn_menu(gam, gid, &title, &items);
             }
             _ => {}
         }
@@ -553,6 +1952,24 @@ impl CalcApp {
     }
 }
 
+/// Cells of `m` as they fit the matrix editor and viewer
+fn matrix_cells(m: &Matrix) -> Vec<Vec<String>> {
+    (0..m.rows())
+        .map(|r| m.row(r).iter().map(|&x| format_cell(x, ui::MATRIX_CELL_WIDTH)).collect())
+        .collect()
+}
+
+/// Take the value of an integer word result, keeping its flags in `flags`
+fn with_flags(
+    flags: &mut IntFlags,
+    result: Result<(f64, IntFlags), CalcError>,
+) -> Result<f64, CalcError> {
+    result.map(|(value, raised)| {
+        *flags = raised;
+        value
+    })
+}
+
 extern crate alloc;
 
 // Helper function needed by keymap
@@ -0,0 +1,315 @@
+//! Bit-field inspector: view, toggle and edit the bits of a value, read as
+//! an integer or as an IEEE-754 float
+
+use crate::functions::{
+    bit_field, with_bit_field, CalcError, FloatFormat, NumberBase, SignMode, WordSize,
+};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+/// Bits per row of the inspector grid
+pub const GRID_ROW_BITS: u32 = 8;
+
+/// How the inspector reads its bits
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reading {
+    Integer,
+    Float(FloatFormat),
+}
+
+/// A value opened in the inspector, with a cursor and a selected field
+pub struct BitInspector {
+    /// 32 or 64 bits, with the sign mode of the calculator's word, for
+    /// reading as an integer
+    int_word: WordSize,
+    reading: Reading,
+    pattern: u64,
+    /// Bit under the cursor
+    cursor: u32,
+    /// Selected field, highest and lowest bit
+    hi: u32,
+    lo: u32,
+    /// New field value being typed
+    entry: String,
+}
+
+impl BitInspector {
+    /// Open `value` in a 64-bit grid for 64-bit words, 32-bit otherwise.
+    /// Values that are not integers open as f64.
+    pub fn new(value: f64, word: WordSize) -> Self {
+        let bits = if word.bits == 64 { 64 } else { 32 };
+        let int_word = WordSize::new(bits, word.sign);
+        let (reading, pattern) = match int_word.to_pattern(value) {
+            Ok(pattern) => (Reading::Integer, pattern),
+            Err(_) => (Reading::Float(FloatFormat::Double), value.to_bits()),
+        };
+        Self {
+            int_word,
+            reading,
+            pattern,
+            cursor: 0,
+            hi: 0,
+            lo: 0,
+            entry: String::new(),
+        }
+    }
+
+    /// Width and sign of the bits as currently read
+    pub fn word(&self) -> WordSize {
+        match self.reading {
+            Reading::Integer => self.int_word,
+            Reading::Float(format) => WordSize::new(format.bits(), SignMode::Unsigned),
+        }
+    }
+
+    pub fn reading(&self) -> Reading {
+        self.reading
+    }
+
+    /// Status label: the integer word (`32-2C`) or the float format
+    pub fn label(&self) -> String {
+        match self.reading {
+            Reading::Integer => self.int_word.label(),
+            Reading::Float(format) => String::from(format.label()),
+        }
+    }
+
+    /// Read the same bits another way: integer → f64 → f32 → f16 → integer.
+    /// Bits beyond the new width are dropped.
+    pub fn cycle_reading(&mut self) {
+        self.reading = match self.reading {
+            Reading::Integer => Reading::Float(FloatFormat::Double),
+            Reading::Float(FloatFormat::Half) => Reading::Integer,
+            Reading::Float(format) => Reading::Float(format.cycle()),
+        };
+        let top = self.word().bits - 1;
+        self.pattern &= self.word().mask();
+        self.cursor = self.cursor.min(top);
+        self.hi = self.hi.min(top);
+        self.lo = self.lo.min(self.hi);
+    }
+
+    /// Select the next part of a float: sign, then exponent, then mantissa
+    pub fn select_part(&mut self) {
+        let Reading::Float(format) = self.reading else {
+            return;
+        };
+        let top = format.bits() - 1;
+        let m = format.mantissa_bits();
+        (self.hi, self.lo) = match (self.hi, self.lo) {
+            (hi, lo) if hi == top && lo == top => (top - 1, m),
+            (hi, lo) if hi == top - 1 && lo == m => (m - 1, 0),
+            _ => (top, top),
+        };
+    }
+
+    pub fn pattern(&self) -> u64 {
+        self.pattern
+    }
+
+    pub fn cursor(&self) -> u32 {
+        self.cursor
+    }
+
+    /// Selected field as (highest, lowest) bit
+    pub fn field_range(&self) -> (u32, u32) {
+        (self.hi, self.lo)
+    }
+
+    pub fn entry(&self) -> &str {
+        &self.entry
+    }
+
+    /// Value of the bits as currently read
+    pub fn value(&self) -> f64 {
+        match self.reading {
+            Reading::Integer => self.int_word.to_value(self.pattern),
+            Reading::Float(format) => format.decode(self.pattern),
+        }
+    }
+
+    /// Value of the selected field, unsigned
+    pub fn field(&self) -> u64 {
+        bit_field(self.pattern, self.hi, self.lo)
+    }
+
+    /// Move the cursor `delta` bits toward the most significant end
+    pub fn move_cursor(&mut self, delta: i32) {
+        let cursor = self.cursor as i32 + delta;
+        self.cursor = cursor.clamp(0, self.word().bits as i32 - 1) as u32;
+    }
+
+    /// Flip the bit under the cursor
+    pub fn toggle(&mut self) {
+        self.pattern ^= 1 << self.cursor;
+    }
+
+    /// Make the cursor bit the top of the field
+    pub fn set_high(&mut self) {
+        self.hi = self.cursor;
+        self.lo = self.lo.min(self.hi);
+    }
+
+    /// Make the cursor bit the bottom of the field
+    pub fn set_low(&mut self) {
+        self.lo = self.cursor;
+        self.hi = self.hi.max(self.lo);
+    }
+
+    /// Type a digit of a new field value; false if it is not a digit in `base`
+    pub fn push_digit(&mut self, c: char, base: NumberBase) -> bool {
+        if c.is_digit(base.radix()) {
+            self.entry.push(c);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.entry.pop();
+    }
+
+    /// Write the typed value into the selected field
+    pub fn deposit(&mut self, base: NumberBase) -> Result<(), CalcError> {
+        let field = u64::from_str_radix(&self.entry, base.radix())
+            .map_err(|_| CalcError::Overflow)?;
+        if field > bit_field(u64::MAX, self.hi, self.lo) {
+            return Err(CalcError::DomainError("value wider than field"));
+        }
+        self.pattern = with_bit_field(self.pattern, self.hi, self.lo, field);
+        self.entry.clear();
+        Ok(())
+    }
+
+    /// The grid, most significant row first: a line of bits framed by the
+    /// row's highest and lowest bit index, and a line beneath marking the
+    /// selected field with `-` and the cursor with `^`
+    pub fn grid_rows(&self) -> Vec<(String, String)> {
+        (0..self.word().bits / GRID_ROW_BITS)
+            .rev()
+            .map(|row| {
+                let low = row * GRID_ROW_BITS;
+                let high = low + GRID_ROW_BITS - 1;
+                let mut bits = String::new();
+                let mut marks = String::from("   ");
+                write!(bits, "{:>2} ", high).ok();
+                for bit in (low..=high).rev() {
+                    if bit != high && (bit + 1) % 4 == 0 {
+                        bits.push(' ');
+                        marks.push(' ');
+                    }
+                    bits.push(if self.pattern >> bit & 1 == 1 { '1' } else { '0' });
+                    bits.push(' ');
+                    marks.push(if bit == self.cursor {
+                        '^'
+                    } else if (self.lo..=self.hi).contains(&bit) {
+                        '-'
+                    } else {
+                        ' '
+                    });
+                    marks.push(' ');
+                }
+                write!(bits, "{}", low).ok();
+                (bits, marks)
+            })
+            .collect()
+    }
+}
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::SignMode;
+
+    #[test]
+    fn test_inspector_editing() {
+        let word = WordSize::new(8, SignMode::TwosComplement);
+        let mut bits = BitInspector::new(-1.0, word);
+        assert_eq!(bits.word().bits, 32);
+        assert_eq!(bits.pattern(), 0xFFFF_FFFF);
+
+        bits.toggle();
+        assert_eq!(bits.value(), -2.0);
+        bits.move_cursor(-5);
+        assert_eq!(bits.cursor(), 0);
+        bits.move_cursor(40);
+        assert_eq!(bits.cursor(), 31);
+
+        // Select bits 15..8 and write 0x12 into them
+        bits.move_cursor(-16);
+        bits.set_high();
+        bits.move_cursor(-7);
+        bits.set_low();
+        assert_eq!(bits.field_range(), (15, 8));
+        assert_eq!(bits.field(), 0xFF);
+        assert!(bits.push_digit('1', NumberBase::Hexadecimal));
+        assert!(bits.push_digit('2', NumberBase::Hexadecimal));
+        bits.deposit(NumberBase::Hexadecimal).unwrap();
+        assert_eq!(bits.pattern(), 0xFFFF_12FE);
+        assert_eq!(bits.field(), 0x12);
+        assert!(bits.entry().is_empty());
+
+        assert!(!bits.push_digit('2', NumberBase::Binary));
+        for c in "100000000".chars() {
+            bits.push_digit(c, NumberBase::Binary);
+        }
+        assert!(bits.deposit(NumberBase::Binary).is_err());
+    }
+
+    #[test]
+    fn test_grid_rows() {
+        let mut bits = BitInspector::new(0xA5 as f64, WordSize::default());
+        let rows = bits.grid_rows();
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[7].0, " 7 1 0 1 0  0 1 0 1 0");
+        assert_eq!(rows[7].1, "                  ^ ");
+
+        bits.move_cursor(3);
+        bits.set_high();
+        bits.move_cursor(-2);
+        bits.set_low();
+        assert_eq!(bits.grid_rows()[7].1, "            - - ^   ");
+    }
+
+    #[test]
+    fn test_float_reading() {
+        // A register holding an f32 pattern, read as a float
+        let mut bits = BitInspector::new(0x3FC0_0000 as f64, WordSize::default());
+        assert_eq!(bits.reading(), Reading::Integer);
+        bits.cycle_reading();
+        assert_eq!(bits.reading(), Reading::Float(FloatFormat::Double));
+        bits.cycle_reading();
+        assert_eq!(bits.label(), "f32");
+        assert_eq!(bits.value(), 1.5);
+
+        // Sign, exponent, mantissa in turn
+        bits.select_part();
+        assert_eq!(bits.field_range(), (31, 31));
+        bits.move_cursor(31);
+        bits.toggle();
+        assert_eq!(bits.value(), -1.5);
+        bits.select_part();
+        assert_eq!((bits.field_range(), bits.field()), ((30, 23), 127));
+        bits.select_part();
+        assert_eq!((bits.field_range(), bits.field()), ((22, 0), 0x40_0000));
+
+        // Narrowing to f16 keeps the low bits
+        bits.cycle_reading();
+        assert_eq!(bits.word().bits, 16);
+        assert_eq!(bits.cursor(), 15);
+        assert_eq!(bits.field_range(), (15, 0));
+        assert_eq!(bits.value(), 0.0);
+        bits.cycle_reading();
+        assert_eq!(bits.reading(), Reading::Integer);
+
+        // Fractions open as f64
+        let bits = BitInspector::new(0.1, WordSize::default());
+        assert_eq!(bits.reading(), Reading::Float(FloatFormat::Double));
+        assert_eq!(bits.pattern(), 0.1f64.to_bits());
+        assert_eq!(bits.value(), 0.1);
+    }
+}
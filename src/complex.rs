@@ -0,0 +1,284 @@
+//! Complex arithmetic, on principal branches
+
+use crate::functions::CalcError;
+use core::f64::consts::{FRAC_PI_2, LN_10, LN_2};
+use core::ops::{Add, Mul, Neg, Sub};
+
+/// A complex number `re + im·i`
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, w: Complex) -> Complex {
+        Complex::new(self.re + w.re, self.im + w.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, w: Complex) -> Complex {
+        Complex::new(self.re - w.re, self.im - w.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, w: Complex) -> Complex {
+        Complex::new(
+            self.re * w.re - self.im * w.im,
+            self.re * w.im + self.im * w.re,
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl Complex {
+    pub const ZERO: Complex = Complex { re: 0.0, im: 0.0 };
+    pub const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+    pub const I: Complex = Complex { re: 0.0, im: 1.0 };
+
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    /// The number with magnitude `r` at `theta` radians
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Self::new(r * theta.cos(), r * theta.sin())
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im == 0.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.re.is_finite() && self.im.is_finite()
+    }
+
+    /// Magnitude
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// Angle in radians, in (-π, π]
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn scale(self, k: f64) -> Self {
+        Self::new(self.re * k, self.im * k)
+    }
+
+    /// Apply `f` to both parts (`floor`, `round`, ...)
+    pub fn map(self, f: impl Fn(f64) -> f64) -> Self {
+        Self::new(f(self.re), f(self.im))
+    }
+
+    pub fn checked_div(self, w: Complex) -> Result<Self, CalcError> {
+        if w.is_zero() {
+            return Err(CalcError::DivideByZero);
+        }
+        // Smith's method keeps the intermediates in range
+        let result = if w.re.abs() >= w.im.abs() {
+            let r = w.im / w.re;
+            let d = w.re + w.im * r;
+            Self::new((self.re + self.im * r) / d, (self.im - self.re * r) / d)
+        } else {
+            let r = w.re / w.im;
+            let d = w.re * r + w.im;
+            Self::new((self.re * r + self.im) / d, (self.im * r - self.re) / d)
+        };
+        finite(result)
+    }
+
+    pub fn recip(self) -> Result<Self, CalcError> {
+        Self::ONE.checked_div(self)
+    }
+
+    pub fn exp(self) -> Result<Self, CalcError> {
+        finite(Self::from_polar(self.re.exp(), self.im))
+    }
+
+    pub fn ln(self) -> Result<Self, CalcError> {
+        if self.is_zero() {
+            return Err(CalcError::DomainError("ln undefined at 0"));
+        }
+        Ok(Self::new(self.abs().ln(), self.arg()))
+    }
+
+    pub fn log10(self) -> Result<Self, CalcError> {
+        Ok(self.ln()?.scale(1.0 / LN_10))
+    }
+
+    pub fn log2(self) -> Result<Self, CalcError> {
+        Ok(self.ln()?.scale(1.0 / LN_2))
+    }
+
+    /// Principal square root, with its real part ≥ 0
+    pub fn sqrt(self) -> Self {
+        if self.is_zero() {
+            return Self::ZERO;
+        }
+        let t = ((self.abs() + self.re.abs()) / 2.0).sqrt();
+        if self.re >= 0.0 {
+            Self::new(t, self.im / (2.0 * t))
+        } else {
+            Self::new(self.im.abs() / (2.0 * t), t.copysign(self.im))
+        }
+    }
+
+    /// Principal power `self^w`; whole exponents multiply exactly, so that
+    /// `i^2` is `-1` and not `-1+1.2e-16i`
+    pub fn pow(self, w: Complex) -> Result<Self, CalcError> {
+        if w.is_real() && w.re.fract() == 0.0 && w.re.abs() <= 64.0 {
+            let mut n = w.re.abs() as u32;
+            let (mut result, mut square) = (Self::ONE, self);
+            while n > 0 {
+                if n & 1 == 1 {
+                    result = result * square;
+                }
+                square = square * square;
+                n >>= 1;
+            }
+            return if w.re < 0.0 { result.recip() } else { finite(result) };
+        }
+        if self.is_zero() {
+            return if w.re > 0.0 {
+                Ok(Self::ZERO)
+            } else {
+                Err(CalcError::DomainError("invalid power"))
+            };
+        }
+        (w * self.ln()?).exp()
+    }
+
+    pub fn sin(self) -> Self {
+        Self::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    pub fn cos(self) -> Self {
+        Self::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+    }
+
+    pub fn tan(self) -> Result<Self, CalcError> {
+        self.sin().checked_div(self.cos())
+    }
+
+    pub fn sinh(self) -> Self {
+        Self::new(self.re.sinh() * self.im.cos(), self.re.cosh() * self.im.sin())
+    }
+
+    pub fn cosh(self) -> Self {
+        Self::new(self.re.cosh() * self.im.cos(), self.re.sinh() * self.im.sin())
+    }
+
+    pub fn tanh(self) -> Result<Self, CalcError> {
+        self.sinh().checked_div(self.cosh())
+    }
+
+    /// `-i ln(iz + √(1-z²))`
+    pub fn asin(self) -> Result<Self, CalcError> {
+        let w = (Self::I * self + (Self::ONE - self * self).sqrt()).ln()?;
+        Ok(Self::new(w.im, -w.re))
+    }
+
+    /// `π/2 - asin z`
+    pub fn acos(self) -> Result<Self, CalcError> {
+        Ok(Self::from(FRAC_PI_2) - self.asin()?)
+    }
+
+    /// `(i/2)(ln(1-iz) - ln(1+iz))`, undefined at ±i
+    pub fn atan(self) -> Result<Self, CalcError> {
+        let iz = Self::I * self;
+        let w = (Self::ONE - iz).ln()? - (Self::ONE + iz).ln()?;
+        Ok(Self::new(-w.im, w.re).scale(0.5))
+    }
+
+    /// `ln(z + √(z²+1))`
+    pub fn asinh(self) -> Result<Self, CalcError> {
+        (self + (self * self + Self::ONE).sqrt()).ln()
+    }
+
+    /// `ln(z + √(z+1)√(z-1))`
+    pub fn acosh(self) -> Result<Self, CalcError> {
+        (self + (self + Self::ONE).sqrt() * (self - Self::ONE).sqrt()).ln()
+    }
+
+    /// `(ln(1+z) - ln(1-z))/2`, undefined at ±1
+    pub fn atanh(self) -> Result<Self, CalcError> {
+        Ok(((Self::ONE + self).ln()? - (Self::ONE - self).ln()?).scale(0.5))
+    }
+}
+
+/// Reject results that overflowed
+fn finite(z: Complex) -> Result<Complex, CalcError> {
+    if z.re.is_infinite() || z.im.is_infinite() {
+        Err(CalcError::Overflow)
+    } else {
+        Ok(z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::f64::consts::PI;
+
+    fn close(z: Complex, re: f64, im: f64) -> bool {
+        (z.re - re).abs() < 1e-12 && (z.im - im).abs() < 1e-12
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let z = Complex::new(3.0, 4.0);
+        assert_eq!(z.abs(), 5.0);
+        assert_eq!(z * z.conj(), Complex::from(25.0));
+        assert!(close(z.checked_div(Complex::new(1.0, 2.0)).unwrap(), 2.2, -0.4));
+        assert!(z.checked_div(Complex::ZERO).is_err());
+        assert_eq!(Complex::I.pow(Complex::from(2.0)).unwrap(), Complex::from(-1.0));
+        assert!(close(Complex::I.pow(Complex::I).unwrap(), (-PI / 2.0).exp(), 0.0));
+        assert!(close(Complex::from(-8.0).pow(Complex::from(1.0 / 3.0)).unwrap(), 1.0, 3f64.sqrt()));
+        assert!(close(Complex::from_polar(2.0, PI / 2.0), 0.0, 2.0));
+    }
+
+    #[test]
+    fn test_functions() {
+        assert_eq!(Complex::from(-4.0).sqrt(), Complex::new(0.0, 2.0));
+        assert_eq!(Complex::new(-4.0, -0.0).sqrt(), Complex::new(0.0, -2.0));
+        assert!(close(Complex::new(0.0, 2.0).sqrt(), 1.0, 1.0));
+        assert!(close(Complex::from(-1.0).ln().unwrap(), 0.0, PI));
+        assert!(Complex::ZERO.ln().is_err());
+        assert!(close(Complex::new(0.0, PI).exp().unwrap(), -1.0, 0.0));
+        assert!(close(Complex::from(2.0).asin().unwrap(), PI / 2.0, -(2.0 + 3f64.sqrt()).ln()));
+        assert!(close(Complex::from(0.5).acosh().unwrap(), 0.0, PI / 3.0));
+        assert!(Complex::I.atan().is_err());
+        assert!(close(Complex::new(1.0, 1.0).sin().asin().unwrap(), 1.0, 1.0));
+        assert!(close(Complex::new(0.5, -0.25).tan().unwrap().atan().unwrap(), 0.5, -0.25));
+        assert!(close(Complex::new(0.5, 0.25).tanh().unwrap().atanh().unwrap(), 0.5, 0.25));
+        assert!(close(Complex::new(1.0, 2.0).cosh().acosh().unwrap(), 1.0, 2.0));
+        assert!(close(Complex::new(1.0, 0.5).sinh().asinh().unwrap(), 1.0, 0.5));
+    }
+}
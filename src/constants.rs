@@ -0,0 +1,110 @@
+//! Physical constants (CODATA 2018), written `_name` in expressions, or by
+//! their symbol where no variable or unit has it
+
+use crate::quantity::{Dims, Quantity};
+
+/// A named physical constant
+pub struct Constant {
+    /// Name in expressions
+    pub name: &'static str,
+    /// Symbol shown in the menu
+    pub symbol: &'static str,
+    pub value: f64,
+    /// SI unit of the value
+    pub unit: &'static str,
+    pub description: &'static str,
+    /// Dimensions of the unit
+    pub dims: Dims,
+}
+
+const fn constant(
+    name: &'static str,
+    symbol: &'static str,
+    value: f64,
+    unit: &'static str,
+    description: &'static str,
+    dims: Dims,
+) -> Constant {
+    Constant {
+        name,
+        symbol,
+        value,
+        unit,
+        description,
+        dims,
+    }
+}
+
+impl Constant {
+    /// The value with its dimensions
+    pub fn quantity(&self) -> Quantity {
+        Quantity::new(self.value, self.dims)
+    }
+}
+
+/// Every constant, in menu order
+pub const CONSTANTS: &[Constant] = &[
+    constant("_c", "c", 299_792_458.0, "m/s", "Speed of light in vacuum", Dims::SPEED),
+    constant("_h", "h", 6.626_070_15e-34, "J s", "Planck constant", Dims([2, 1, -1, 0, 0, 0, 0, 0])),
+    constant("_hbar", "ħ", 1.054_571_817e-34, "J s", "Reduced Planck constant", Dims([2, 1, -1, 0, 0, 0, 0, 0])),
+    constant("_kB", "k_B", 1.380_649e-23, "J/K", "Boltzmann constant", Dims([2, 1, -2, -1, 0, 0, 0, 0])),
+    constant("_NA", "N_A", 6.022_140_76e23, "1/mol", "Avogadro constant", Dims([0, 0, 0, 0, 0, 0, 0, -1])),
+    constant("_e", "e", 1.602_176_634e-19, "C", "Elementary charge", Dims([0, 0, 1, 0, 0, 0, 1, 0])),
+    constant("_me", "m_e", 9.109_383_701_5e-31, "kg", "Electron mass", Dims::MASS),
+    constant("_G", "G", 6.674_30e-11, "m³/(kg s²)", "Newtonian constant of gravitation", Dims([3, -1, -2, 0, 0, 0, 0, 0])),
+    constant("_g", "g", 9.806_65, "m/s²", "Standard acceleration of gravity", Dims([1, 0, -2, 0, 0, 0, 0, 0])),
+    constant("_R", "R", 8.314_462_618, "J/(mol K)", "Molar gas constant", Dims([2, 1, -2, -1, 0, 0, 0, -1])),
+    constant("_eps0", "ε0", 8.854_187_812_8e-12, "F/m", "Vacuum electric permittivity", Dims([-3, -1, 4, 0, 0, 0, 2, 0])),
+    constant("_mu0", "μ0", 1.256_637_062_12e-6, "N/A²", "Vacuum magnetic permeability", Dims([1, 1, -2, 0, 0, 0, -2, 0])),
+    constant("_mp", "m_p", 1.672_621_923_69e-27, "kg", "Proton mass", Dims::MASS),
+    constant("_mn", "m_n", 1.674_927_498_04e-27, "kg", "Neutron mass", Dims::MASS),
+    constant("_u", "u", 1.660_539_066_60e-27, "kg", "Atomic mass constant", Dims::MASS),
+    constant("_F", "F", 96_485.332_12, "C/mol", "Faraday constant", Dims([0, 0, 1, 0, 0, 0, 1, -1])),
+    constant("_sigma", "σ", 5.670_374_419e-8, "W/(m² K⁴)", "Stefan-Boltzmann constant", Dims([0, 1, -3, -4, 0, 0, 0, 0])),
+    constant("_alpha", "α", 7.297_352_569_3e-3, "", "Fine-structure constant", Dims::NONE),
+    constant("_a0", "a0", 5.291_772_109_03e-11, "m", "Bohr radius", Dims::LENGTH),
+    constant("_Rinf", "R∞", 10_973_731.568_160, "1/m", "Rydberg constant", Dims([-1, 0, 0, 0, 0, 0, 0, 0])),
+];
+
+/// Index of the constant called `name`. An exact match wins, so `_g` is
+/// standard gravity and `_G` gravitation; otherwise case is ignored, since
+/// the keyboard types lowercase names (`_kb`, `_na`).
+pub fn index_of(name: &str) -> Option<usize> {
+    CONSTANTS
+        .iter()
+        .position(|c| c.name == name)
+        .or_else(|| CONSTANTS.iter().position(|c| c.name.eq_ignore_ascii_case(name)))
+}
+
+/// Index of the constant whose symbol is `name`, case included (`k_B`,
+/// `G`, `ħ`). The symbol `e` is Euler's number; the elementary charge is
+/// only `_e`.
+pub fn by_symbol(name: &str) -> Option<usize> {
+    CONSTANTS.iter().position(|c| c.symbol == name && c.symbol != "e")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(index_of("_c").map(|i| CONSTANTS[i].value), Some(299_792_458.0));
+        assert_ne!(index_of("_G"), index_of("_g"));
+        assert_eq!(index_of("_kb"), index_of("_kB"));
+        assert_eq!(index_of("_x"), None);
+        assert_eq!(index_of("c"), None);
+
+        // Names are unique and readable by the tokenizer
+        for (i, c) in CONSTANTS.iter().enumerate() {
+            assert_eq!(index_of(c.name), Some(i));
+            assert!(c.name[1..].chars().all(|ch| ch.is_ascii_alphanumeric()), "{}", c.name);
+        }
+
+        assert_eq!(by_symbol("k_B"), index_of("_kB"));
+        assert_eq!(by_symbol("ħ"), index_of("_hbar"));
+        assert_eq!(by_symbol("G"), index_of("_G"));
+        assert_eq!(by_symbol("kb"), None);
+        assert_eq!(by_symbol("e"), None);
+    }
+}
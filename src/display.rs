@@ -1,12 +1,134 @@
 //! Number formatting and display utilities
 
-use crate::functions::NumberBase;
+use crate::functions::{from_radians, AngleMode, FloatFormat, NumberBase, WordSize};
+use crate::matrix::Matrix;
+use crate::quantity::Quantity;
+use crate::value::Value;
 use alloc::string::String;
 use alloc::vec::Vec;
 use core::fmt::Write;
 
-/// Format a number for display
-pub fn format_number(value: f64, base: NumberBase) -> String {
+/// Parts of a complex number this much smaller than the other are
+/// rounding noise (`e^(iπ)`), and shown as zero
+const NEGLIGIBLE: f64 = 1e-14;
+
+/// Format a number for display; non-decimal bases show the bit pattern of
+/// the value in `word`. A quantity is followed by its unit: `6 m/s`.
+pub fn format_number(value: impl Into<Quantity>, base: NumberBase, word: WordSize) -> String {
+    format_complex(value, base, word, None)
+}
+
+/// Format a number that may be complex: `3-4i`, or `5∠-53.1301024` with
+/// the angle in `polar` if given. A complex quantity is bracketed before
+/// its unit: `(3+4i) m`.
+pub fn format_complex(
+    value: impl Into<Quantity>,
+    base: NumberBase,
+    word: WordSize,
+    polar: Option<AngleMode>,
+) -> String {
+    let quantity = value.into();
+    let z = quantity.shown_complex();
+    let re = if z.re.abs() < NEGLIGIBLE * z.im.abs() { 0.0 } else { z.re };
+    let im = if z.im.abs() < NEGLIGIBLE * z.re.abs() { 0.0 } else { z.im };
+    let mut buf = String::new();
+    match polar {
+        _ if im == 0.0 => buf.push_str(&format_real(re, base, word)),
+        Some(angle_mode) => {
+            let theta = from_radians(im.atan2(re), angle_mode);
+            write!(buf, "{}∠{}", format_real(z.abs(), base, word), format_real(theta, base, word)).ok();
+        }
+        None => {
+            if re != 0.0 {
+                buf.push_str(&format_real(re, base, word));
+                buf.push(if im < 0.0 { '-' } else { '+' });
+            } else if im < 0.0 {
+                buf.push('-');
+            }
+            if im.abs() != 1.0 {
+                buf.push_str(&format_real(im.abs(), base, word));
+            }
+            buf.push('i');
+        }
+    }
+    if !quantity.is_plain() {
+        if im != 0.0 {
+            buf.insert(0, '(');
+            buf.push(')');
+        }
+        buf.push(' ');
+        buf.push_str(&quantity.label());
+    }
+    buf
+}
+
+/// Format a number, or a matrix in at most `width` characters
+pub fn format_value(
+    value: impl Into<Value>,
+    base: NumberBase,
+    word: WordSize,
+    polar: Option<AngleMode>,
+    width: usize,
+) -> String {
+    match value.into() {
+        Value::Number(q) => format_complex(q, base, word, polar),
+        Value::Matrix(m) => format_matrix(&m, width),
+    }
+}
+
+/// Format a matrix in one line, `[[1,2],[3,4]]`, or just its size,
+/// `[3×3 matrix]`, if that would take more than `width` characters
+pub fn format_matrix(m: &Matrix, width: usize) -> String {
+    let mut buf = String::from("[");
+    for r in 0..m.rows() {
+        if r > 0 {
+            buf.push(',');
+        }
+        buf.push('[');
+        for (c, &x) in m.row(r).iter().enumerate() {
+            if c > 0 {
+                buf.push(',');
+            }
+            buf.push_str(&format_real(x, NumberBase::Decimal, WordSize::default()));
+        }
+        buf.push(']');
+    }
+    buf.push(']');
+    if buf.chars().count() > width {
+        buf.clear();
+        let kind = if m.rows() == 1 { "vector" } else { "matrix" };
+        write!(buf, "[{}×{} {}]", m.rows(), m.cols(), kind).ok();
+    }
+    buf
+}
+
+/// Format a matrix entry in at most `width` characters (at least 8),
+/// giving up digits of the fraction, then of the mantissa
+pub fn format_cell(value: f64, width: usize) -> String {
+    let full = format_real(value, NumberBase::Decimal, WordSize::default());
+    if full.chars().count() <= width {
+        return full;
+    }
+    let fits = |s: &String| s.chars().count() <= width;
+    let fixed = (1e-4..1e10).contains(&value.abs());
+    let scientific = |decimals| {
+        let buf = format_scientific(value, decimals);
+        let (mantissa, exponent) = buf.split_once('e').unwrap_or((&buf, ""));
+        let mut mantissa = String::from(mantissa);
+        trim_trailing_zeros(&mut mantissa);
+        alloc::format!("{}e{}", mantissa, exponent)
+    };
+    (0..10)
+        .rev()
+        .filter(|_| fixed)
+        .map(|decimals| format_fixed(value, decimals))
+        .find(fits)
+        .or_else(|| (0..7).rev().map(scientific).find(fits))
+        .unwrap_or(full)
+}
+
+/// Format a plain number
+fn format_real(value: f64, base: NumberBase, word: WordSize) -> String {
     if value.is_nan() {
         return String::from("NaN");
     }
@@ -18,11 +140,18 @@ pub fn format_number(value: f64, base: NumberBase) -> String {
         };
     }
 
+    let pattern = match base {
+        NumberBase::Decimal => return format_decimal(value),
+        // Too large for any word: show the value rather than a bogus pattern
+        _ => match word.to_pattern(value.trunc()) {
+            Ok(pattern) => pattern,
+            Err(_) => return format_decimal(value),
+        },
+    };
     match base {
-        NumberBase::Decimal => format_decimal(value),
-        NumberBase::Hexadecimal => format_hex(value),
-        NumberBase::Octal => format_octal(value),
-        NumberBase::Binary => format_binary(value),
+        NumberBase::Hexadecimal => format_hex(pattern),
+        NumberBase::Octal => format_octal(pattern),
+        _ => format_binary(pattern),
     }
 }
 
@@ -36,24 +165,24 @@ fn format_decimal(value: f64) -> String {
 
     // Use scientific notation for very large or very small numbers
     if abs >= 1e10 || (abs != 0.0 && abs < 1e-4) {
-        format_scientific(value)
+        format_scientific(value, 7)
     } else {
-        format_fixed(value)
+        format_fixed(value, 10)
     }
 }
 
 /// Format in fixed-point notation, trimming trailing zeros
-fn format_fixed(value: f64) -> String {
+fn format_fixed(value: f64, decimals: usize) -> String {
     let mut buf = String::new();
-    write!(buf, "{:.10}", value).ok();
+    write!(buf, "{:.*}", decimals, value).ok();
     trim_trailing_zeros(&mut buf);
     buf
 }
 
 /// Format in scientific notation
-fn format_scientific(value: f64) -> String {
+fn format_scientific(value: f64, decimals: usize) -> String {
     let mut buf = String::new();
-    write!(buf, "{:.7e}", value).ok();
+    write!(buf, "{:.*e}", decimals, value).ok();
     // Clean up exponent: e+07 -> e7, e-03 -> e-3
     buf = buf.replace("e+", "e").replace("e0", "e").replace("e-0", "e-");
     buf
@@ -71,67 +200,104 @@ fn trim_trailing_zeros(s: &mut String) {
     }
 }
 
-/// Format as hexadecimal (integer only)
-fn format_hex(value: f64) -> String {
-    let int_val = value as i64;
+/// Format a bit pattern as hexadecimal
+fn format_hex(pattern: u64) -> String {
     let mut buf = String::new();
-    if int_val < 0 {
-        write!(buf, "-0x{:X}", -int_val).ok();
-    } else {
-        write!(buf, "0x{:X}", int_val).ok();
-    }
+    write!(buf, "0x{:X}", pattern).ok();
     buf
 }
 
-/// Format as octal (integer only)
-fn format_octal(value: f64) -> String {
-    let int_val = value as i64;
+/// Format a bit pattern as octal
+fn format_octal(pattern: u64) -> String {
     let mut buf = String::new();
-    if int_val < 0 {
-        write!(buf, "-0o{:o}", -int_val).ok();
-    } else {
-        write!(buf, "0o{:o}", int_val).ok();
-    }
+    write!(buf, "0o{:o}", pattern).ok();
     buf
 }
 
-/// Format as binary (integer only)
-fn format_binary(value: f64) -> String {
-    let int_val = value as i64;
-    let abs = int_val.abs();
+/// Format a bit pattern as binary
+fn format_binary(pattern: u64) -> String {
+    let mut buf = String::new();
+    write!(buf, "0b{:b}", pattern).ok();
+    buf
+}
 
-    // Limit binary display length
-    if abs > 0xFFFF {
-        let mut buf = String::new();
-        if int_val < 0 {
-            write!(buf, "-0b{:b}", abs).ok();
-        } else {
-            write!(buf, "0b{:b}", abs).ok();
-        }
-        // Truncate if too long
-        if buf.len() > 24 {
-            buf.truncate(21);
-            buf.push_str("...");
-        }
-        buf
+/// Bits per row of the grouped binary display
+pub const BINARY_ROW_BITS: u32 = 16;
+
+/// The full bit pattern of `value` in `word`, as rows of `row_bits` bits in
+/// nibble groups, most significant row first. Each row is framed by the
+/// indices of its highest and lowest bits: `31 0000 0000 1010 0101 16`.
+pub fn binary_rows(value: f64, word: WordSize, row_bits: u32) -> Option<Vec<String>> {
+    let pattern = word.to_pattern(value.trunc()).ok()?;
+    let row_bits = row_bits.clamp(4, word.bits);
+    let rows = (0..word.bits / row_bits)
+        .rev()
+        .map(|row| {
+            let low = row * row_bits;
+            let high = low + row_bits - 1;
+            let mut buf = String::new();
+            write!(buf, "{:>2}", high).ok();
+            for bit in (low..=high).rev() {
+                if (bit + 1) % 4 == 0 {
+                    buf.push(' ');
+                }
+                buf.push(if pattern >> bit & 1 == 1 { '1' } else { '0' });
+            }
+            write!(buf, " {}", low).ok();
+            buf
+        })
+        .collect();
+    Some(rows)
+}
+
+/// Breakdown of an IEEE-754 bit pattern: the raw sign, exponent and
+/// mantissa fields, then the value they make (`+1.5 × 2^0`) or the special
+/// value they encode
+pub fn format_float_fields(pattern: u64, format: FloatFormat) -> (String, String) {
+    let (sign, exponent, mantissa) = format.fields(pattern);
+    let mut fields = String::new();
+    write!(fields, "s {}  e 0x{:X}  m 0x{:X}", sign as u8, exponent, mantissa).ok();
+
+    let sign = if sign { '-' } else { '+' };
+    let max_exponent = (1 << format.exponent_bits()) - 1;
+    let fraction = mantissa as f64 / (1u64 << format.mantissa_bits()) as f64;
+    let mut meaning = String::new();
+    if exponent == max_exponent && mantissa == 0 {
+        write!(meaning, "{}∞", sign).ok();
+    } else if exponent == max_exponent {
+        // The top mantissa bit tells quiet from signalling NaNs
+        let quiet = mantissa >> (format.mantissa_bits() - 1) == 1;
+        write!(meaning, "NaN ({})", if quiet { "quiet" } else { "signalling" }).ok();
+    } else if exponent == 0 && mantissa == 0 {
+        write!(meaning, "{}0", sign).ok();
+    } else if exponent == 0 {
+        let power = 1 - format.bias();
+        write!(meaning, "{}{} × 2^{} subnormal", sign, format_decimal(fraction), power).ok();
     } else {
-        let mut buf = String::new();
-        if int_val < 0 {
-            write!(buf, "-0b{:b}", abs).ok();
-        } else {
-            write!(buf, "0b{:b}", abs).ok();
-        }
-        buf
+        let power = exponent as i32 - format.bias();
+        write!(meaning, "{}{} × 2^{}", sign, format_decimal(1.0 + fraction), power).ok();
     }
+    (fields, meaning)
 }
 
 /// Format for stack display (shorter, right-aligned)
-pub fn format_stack_number(value: f64, base: NumberBase) -> String {
-    let formatted = format_number(value, base);
+pub fn format_stack_number(
+    value: impl Into<Value>,
+    base: NumberBase,
+    word: WordSize,
+    polar: Option<AngleMode>,
+) -> String {
+    let formatted = format_value(value, base, word, polar, 20);
+    let len = formatted.chars().count();
+    if base == NumberBase::Binary && len > 20 {
+        // Keep the low-order bits, which change most
+        let mut s = String::from("…");
+        s.extend(formatted.chars().skip(len - 19));
+        return s;
+    }
     // Limit to reasonable display width
-    if formatted.len() > 20 {
-        let mut s = formatted;
-        s.truncate(17);
+    if len > 20 {
+        let mut s: String = formatted.chars().take(17).collect();
         s.push_str("...");
         s
     } else {
@@ -158,17 +324,17 @@ pub fn parse_number(input: &str) -> Option<f64> {
 
     // Check for hex prefix
     if input.starts_with("0x") || input.starts_with("0X") {
-        return i64::from_str_radix(&input[2..], 16).ok().map(|n| n as f64);
+        return u64::from_str_radix(&input[2..], 16).ok().map(|n| n as f64);
     }
 
     // Check for octal prefix
     if input.starts_with("0o") || input.starts_with("0O") {
-        return i64::from_str_radix(&input[2..], 8).ok().map(|n| n as f64);
+        return u64::from_str_radix(&input[2..], 8).ok().map(|n| n as f64);
     }
 
     // Check for binary prefix
     if input.starts_with("0b") || input.starts_with("0B") {
-        return i64::from_str_radix(&input[2..], 2).ok().map(|n| n as f64);
+        return u64::from_str_radix(&input[2..], 2).ok().map(|n| n as f64);
     }
 
     // Standard decimal parse
@@ -179,17 +345,21 @@ pub fn parse_number(input: &str) -> Option<f64> {
 #[derive(Clone)]
 pub struct HistoryEntry {
     pub expression: String,
-    pub result: f64,
+    pub result: Value,
 }
 
 impl HistoryEntry {
-    pub fn new(expression: String, result: f64) -> Self {
-        Self { expression, result }
+    pub fn new(expression: String, result: impl Into<Value>) -> Self {
+        Self {
+            expression,
+            result: result.into(),
+        }
     }
 
-    pub fn format(&self, base: NumberBase) -> String {
+    pub fn format(&self, base: NumberBase, word: WordSize, polar: Option<AngleMode>) -> String {
         let mut buf = String::new();
-        write!(buf, "{} = {}", self.expression, format_number(self.result, base)).ok();
+        let result = format_value(self.result, base, word, polar, 24);
+        write!(buf, "{} = {}", self.expression, result).ok();
         buf
     }
 }
@@ -224,6 +394,25 @@ impl History {
         &self.entries[start..]
     }
 
+    /// Up to `n` entries ending `skip` entries before the newest
+    pub fn window(&self, skip: usize, n: usize) -> &[HistoryEntry] {
+        let end = self.entries.len().saturating_sub(skip);
+        &self.entries[end.saturating_sub(n)..end]
+    }
+
+    /// Entry `age` steps back from the newest (0 = newest)
+    pub fn recent(&self, age: usize) -> Option<&HistoryEntry> {
+        self.entries.iter().rev().nth(age)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
     pub fn clear(&mut self) {
         self.entries.clear();
     }
@@ -245,14 +434,109 @@ mod tests {
 
     #[test]
     fn test_format_scientific() {
-        let s = format_number(1.23e15, NumberBase::Decimal);
+        let s = format_number(1.23e15, NumberBase::Decimal, WordSize::default());
         assert!(s.contains('e'));
     }
 
     #[test]
     fn test_format_hex() {
-        assert_eq!(format_hex(255.0), "0xFF");
-        assert_eq!(format_hex(16.0), "0x10");
+        assert_eq!(format_hex(255), "0xFF");
+        assert_eq!(format_hex(16), "0x10");
+    }
+
+    #[test]
+    fn test_format_word() {
+        use crate::functions::SignMode;
+        let hex = |value, bits, sign| format_number(value, NumberBase::Hexadecimal, WordSize::new(bits, sign));
+        assert_eq!(hex(-1.0, 8, SignMode::TwosComplement), "0xFF");
+        assert_eq!(hex(-1.0, 16, SignMode::OnesComplement), "0xFFFE");
+        assert_eq!(hex(-1.0, 64, SignMode::TwosComplement), "0xFFFFFFFFFFFFFFFF");
+        assert_eq!(hex(255.9, 8, SignMode::Unsigned), "0xFF");
+        assert_eq!(hex(1e30, 64, SignMode::Unsigned), "1.0000000e30");
+        assert_eq!(
+            format_number(-2.0, NumberBase::Binary, WordSize::new(8, SignMode::TwosComplement)),
+            "0b11111110"
+        );
+    }
+
+    #[test]
+    fn test_binary_rows() {
+        use crate::functions::SignMode;
+        let word = |bits| WordSize::new(bits, SignMode::TwosComplement);
+        assert_eq!(binary_rows(165.0, word(8), BINARY_ROW_BITS).unwrap(), [" 7 1010 0101 0"]);
+        assert_eq!(
+            binary_rows(-2.0, word(32), BINARY_ROW_BITS).unwrap(),
+            ["31 1111 1111 1111 1111 16", "15 1111 1111 1111 1110 0"]
+        );
+        assert_eq!(binary_rows(1.0, word(64), 8).unwrap().len(), 8);
+        assert_eq!(binary_rows(1.0, word(64), 8).unwrap()[7], " 7 0000 0001 0");
+        assert!(binary_rows(1e30, word(64), BINARY_ROW_BITS).is_none());
+
+        let stacked = format_stack_number(-1.0, NumberBase::Binary, word(32), None);
+        assert_eq!(stacked, "…1111111111111111111");
+        assert_eq!(stacked.chars().count(), 20);
+    }
+
+    #[test]
+    fn test_float_fields() {
+        let fields = |pattern, format| format_float_fields(pattern, format);
+        assert_eq!(
+            fields(0x3FC0_0000, FloatFormat::Single),
+            ("s 0  e 0x7F  m 0x400000".into(), "+1.5 × 2^0".into())
+        );
+        assert_eq!(fields(0xC100, FloatFormat::Half).1, "-1.25 × 2^1");
+        assert_eq!(fields(0x0200, FloatFormat::Half).1, "+0.5 × 2^-14 subnormal");
+        assert_eq!(fields(0x8000, FloatFormat::Half).1, "-0");
+        assert_eq!(fields(0x7C00, FloatFormat::Half).1, "+∞");
+        assert_eq!(fields(0x7E00, FloatFormat::Half).1, "NaN (quiet)");
+        assert_eq!(fields(0x7FF0_0000_0000_0001, FloatFormat::Double).1, "NaN (signalling)");
+    }
+
+    #[test]
+    fn test_format_quantity() {
+        use crate::quantity::Dims;
+        let word = WordSize::default();
+        let speed = Quantity::new(6.0, Dims::SPEED);
+        assert_eq!(format_number(speed, NumberBase::Decimal, word), "6 m/s");
+        let ft = crate::units::index_of("ft").unwrap();
+        let feet = Quantity::new(0.9144, Dims::LENGTH).express_in(ft).unwrap();
+        assert_eq!(format_number(feet, NumberBase::Decimal, word), "3 ft");
+        assert_eq!(format_number(Quantity::new(2.0, Dims::ENERGY), NumberBase::Decimal, word), "2 J");
+    }
+
+    #[test]
+    fn test_format_complex() {
+        use crate::complex::Complex;
+        let (dec, word) = (NumberBase::Decimal, WordSize::default());
+        let rect = |re, im| format_number(Complex::new(re, im), dec, word);
+        assert_eq!(rect(3.0, 4.0), "3+4i");
+        assert_eq!(rect(3.0, -4.5), "3-4.5i");
+        assert_eq!(rect(0.0, 1.0), "i");
+        assert_eq!(rect(0.0, -2.0), "-2i");
+        assert_eq!(rect(-1.0, 1.2246e-16), "-1");
+
+        let polar = |re, im, mode| format_complex(Complex::new(re, im), dec, word, Some(mode));
+        assert_eq!(polar(0.0, 2.0, AngleMode::Degrees), "2∠90");
+        assert_eq!(polar(-1.0, 0.0, AngleMode::Degrees), "-1");
+        assert_eq!(polar(1.0, -1.0, AngleMode::Radians), "1.4142135624∠-0.7853981634");
+
+        let volts = Quantity::new(0.0, crate::quantity::Dims::LENGTH).with_value(Complex::new(1.0, 1.0));
+        assert_eq!(format_number(volts, dec, word), "(1+i) m");
+    }
+
+    #[test]
+    fn test_format_matrix() {
+        let m = Matrix::from_rows(&[alloc::vec![1.0, -2.5], alloc::vec![3.0, 4.0]]).unwrap();
+        assert_eq!(format_matrix(&m, 40), "[[1,-2.5],[3,4]]");
+        assert_eq!(format_matrix(&m, 10), "[2×2 matrix]");
+        let v = Matrix::from_rows(&[alloc::vec![1.0, 2.0, 3.0]]).unwrap();
+        assert_eq!(format_matrix(&v, 5), "[1×3 vector]");
+
+        assert_eq!(format_cell(2.0 / 3.0, 8), "0.666667");
+        assert_eq!(format_cell(-1234.56789, 8), "-1234.57");
+        assert_eq!(format_cell(1.5e-7, 8), "1.5e-7");
+        assert_eq!(format_cell(123_456_789_012.0, 8), "1.235e11");
+        assert_eq!(format_cell(-0.000_123_456, 8), "-0.00012");
     }
 
     #[test]
@@ -262,4 +546,23 @@ mod tests {
         assert_eq!(parse_number("0b1010"), Some(10.0));
         assert_eq!(parse_number("0o17"), Some(15.0));
     }
+
+    #[test]
+    fn test_history_browsing() {
+        let mut history = History::new(3);
+        for i in 1..=4 {
+            history.add(HistoryEntry::new(alloc::format!("{}+0", i), i as f64));
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.recent(0).map(|e| e.result), Some(Value::from(4.0)));
+        assert_eq!(history.recent(2).map(|e| e.expression.as_str()), Some("2+0"));
+        assert!(history.recent(3).is_none());
+
+        let results = |entries: &[HistoryEntry]| {
+            entries.iter().map(|e| e.result.number().unwrap().value).collect::<Vec<_>>()
+        };
+        assert_eq!(results(history.window(0, 2)), [3.0, 4.0]);
+        assert_eq!(results(history.window(1, 5)), [2.0, 3.0]);
+        assert!(history.window(4, 2).is_empty());
+    }
 }
@@ -0,0 +1,512 @@
+//! Equation solver: a real root of an expression in one unknown, by Brent's
+//! method once a sign change is bracketed and the secant method until then
+
+use crate::algebraic::{AlgebraicParser, EvalContext, Spanned, Token};
+use crate::functions::{CalcError, Op};
+use crate::value::Value;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Steps either method may take before giving up
+pub const MAX_ITERATIONS: usize = 200;
+
+/// Where the search for a root starts
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Start {
+    /// A single guess, from which the secant method looks for the root
+    Guess(f64),
+    /// Two points between which the expression changes sign
+    Bracket(f64, f64),
+}
+
+/// A root, with what it took to find it
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Root {
+    pub x: f64,
+    pub iterations: usize,
+    /// The expression's value at `x`
+    pub residual: f64,
+}
+
+/// Smallest step worth taking from `x`, where `scale` is the size of the
+/// starting points: a root at zero is taken to within rounding of them,
+/// since a multiple root there only ever creeps closer
+fn tolerance(x: f64, scale: f64) -> f64 {
+    2.0 * f64::EPSILON * x.abs() + f64::EPSILON * scale + f64::MIN_POSITIVE
+}
+
+/// A root of `f` from `start`. A bracket must hold a sign change; a guess
+/// is followed by secant steps, switching to Brent's method as soon as two
+/// of them straddle the root.
+pub fn find_root(
+    mut f: impl FnMut(f64) -> Result<f64, CalcError>,
+    start: Start,
+) -> Result<Root, CalcError> {
+    match start {
+        Start::Bracket(a, b) => {
+            let (fa, fb) = (f(a)?, f(b)?);
+            if fa != 0.0 && fb != 0.0 && fa.signum() == fb.signum() {
+                return Err(CalcError::DomainError("solve: no sign change in bracket"));
+            }
+            brent(f, (a, fa), (b, fb), a.abs().max(b.abs()), 0)
+        }
+        Start::Guess(x) => secant(f, x),
+    }
+}
+
+/// Secant steps from `x0`, handing over to Brent's method at a sign change
+fn secant(mut f: impl FnMut(f64) -> Result<f64, CalcError>, x0: f64) -> Result<Root, CalcError> {
+    let (mut x0, mut f0) = (x0, f(x0)?);
+    if f0 == 0.0 {
+        return Ok(Root { x: x0, iterations: 0, residual: f0 });
+    }
+    let mut x1 = x0 + 1e-4 * x0.abs().max(1.0);
+    let mut f1 = f(x1)?;
+    let scale = if x0 == 0.0 { x1.abs() } else { x0.abs() };
+    for iterations in 1..=MAX_ITERATIONS {
+        if f1 == 0.0 {
+            return Ok(Root { x: x1, iterations, residual: f1 });
+        }
+        if f0.signum() != f1.signum() {
+            return brent(f, (x0, f0), (x1, f1), scale, iterations);
+        }
+        let x2 = x1 - f1 * (x1 - x0) / (f1 - f0);
+        if !x2.is_finite() {
+            break;
+        }
+        let f2 = f(x2)?;
+        if (x2 - x1).abs() <= tolerance(x2, scale) {
+            return Ok(Root { x: x2, iterations, residual: f2 });
+        }
+        (x0, f0, x1, f1) = (x1, f1, x2, f2);
+    }
+    Err(CalcError::NoConvergence)
+}
+
+/// Brent's method on a bracket `a`, `b` holding a sign change: inverse
+/// quadratic or secant steps when they land well inside it, bisection when
+/// they would not
+fn brent(
+    mut f: impl FnMut(f64) -> Result<f64, CalcError>,
+    (mut a, mut fa): (f64, f64),
+    (mut b, mut fb): (f64, f64),
+    scale: f64,
+    mut iterations: usize,
+) -> Result<Root, CalcError> {
+    // `b` is the best estimate and `c` the point across the root from it
+    let (mut c, mut fc) = (b, fb);
+    let (mut d, mut e) = (b - a, b - a);
+    while iterations < MAX_ITERATIONS {
+        if fb != 0.0 && fb.signum() == fc.signum() {
+            (c, fc) = (a, fa);
+            d = b - a;
+            e = d;
+        }
+        if fc.abs() < fb.abs() {
+            (a, fa) = (b, fb);
+            (b, fb) = (c, fc);
+            (c, fc) = (a, fa);
+        }
+        let tol = tolerance(b, scale);
+        let m = (c - b) / 2.0;
+        if fb == 0.0 || m.abs() <= tol {
+            return Ok(Root { x: b, iterations, residual: fb });
+        }
+        if e.abs() >= tol && fa.abs() > fb.abs() {
+            let s = fb / fa;
+            let (mut p, mut q) = if a == c {
+                (2.0 * m * s, 1.0 - s)
+            } else {
+                let (q, r) = (fa / fc, fb / fc);
+                (s * (2.0 * m * q * (q - r) - (b - a) * (r - 1.0)), (q - 1.0) * (r - 1.0) * (s - 1.0))
+            };
+            if p > 0.0 {
+                q = -q;
+            } else {
+                p = -p;
+            }
+            if 2.0 * p < (3.0 * m * q - (tol * q).abs()).min((e * q).abs()) {
+                e = d;
+                d = p / q;
+            } else {
+                d = m;
+                e = m;
+            }
+        } else {
+            d = m;
+            e = m;
+        }
+        (a, fa) = (b, fb);
+        b += if d.abs() > tol { d } else { tol.copysign(m) };
+        fb = f(b)?;
+        iterations += 1;
+    }
+    Err(CalcError::NoConvergence)
+}
+
+/// An equation `lhs = rhs`, or an expression taken as `= 0`, in one
+/// unknown
+pub struct Equation {
+    unknown: String,
+    lhs: Vec<Spanned>,
+    rhs: Option<Vec<Spanned>>,
+}
+
+impl Equation {
+    /// Parse `input`; the unknown is its one name that is not a variable,
+    /// unit or constant, or its only name if every one is
+    pub fn parse(input: &str, ctx: &EvalContext) -> Result<Self, CalcError> {
+        // Spans, in tokens and errors, count from the start of `input`
+        let compile = |expr: &str, offset: usize| {
+            if expr.trim().is_empty() {
+                return Err(CalcError::SyntaxError("Missing expression".into()));
+            }
+            let mut postfix = AlgebraicParser::tokenize_with(expr, ctx.base, &|name| ctx.functions.contains(name))
+                .and_then(AlgebraicParser::to_postfix)
+                .map_err(|e| shift(e, offset))?;
+            for spanned in postfix.iter_mut() {
+                spanned.span = spanned.span.shift(offset);
+            }
+            Ok(postfix)
+        };
+        let (lhs, rhs) = match AlgebraicParser::find_assign_equals(input) {
+            Some(pos) => {
+                let offset = input[..=pos].chars().count();
+                (compile(&input[..pos], 0)?, Some(compile(&input[pos + 1..], offset)?))
+            }
+            None => (compile(input, 0)?, None),
+        };
+
+        let mut names: Vec<&str> = Vec::new();
+        for spanned in lhs.iter().chain(rhs.iter().flatten()) {
+            if let Token::Variable(name) = &spanned.token {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+        let free: Vec<&str> = names.iter().copied().filter(|name| !ctx.is_bound(name)).collect();
+        let unknown = match (free.as_slice(), names.as_slice()) {
+            ([name], _) | ([], [name]) => String::from(*name),
+            _ => return Err(CalcError::DomainError("solve: needs one unknown")),
+        };
+        Ok(Self { unknown, lhs, rhs })
+    }
+
+    pub fn unknown(&self) -> &str {
+        &self.unknown
+    }
+
+    /// `lhs - rhs` with the unknown set to `x`, in SI units if the sides
+    /// are quantities
+    pub fn evaluate(&self, x: f64, ctx: &EvalContext) -> Result<f64, CalcError> {
+        let locals = [(self.unknown.as_str(), Value::from(x))];
+        let ctx = EvalContext { locals: &locals, ..*ctx };
+        let side = |postfix: &[Spanned]| AlgebraicParser::evaluate_in(postfix, &ctx)?.number();
+        let lhs = side(&self.lhs)?;
+        let difference = match &self.rhs {
+            Some(rhs) => Op::Sub.evaluate_quantity(lhs, side(rhs)?, ctx.angle_mode, ctx.complex)?,
+            None => lhs,
+        };
+        if difference.is_real() {
+            Ok(difference.value)
+        } else {
+            Err(CalcError::DomainError("needs a real number"))
+        }
+    }
+
+    pub fn solve(&self, start: Start, ctx: &EvalContext) -> Result<Root, CalcError> {
+        find_root(|x| self.evaluate(x, ctx), start)
+    }
+}
+
+/// Read a guess `a`, or a bracket `a, b`; each may be an expression. An
+/// empty start is a guess of 0.
+pub fn parse_start(input: &str, ctx: &EvalContext) -> Result<Start, CalcError> {
+    let value = |expr: &str, offset: usize| {
+        AlgebraicParser::calculate_in(expr, ctx)
+            .and_then(|v| v.number()?.plain())
+            .map_err(|e| shift(e, offset))
+    };
+    if input.trim().is_empty() {
+        return Ok(Start::Guess(0.0));
+    }
+    // Split at a comma outside any parentheses, so that `max(1,2)` is one
+    // guess
+    let mut depth = 0i32;
+    let comma = input.char_indices().find_map(|(i, c)| {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => return Some(i),
+            _ => {}
+        }
+        None
+    });
+    match comma {
+        Some(i) => {
+            let offset = input[..=i].chars().count();
+            Ok(Start::Bracket(value(&input[..i], 0)?, value(&input[i + 1..], offset)?))
+        }
+        None => Ok(Start::Guess(value(input, 0)?)),
+    }
+}
+
+/// Move an error's span right by `offset` characters, to where its part
+/// starts in the whole line
+fn shift(e: CalcError, offset: usize) -> CalcError {
+    match e.span() {
+        Some(span) => e.at(span.shift(offset)),
+        None => e,
+    }
+}
+
+/// Which line of the solver screen is being typed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Field {
+    Equation,
+    Start,
+}
+
+/// The solver screen: an equation and where to start, then the root found
+pub struct EquationSolver {
+    equation: String,
+    start: String,
+    field: Field,
+    unknown: String,
+    root: Option<Root>,
+}
+
+impl Default for EquationSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EquationSolver {
+    pub fn new() -> Self {
+        Self {
+            equation: String::new(),
+            start: String::new(),
+            field: Field::Equation,
+            unknown: String::new(),
+            root: None,
+        }
+    }
+
+    pub fn equation(&self) -> &str {
+        &self.equation
+    }
+
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Move to the other line
+    pub fn switch_field(&mut self) {
+        self.field = match self.field {
+            Field::Equation => Field::Start,
+            Field::Start => Field::Equation,
+        };
+    }
+
+    fn text_mut(&mut self) -> &mut String {
+        match self.field {
+            Field::Equation => &mut self.equation,
+            Field::Start => &mut self.start,
+        }
+    }
+
+    /// Type a character into the field being edited; false, and nothing
+    /// typed, for a key that cannot be part of an expression, such as an
+    /// arrow or a function key
+    pub fn push(&mut self, c: char) -> bool {
+        let typed = c.is_ascii_alphanumeric() || " _.,=+-*/^%&|~<>!()[]×÷".contains(c);
+        if typed {
+            self.text_mut().push(c);
+        }
+        typed
+    }
+
+    pub fn backspace(&mut self) {
+        self.text_mut().pop();
+    }
+
+    /// Solve, keeping the root found. A mistake in the equation or the
+    /// start moves the editing to that line, which the error's span is in.
+    pub fn solve(&mut self, ctx: &EvalContext) -> Result<&Root, CalcError> {
+        let equation = Equation::parse(&self.equation, ctx).inspect_err(|_| self.field = Field::Equation)?;
+        let start = parse_start(&self.start, ctx).inspect_err(|_| self.field = Field::Start)?;
+        let root = equation.solve(start, ctx).inspect_err(|e| {
+            if e.span().is_some() {
+                self.field = Field::Equation;
+            }
+        })?;
+        self.unknown = String::from(equation.unknown());
+        Ok(self.root.insert(root))
+    }
+
+    /// The unknown solved for
+    pub fn unknown(&self) -> &str {
+        &self.unknown
+    }
+
+    pub fn root(&self) -> Option<&Root> {
+        self.root.as_ref()
+    }
+
+    /// Go back to editing the equation
+    pub fn edit(&mut self) {
+        self.root = None;
+    }
+}
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::{AngleMode, Span};
+    use crate::memory::Variables;
+    use crate::userfunc::UserFunctions;
+
+    fn solve(input: &str, start: Start) -> Result<Root, CalcError> {
+        let variables = Variables::new();
+        let functions = UserFunctions::new();
+        let ctx = EvalContext::new(0.0, AngleMode::Radians, &variables, &functions);
+        Equation::parse(input, &ctx)?.solve(start, &ctx)
+    }
+
+    #[test]
+    fn test_find_root() {
+        let cubic = |x: f64| Ok(x * x * x - 2.0 * x - 5.0);
+        let root = find_root(cubic, Start::Bracket(2.0, 3.0)).unwrap();
+        assert!((root.x - 2.0945514815423265).abs() < 1e-14, "{:?}", root);
+        assert!(root.residual.abs() < 1e-12);
+        assert!(root.iterations < 15);
+        let root = find_root(cubic, Start::Guess(2.0)).unwrap();
+        assert!((root.x - 2.0945514815423265).abs() < 1e-14, "{:?}", root);
+
+        // A bracket whose ends are on the same side, and a function with
+        // no real root
+        assert!(matches!(find_root(cubic, Start::Bracket(3.0, 4.0)), Err(CalcError::DomainError(_))));
+        assert!(matches!(find_root(|x| Ok(x * x + 1.0), Start::Guess(0.0)), Err(CalcError::NoConvergence)));
+        // Errors evaluating the function are passed on
+        assert!(find_root(|x| if x < 0.0 { Err(CalcError::DomainError("ln")) } else { Ok(x - 1.0) }, Start::Guess(-1.0)).is_err());
+    }
+
+    #[test]
+    fn test_bisection_fallback() {
+        // A step function defeats interpolation; bisection still closes in
+        let step = |x: f64| Ok(if x < 0.3 { -1.0 } else { 1.0 });
+        let root = find_root(step, Start::Bracket(0.0, 1.0)).unwrap();
+        assert!((root.x - 0.3).abs() < 1e-12, "{:?}", root);
+        // A root at zero
+        let root = find_root(|x| Ok(x.sin()), Start::Bracket(-1.0, 2.0)).unwrap();
+        assert!(root.x.abs() < 1e-15, "{:?}", root);
+        let root = find_root(|x| Ok(x * x * x), Start::Bracket(-1.0, 2.0)).unwrap();
+        assert!(root.x.abs() < 1e-15, "{:?}", root);
+    }
+
+    #[test]
+    fn test_multiple_root_at_zero() {
+        // The secant method only creeps towards a multiple root, so the
+        // step never shrinks relative to x; it stops at the start's scale
+        for (n, x0, near) in [(2, 1.0, 1e-15), (2, 0.001, 1e-18), (3, 1.0, 1e-15), (3, -3.0, 1e-14)] {
+            let root = find_root(|x| Ok(x.powi(n)), Start::Guess(x0)).unwrap();
+            assert!(root.x.abs() < near, "x^{} from {}: {:?}", n, x0, root);
+        }
+        // A small root is still found to full relative precision
+        let root = find_root(|x| Ok(x * x - 1e-40), Start::Guess(1e-19)).unwrap();
+        assert!((root.x - 1e-20).abs() < 1e-34, "{:?}", root);
+    }
+
+    #[test]
+    fn test_equation() {
+        let root = solve("x^3-2x-5=0", Start::Guess(2.0)).unwrap();
+        assert!((root.x - 2.0945514815423265).abs() < 1e-12);
+        let root = solve("cos(t) = t", Start::Bracket(0.0, 1.0)).unwrap();
+        assert!((root.x - 0.7390851332151607).abs() < 1e-12);
+        let root = solve("2^n - 1000", Start::Guess(5.0)).unwrap();
+        assert!((root.x - 1000f64.log2()).abs() < 1e-12);
+
+        assert!(matches!(solve("x*y=1", Start::Guess(1.0)), Err(CalcError::DomainError("solve: needs one unknown"))));
+        assert!(matches!(solve("2+2=4", Start::Guess(1.0)), Err(CalcError::DomainError("solve: needs one unknown"))));
+        assert!(solve("x^2=", Start::Guess(1.0)).is_err());
+
+        // Units and constants are not unknowns
+        let root = solve("3 m - x m", Start::Guess(1.0)).unwrap();
+        assert!((root.x - 3.0).abs() < 1e-12, "{:?}", root);
+        let root = solve("c*x = 1 m/s", Start::Guess(1.0)).unwrap();
+        assert!((root.x - 1.0 / 299_792_458.0).abs() < 1e-20, "{:?}", root);
+
+        // A defined variable is a constant, unless it is the only name
+        let mut variables = Variables::new();
+        variables.set("a", Value::from(4.0)).unwrap();
+        let functions = UserFunctions::new();
+        let ctx = EvalContext::new(0.0, AngleMode::Radians, &variables, &functions);
+        let equation = Equation::parse("x^2 = a", &ctx).unwrap();
+        assert_eq!(equation.unknown(), "x");
+        assert!((equation.solve(Start::Guess(1.0), &ctx).unwrap().x - 2.0).abs() < 1e-12);
+        assert_eq!(Equation::parse("a^2 = 9", &ctx).unwrap().unknown(), "a");
+
+        assert_eq!(parse_start("", &ctx).unwrap(), Start::Guess(0.0));
+        assert_eq!(parse_start("a/2", &ctx).unwrap(), Start::Guess(2.0));
+        assert_eq!(parse_start("max(1,2), 3", &ctx).unwrap(), Start::Bracket(2.0, 3.0));
+    }
+
+    #[test]
+    fn test_screen() {
+        let variables = Variables::new();
+        let functions = UserFunctions::new();
+        let ctx = EvalContext::new(0.0, AngleMode::Radians, &variables, &functions);
+        let mut solver = EquationSolver::new();
+        "x^2=2".chars().for_each(|c| {
+            solver.push(c);
+        });
+        // Arrows and function keys are not typed
+        assert!(!solver.push('←') && !solver.push('\u{F704}') && !solver.push('\u{0F}'));
+        assert_eq!(solver.equation(), "x^2=2");
+        solver.switch_field();
+        assert_eq!(solver.field(), Field::Start);
+        "1,2".chars().for_each(|c| {
+            solver.push(c);
+        });
+        solver.backspace();
+        assert_eq!(solver.start(), "1,");
+        assert!(solver.solve(&ctx).is_err());
+        assert!(solver.push('2'));
+        let root = *solver.solve(&ctx).unwrap();
+        assert!((root.x - 2f64.sqrt()).abs() < 1e-14);
+        assert_eq!(solver.unknown(), "x");
+        assert!(solver.root().is_some());
+        solver.edit();
+        assert!(solver.root().is_none());
+        assert_eq!(solver.equation(), "x^2=2");
+
+        // Errors point into the line they are in, and editing moves there
+        let mut solver = EquationSolver::new();
+        "x^2 = (2".chars().for_each(|c| {
+            solver.push(c);
+        });
+        solver.switch_field();
+        let err = solver.solve(&ctx).unwrap_err();
+        assert_eq!(err.detail(), "Mismatched parentheses");
+        assert_eq!(err.span(), Some(Span::new(6, 7)));
+        assert_eq!(solver.field(), Field::Equation);
+        solver.backspace();
+        solver.backspace();
+        solver.push('2');
+        solver.switch_field();
+        "1, foo".chars().for_each(|c| {
+            solver.push(c);
+        });
+        let err = solver.solve(&ctx).unwrap_err();
+        assert_eq!(err.span(), Some(Span::new(3, 6)));
+        assert_eq!(solver.field(), Field::Start);
+    }
+}
@@ -1,5 +1,11 @@
 //! Scientific function implementations
 
+use crate::complex::Complex;
+use crate::constants::CONSTANTS;
+use crate::matrix::{Matrix, MAX_SIZE};
+use crate::quantity::{Dims, Quantity};
+use crate::units;
+use crate::value::Value;
 use core::f64::consts::{E, PI};
 
 /// Angle unit for trig functions
@@ -45,6 +51,58 @@ impl AngleMode {
     }
 }
 
+/// Whether real arguments may give complex results, and how complex
+/// numbers are shown
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum ComplexMode {
+    /// `sqrt(-1)` is an error; `i` still makes complex numbers
+    #[default]
+    Real,
+    /// `a+bi`
+    Rectangular,
+    /// `r∠θ`, with θ in the angle mode
+    Polar,
+}
+
+impl ComplexMode {
+    pub fn cycle(&self) -> Self {
+        match self {
+            ComplexMode::Real => ComplexMode::Rectangular,
+            ComplexMode::Rectangular => ComplexMode::Polar,
+            ComplexMode::Polar => ComplexMode::Real,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ComplexMode::Real => "REAL",
+            ComplexMode::Rectangular => "a+bi",
+            ComplexMode::Polar => "r∠θ",
+        }
+    }
+
+    /// May real arguments give complex results?
+    pub fn is_complex(&self) -> bool {
+        *self != ComplexMode::Real
+    }
+
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            ComplexMode::Real => 0,
+            ComplexMode::Rectangular => 1,
+            ComplexMode::Polar => 2,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Self {
+        match v {
+            1 => ComplexMode::Rectangular,
+            2 => ComplexMode::Polar,
+            _ => ComplexMode::Real,
+        }
+    }
+}
+
 /// Number display base
 #[derive(Clone, Copy, PartialEq, Debug, Default)]
 pub enum NumberBase {
@@ -74,6 +132,15 @@ impl NumberBase {
         }
     }
 
+    pub fn radix(&self) -> u32 {
+        match self {
+            NumberBase::Decimal => 10,
+            NumberBase::Hexadecimal => 16,
+            NumberBase::Octal => 8,
+            NumberBase::Binary => 2,
+        }
+    }
+
     pub fn to_u8(&self) -> u8 {
         match self {
             NumberBase::Decimal => 0,
@@ -93,6 +160,124 @@ impl NumberBase {
     }
 }
 
+/// IEEE-754 binary floating point formats
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FloatFormat {
+    Half,
+    Single,
+    Double,
+}
+
+impl FloatFormat {
+    pub fn cycle(&self) -> Self {
+        match self {
+            FloatFormat::Double => FloatFormat::Single,
+            FloatFormat::Single => FloatFormat::Half,
+            FloatFormat::Half => FloatFormat::Double,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FloatFormat::Half => "f16",
+            FloatFormat::Single => "f32",
+            FloatFormat::Double => "f64",
+        }
+    }
+
+    /// Width of the whole pattern
+    pub fn bits(&self) -> u32 {
+        match self {
+            FloatFormat::Half => 16,
+            FloatFormat::Single => 32,
+            FloatFormat::Double => 64,
+        }
+    }
+
+    pub fn exponent_bits(&self) -> u32 {
+        match self {
+            FloatFormat::Half => 5,
+            FloatFormat::Single => 8,
+            FloatFormat::Double => 11,
+        }
+    }
+
+    /// Stored fraction bits (the leading 1 is implicit)
+    pub fn mantissa_bits(&self) -> u32 {
+        self.bits() - self.exponent_bits() - 1
+    }
+
+    pub fn bias(&self) -> i32 {
+        (1 << (self.exponent_bits() - 1)) - 1
+    }
+
+    /// Split a pattern into sign, biased exponent and mantissa
+    pub fn fields(&self, pattern: u64) -> (bool, u64, u64) {
+        let m = self.mantissa_bits();
+        let sign = pattern >> (self.bits() - 1) & 1 == 1;
+        let exponent = pattern >> m & ((1 << self.exponent_bits()) - 1);
+        let mantissa = pattern & ((1 << m) - 1);
+        (sign, exponent, mantissa)
+    }
+
+    /// Bit pattern of the nearest value in this format (ties to even)
+    pub fn encode(&self, x: f64) -> u64 {
+        match self {
+            FloatFormat::Double => x.to_bits(),
+            FloatFormat::Single => (x as f32).to_bits() as u64,
+            FloatFormat::Half => f16_bits(x) as u64,
+        }
+    }
+
+    /// Value of a pattern read in this format
+    pub fn decode(&self, pattern: u64) -> f64 {
+        match self {
+            FloatFormat::Double => f64::from_bits(pattern),
+            FloatFormat::Single => f32::from_bits(pattern as u32) as f64,
+            FloatFormat::Half => {
+                let (sign, exponent, mantissa) = self.fields(pattern);
+                let magnitude = match exponent {
+                    0 => mantissa as f64 * 2f64.powi(-24),
+                    31 if mantissa == 0 => f64::INFINITY,
+                    31 => f64::NAN,
+                    _ => (1024 + mantissa) as f64 * 2f64.powi(exponent as i32 - 25),
+                };
+                if sign {
+                    -magnitude
+                } else {
+                    magnitude
+                }
+            }
+        }
+    }
+}
+
+/// Half precision pattern of the nearest f16 to `x`
+fn f16_bits(x: f64) -> u16 {
+    let sign = if x.is_sign_negative() { 0x8000 } else { 0 };
+    let a = x.abs();
+    if a.is_nan() {
+        return 0x7E00;
+    }
+    if a < 2f64.powi(-14) {
+        // Subnormal; rounding up to 0x400 gives the smallest normal
+        return sign | (a * 2f64.powi(24)).round_ties_even() as u16;
+    }
+    if a.is_infinite() {
+        return sign | 0x7C00;
+    }
+    let mut exponent = ((a.to_bits() >> 52) & 0x7FF) as i32 - 1023;
+    let mut mantissa = ((a / 2f64.powi(exponent) - 1.0) * 1024.0).round_ties_even() as u16;
+    if mantissa == 1024 {
+        mantissa = 0;
+        exponent += 1;
+    }
+    if exponent > 15 {
+        return sign | 0x7C00;
+    }
+    sign | ((exponent + 15) as u16) << 10 | mantissa
+}
+
 /// Scientific functions
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Func {
@@ -128,11 +313,81 @@ pub enum Func {
     Ceil,
     Round,
     Factorial,
+    DoubleFactorial,
+    Percent,
     Reciprocal,
     Negate,
+    // Complex
+    Re,
+    Im,
+    /// Angle of a complex number, in the angle mode
+    Arg,
+    Conj,
+    // Bitwise
+    Not,
+    /// IEEE-754 bit pattern of a value
+    FloatBits(FloatFormat),
+    /// Value of an IEEE-754 bit pattern
+    FromFloatBits(FloatFormat),
+    // Multi-argument
+    Atan2,
+    Max,
+    Min,
+    LogBase,
+    Root,
+    NCr,
+    NPr,
+    Hypot,
+    Bits,
+    /// `convert(x, from, to)`, with unit names as its last two arguments
+    Convert,
+    // Logical
+    LogicalNot,
+    /// `if(cond, a, b)`: evaluated lazily in algebraic mode
+    If,
+    /// A quantity shown in SI units
+    Si,
+    // Matrix
+    Det,
+    Transpose,
+    /// Matrix inverse
+    Inverse,
+    /// Reduced row echelon form
+    Rref,
+    /// `identity(n)`: the n×n identity matrix
+    Identity,
+    Dot,
+    Cross,
+    /// Length of a vector, Frobenius norm of a matrix
+    Norm,
     // Constants (evaluated to values)
     Pi,
     E,
+    /// The imaginary unit
+    I,
+    /// Physical constant, by index into `CONSTANTS`
+    Constant(usize),
+}
+
+/// Number of arguments a function accepts
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    /// Exactly n arguments
+    Fixed(usize),
+    /// Between min and max arguments (inclusive)
+    Range(usize, usize),
+    /// At least n arguments
+    Variadic(usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, n: usize) -> bool {
+        match *self {
+            Arity::Fixed(k) => n == k,
+            Arity::Range(min, max) => n >= min && n <= max,
+            Arity::Variadic(min) => n >= min,
+        }
+    }
 }
 
 impl Func {
@@ -161,8 +416,40 @@ impl Func {
             "floor" => Some(Func::Floor),
             "ceil" => Some(Func::Ceil),
             "round" => Some(Func::Round),
+            "atan2" => Some(Func::Atan2),
+            "max" => Some(Func::Max),
+            "min" => Some(Func::Min),
+            "logb" => Some(Func::LogBase),
+            "root" => Some(Func::Root),
+            "ncr" => Some(Func::NCr),
+            "npr" => Some(Func::NPr),
+            "hypot" => Some(Func::Hypot),
+            "bits" => Some(Func::Bits),
+            "convert" => Some(Func::Convert),
+            "not" => Some(Func::LogicalNot),
+            "if" => Some(Func::If),
+            "si" => Some(Func::Si),
+            "re" => Some(Func::Re),
+            "im" => Some(Func::Im),
+            "arg" => Some(Func::Arg),
+            "conj" => Some(Func::Conj),
+            "det" => Some(Func::Det),
+            "transpose" => Some(Func::Transpose),
+            "inv" => Some(Func::Inverse),
+            "rref" => Some(Func::Rref),
+            "identity" => Some(Func::Identity),
+            "dot" => Some(Func::Dot),
+            "cross" => Some(Func::Cross),
+            "norm" => Some(Func::Norm),
+            "f16bits" => Some(Func::FloatBits(FloatFormat::Half)),
+            "f32bits" => Some(Func::FloatBits(FloatFormat::Single)),
+            "f64bits" => Some(Func::FloatBits(FloatFormat::Double)),
+            "float16" => Some(Func::FromFloatBits(FloatFormat::Half)),
+            "float32" => Some(Func::FromFloatBits(FloatFormat::Single)),
+            "float64" => Some(Func::FromFloatBits(FloatFormat::Double)),
             "pi" => Some(Func::Pi),
             "e" => Some(Func::E),
+            "i" => Some(Func::I),
             _ => None,
         }
     }
@@ -196,16 +483,346 @@ impl Func {
             Func::Ceil => "ceil",
             Func::Round => "round",
             Func::Factorial => "!",
+            Func::DoubleFactorial => "!!",
+            Func::Percent => "%",
             Func::Reciprocal => "1/x",
             Func::Negate => "neg",
+            Func::Re => "re",
+            Func::Im => "im",
+            Func::Arg => "arg",
+            Func::Conj => "conj",
+            Func::Not => "~",
+            Func::FloatBits(FloatFormat::Half) => "f16bits",
+            Func::FloatBits(FloatFormat::Single) => "f32bits",
+            Func::FloatBits(FloatFormat::Double) => "f64bits",
+            Func::FromFloatBits(FloatFormat::Half) => "float16",
+            Func::FromFloatBits(FloatFormat::Single) => "float32",
+            Func::FromFloatBits(FloatFormat::Double) => "float64",
+            Func::Atan2 => "atan2",
+            Func::Max => "max",
+            Func::Min => "min",
+            Func::LogBase => "logb",
+            Func::Root => "root",
+            Func::NCr => "nCr",
+            Func::NPr => "nPr",
+            Func::Hypot => "hypot",
+            Func::Bits => "bits",
+            Func::Convert => "convert",
+            Func::LogicalNot => "not",
+            Func::If => "if",
+            Func::Si => "si",
+            Func::Det => "det",
+            Func::Transpose => "transpose",
+            Func::Inverse => "inv",
+            Func::Rref => "rref",
+            Func::Identity => "identity",
+            Func::Dot => "dot",
+            Func::Cross => "cross",
+            Func::Norm => "norm",
             Func::Pi => "π",
             Func::E => "e",
+            Func::I => "i",
+            Func::Constant(i) => CONSTANTS[*i].name,
         }
     }
 
     /// Is this a constant (no argument needed)?
     pub fn is_constant(&self) -> bool {
-        matches!(self, Func::Pi | Func::E)
+        matches!(self, Func::Pi | Func::E | Func::I | Func::Constant(_))
+    }
+
+    /// Symbol for functions written after their operand (`5!`, `50%`, `3²`)
+    pub fn postfix_symbol(&self) -> Option<&'static str> {
+        match self {
+            Func::Factorial => Some("!"),
+            Func::DoubleFactorial => Some("!!"),
+            Func::Percent => Some("%"),
+            Func::Square => Some("²"),
+            Func::Cube => Some("³"),
+            _ => None,
+        }
+    }
+
+    /// Is this written after its operand?
+    pub fn is_postfix(&self) -> bool {
+        self.postfix_symbol().is_some()
+    }
+
+    /// Arguments accepted in an algebraic call like `max(a,b,c)`
+    pub fn arity(&self) -> Arity {
+        match self {
+            Func::Pi | Func::E | Func::I | Func::Constant(_) => Arity::Fixed(0),
+            Func::Log => Arity::Range(1, 2),
+            Func::Max | Func::Min => Arity::Variadic(1),
+            Func::Atan2
+            | Func::LogBase
+            | Func::Root
+            | Func::NCr
+            | Func::NPr
+            | Func::Hypot
+            | Func::Dot
+            | Func::Cross => Arity::Fixed(2),
+            Func::Bits | Func::Convert | Func::If => Arity::Fixed(3),
+            _ => Arity::Fixed(1),
+        }
+    }
+
+    /// Stack levels consumed when applied in RPN mode
+    pub fn stack_arity(&self) -> usize {
+        match self.arity() {
+            Arity::Fixed(n) => n,
+            Arity::Range(min, _) => min,
+            Arity::Variadic(_) => 2,
+        }
+    }
+
+    /// Evaluate within an integer word: NOT and negation are exact, other
+    /// results are truncated and wrapped to fit
+    pub fn evaluate_word(
+        &self,
+        x: f64,
+        angle_mode: AngleMode,
+        word: WordSize,
+    ) -> Result<(f64, IntFlags), CalcError> {
+        match self {
+            Func::Not => Ok((word.to_value(!word.to_pattern(x)?), IntFlags::default())),
+            Func::Negate => {
+                let (n, overflow) = word.wrap_int(-word.to_int(word.to_pattern(x.trunc())?));
+                Ok((n as f64, IntFlags { carry: false, overflow }))
+            }
+            _ => word.fit_result(self.evaluate(x, angle_mode)?),
+        }
+    }
+
+    /// Evaluate with an explicit argument list (first argument first)
+    pub fn evaluate_args(&self, args: &[f64], angle_mode: AngleMode) -> Result<f64, CalcError> {
+        if !self.arity().accepts(args.len()) {
+            return Err(CalcError::SyntaxError(alloc::format!(
+                "{}: wrong number of arguments",
+                self.name()
+            )));
+        }
+
+        match self {
+            Func::Atan2 => Ok(from_radians(args[0].atan2(args[1]), angle_mode)),
+            Func::Max => Ok(args.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+            Func::Min => Ok(args.iter().copied().fold(f64::INFINITY, f64::min)),
+            Func::Log if args.len() == 2 => log_base(args[0], args[1]),
+            Func::LogBase => log_base(args[0], args[1]),
+            Func::Root => nth_root(args[0], args[1]),
+            Func::NCr => combinations(args[0], args[1]),
+            Func::NPr => permutations(args[0], args[1]),
+            Func::Hypot => Ok(args[0].hypot(args[1])),
+            Func::Bits => {
+                let (hi, lo) = (args[1], args[2]);
+                if lo < 0.0 || hi < lo || hi > 63.0 || hi.fract() != 0.0 || lo.fract() != 0.0 {
+                    return Err(CalcError::DomainError("bits: 0 ≤ lo ≤ hi ≤ 63"));
+                }
+                let pattern = WordSize::default().to_pattern(args[0])?;
+                Ok(bit_field(pattern, hi as u32, lo as u32) as f64)
+            }
+            Func::Convert => units::convert(args[0], args[1] as usize, args[2] as usize),
+            Func::If => Ok(if args[0] != 0.0 { args[1] } else { args[2] }),
+            _ if self.is_constant() => self.evaluate(0.0, angle_mode),
+            _ => self.evaluate(args[0], angle_mode),
+        }
+    }
+
+    /// Evaluate on complex arguments (first argument first): real ones
+    /// give a real result, unless only a complex one exists and `complex`
+    /// allows it
+    pub fn evaluate_args_complex(
+        &self,
+        args: &[Complex],
+        angle_mode: AngleMode,
+        complex: ComplexMode,
+    ) -> Result<Complex, CalcError> {
+        if args.iter().all(Complex::is_real) {
+            let real: alloc::vec::Vec<f64> = args.iter().map(|z| z.re).collect();
+            match self.evaluate_args(&real, angle_mode) {
+                Err(CalcError::DomainError(_))
+                    if complex.is_complex() && matches!(self, Func::Log | Func::LogBase | Func::Root) => {}
+                result => return result.map(Complex::from),
+            }
+        }
+        if !self.arity().accepts(args.len()) {
+            return Err(CalcError::SyntaxError(alloc::format!(
+                "{}: wrong number of arguments",
+                self.name()
+            )));
+        }
+        match self {
+            Func::Log if args.len() == 2 => args[0].ln()?.checked_div(args[1].ln()?),
+            Func::LogBase => args[0].ln()?.checked_div(args[1].ln()?),
+            Func::Root => args[0].pow(args[1].recip()?),
+            Func::If => Ok(if args[0].is_zero() { args[2] } else { args[1] }),
+            _ if args.len() == 1 => self.evaluate_complex(args[0], angle_mode, complex),
+            _ => Err(CalcError::DomainError("needs real numbers")),
+        }
+    }
+
+    /// Evaluate on a complex number: a real one gives a real result, unless
+    /// only a complex one exists (`sqrt(-1)`) and `complex` allows it.
+    /// Trig scales both parts by the angle mode.
+    pub fn evaluate_complex(
+        &self,
+        z: Complex,
+        angle_mode: AngleMode,
+        complex: ComplexMode,
+    ) -> Result<Complex, CalcError> {
+        if *self == Func::I {
+            return Ok(Complex::I);
+        }
+        if z.is_real() {
+            match self.evaluate(z.re, angle_mode) {
+                Err(CalcError::DomainError(_)) if complex.is_complex() && self.has_complex_values() => {}
+                result => return result.map(Complex::from),
+            }
+        }
+        let radians = z.scale(to_radians(1.0, angle_mode));
+        let angle = |w: Complex| w.scale(from_radians(1.0, angle_mode));
+        match self {
+            Func::Sin => Ok(radians.sin()),
+            Func::Cos => Ok(radians.cos()),
+            Func::Tan => radians.tan(),
+            Func::Asin => z.asin().map(angle),
+            Func::Acos => z.acos().map(angle),
+            Func::Atan => z.atan().map(angle),
+            Func::Sinh => Ok(z.sinh()),
+            Func::Cosh => Ok(z.cosh()),
+            Func::Tanh => z.tanh(),
+            Func::Asinh => z.asinh(),
+            Func::Acosh => z.acosh(),
+            Func::Atanh => z.atanh(),
+            Func::Ln => z.ln(),
+            Func::Log => z.log10(),
+            Func::Log2 => z.log2(),
+            Func::Exp => z.exp(),
+            Func::Exp10 => z.scale(core::f64::consts::LN_10).exp(),
+            Func::Sqrt => Ok(z.sqrt()),
+            Func::Cbrt => z.pow(Complex::from(1.0 / 3.0)),
+            Func::Square => Ok(z * z),
+            Func::Cube => Ok(z * z * z),
+            Func::Reciprocal => z.recip(),
+            Func::Negate => Ok(-z),
+            Func::Percent => Ok(z.scale(0.01)),
+            Func::Abs => Ok(Complex::from(z.abs())),
+            Func::Floor => Ok(z.map(f64::floor)),
+            Func::Ceil => Ok(z.map(f64::ceil)),
+            Func::Round => Ok(z.map(f64::round)),
+            Func::Re => Ok(Complex::from(z.re)),
+            Func::Im => Ok(Complex::from(z.im)),
+            Func::Arg => Ok(Complex::from(from_radians(z.arg(), angle_mode))),
+            Func::Conj => Ok(z.conj()),
+            Func::LogicalNot => Ok(Complex::from(truth(z.is_zero()))),
+            Func::Si => Ok(z),
+            _ => Err(CalcError::DomainError("needs a real number")),
+        }
+    }
+
+    /// Does this function take real arguments outside its real domain to
+    /// complex values?
+    fn has_complex_values(&self) -> bool {
+        matches!(
+            self,
+            Func::Sqrt | Func::Ln | Func::Log | Func::Log2 | Func::Asin | Func::Acos | Func::Acosh | Func::Atanh
+        )
+    }
+
+    /// Evaluate on a quantity: powers and roots carry its dimensions,
+    /// sign changes and parts of complex numbers keep its unit, trig takes
+    /// an angle in any unit, and everything else needs a plain number
+    pub fn evaluate_quantity(
+        &self,
+        x: Quantity,
+        angle_mode: AngleMode,
+        complex: ComplexMode,
+    ) -> Result<Quantity, CalcError> {
+        if let Func::Constant(i) = self {
+            return Ok(CONSTANTS[*i].quantity());
+        }
+        if x.is_plain() {
+            return self.evaluate_complex(x.complex(), angle_mode, complex).map(Quantity::from);
+        }
+        let power = match self {
+            Func::Si => return Ok(x.simplified()),
+            Func::Negate | Func::Abs | Func::Percent | Func::Re | Func::Im | Func::Conj => {
+                let value = self.evaluate_complex(x.complex(), angle_mode, complex)?;
+                return Ok(x.with_value(value).linear());
+            }
+            Func::Arg => return self.evaluate_complex(x.complex(), angle_mode, complex).map(Quantity::from),
+            Func::Sin | Func::Cos | Func::Tan if x.dims == Dims::ANGLE => {
+                return self.evaluate_complex(x.complex(), AngleMode::Radians, complex).map(Quantity::from);
+            }
+            Func::Sqrt => 0.5,
+            Func::Cbrt => 1.0 / 3.0,
+            Func::Square => 2.0,
+            Func::Cube => 3.0,
+            Func::Reciprocal => -1.0,
+            _ => {
+                return Err(CalcError::DimensionError(match self.arity() {
+                    Arity::Fixed(1) => "function of a plain number",
+                    _ => "arguments must be plain numbers",
+                }))
+            }
+        };
+        let dims = x.dims.pow(power)?;
+        let value = self.evaluate_complex(x.complex(), angle_mode, complex)?;
+        Ok(Quantity::new(0.0, dims).with_value(value))
+    }
+
+    /// Evaluate on a number or a matrix. Matrices take the matrix
+    /// functions, `1/x`, `x²` and negation; `identity` takes a size.
+    pub fn evaluate_value(&self, x: Value, angle_mode: AngleMode, complex: ComplexMode) -> Result<Value, CalcError> {
+        let m = match x {
+            Value::Number(q) if *self == Func::Identity => {
+                let n = q.plain()?;
+                if n.fract() != 0.0 || !(1.0..=MAX_SIZE as f64).contains(&n) {
+                    return Err(CalcError::DomainError("identity: size 1 to 6"));
+                }
+                return Matrix::identity(n as usize).map(Value::from);
+            }
+            Value::Number(q) => return self.evaluate_quantity(q, angle_mode, complex).map(Value::from),
+            Value::Matrix(m) => m,
+        };
+        match self {
+            Func::Det => m.det().map(Value::from),
+            Func::Transpose => Ok(Value::from(m.transpose())),
+            Func::Inverse | Func::Reciprocal => m.inverse().map(Value::from),
+            Func::Rref => Ok(Value::from(m.rref())),
+            Func::Norm => Ok(Value::from(m.norm())),
+            Func::Negate => m.scale(-1.0).map(Value::from),
+            Func::Square => m.mul(&m).map(Value::from),
+            _ => Err(CalcError::DomainError("not defined for matrices")),
+        }
+    }
+
+    /// Evaluate with an argument list of numbers or matrices (first
+    /// argument first)
+    pub fn evaluate_args_value(
+        &self,
+        args: &[Value],
+        angle_mode: AngleMode,
+        complex: ComplexMode,
+    ) -> Result<Value, CalcError> {
+        if !self.arity().accepts(args.len()) {
+            return Err(CalcError::SyntaxError(alloc::format!(
+                "{}: wrong number of arguments",
+                self.name()
+            )));
+        }
+        match (self, args) {
+            (Func::Dot, [Value::Matrix(a), Value::Matrix(b)]) => a.dot(b).map(Value::from),
+            (Func::Cross, [Value::Matrix(a), Value::Matrix(b)]) => a.cross(b).map(Value::from),
+            (_, [x]) => self.evaluate_value(*x, angle_mode, complex),
+            _ => {
+                let args = args
+                    .iter()
+                    .map(|v| v.number()?.plain_complex())
+                    .collect::<Result<alloc::vec::Vec<_>, _>>()?;
+                self.evaluate_args_complex(&args, angle_mode, complex).map(Value::from)
+            }
+        }
     }
 
     /// Evaluate unary function
@@ -214,6 +831,7 @@ impl Func {
             // Constants
             Func::Pi => Ok(PI),
             Func::E => Ok(E),
+            Func::Constant(i) => Ok(CONSTANTS[*i].value),
 
             // Trigonometric (input in current angle mode)
             Func::Sin => Ok(to_radians(x, angle_mode).sin()),
@@ -333,13 +951,135 @@ impl Func {
                 }
             }
             Func::Negate => Ok(-x),
+            Func::Not => self
+                .evaluate_word(x, angle_mode, WordSize::default())
+                .map(|(value, _)| value),
+            Func::FloatBits(format) => Ok(format.encode(x) as f64),
+            Func::FromFloatBits(format) => {
+                let word = WordSize::new(format.bits(), SignMode::Unsigned);
+                let (_, max) = word.range();
+                if x.abs() > max as f64 {
+                    return Err(CalcError::Overflow);
+                }
+                Ok(format.decode(word.to_pattern(x)?))
+            }
             Func::Factorial => factorial(x),
+            Func::DoubleFactorial => double_factorial(x),
+            Func::Percent => Ok(x / 100.0),
+            Func::LogicalNot => Ok(truth(x == 0.0)),
+            Func::Si => Ok(x),
+            Func::Re | Func::Conj => Ok(x),
+            Func::Im => Ok(0.0),
+            Func::Arg => Ok(from_radians(0.0_f64.atan2(x), angle_mode)),
+            Func::I => Err(CalcError::DomainError("i is not real")),
+            Func::Det
+            | Func::Transpose
+            | Func::Inverse
+            | Func::Rref
+            | Func::Identity
+            | Func::Dot
+            | Func::Cross
+            | Func::Norm => Err(CalcError::DomainError("needs a matrix")),
+
+            // Multi-argument functions called with a single value
+            Func::Atan2
+            | Func::Max
+            | Func::Min
+            | Func::LogBase
+            | Func::Root
+            | Func::NCr
+            | Func::NPr
+            | Func::Hypot
+            | Func::Bits
+            | Func::Convert
+            | Func::If => self.evaluate_args(&[x], angle_mode),
         }
     }
 }
 
+/// 1 for true, 0 for false
+fn truth(b: bool) -> f64 {
+    if b {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Logarithm of x in base b
+fn log_base(x: f64, b: f64) -> Result<f64, CalcError> {
+    if x <= 0.0 {
+        Err(CalcError::DomainError("log domain (0,∞)"))
+    } else if b <= 0.0 || b == 1.0 {
+        Err(CalcError::DomainError("log base (0,1)∪(1,∞)"))
+    } else {
+        Ok(x.ln() / b.ln())
+    }
+}
+
+/// n-th root of x, real-valued for negative x with odd integer n
+fn nth_root(x: f64, n: f64) -> Result<f64, CalcError> {
+    if n == 0.0 {
+        return Err(CalcError::DomainError("root index ≠ 0"));
+    }
+    if x < 0.0 {
+        if n == n.floor() && n % 2.0 != 0.0 {
+            Ok(-(-x).powf(1.0 / n))
+        } else {
+            Err(CalcError::DomainError("even root of negative"))
+        }
+    } else {
+        Ok(x.powf(1.0 / n))
+    }
+}
+
+/// Check that n and r are non-negative integers for nCr/nPr
+fn check_choose_args(n: f64, r: f64) -> Result<(), CalcError> {
+    if n < 0.0 || r < 0.0 || n != n.floor() || r != r.floor() {
+        Err(CalcError::DomainError("nCr/nPr need integers ≥ 0"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Combinations: n choose r
+fn combinations(n: f64, r: f64) -> Result<f64, CalcError> {
+    check_choose_args(n, r)?;
+    if r > n {
+        return Ok(0.0);
+    }
+
+    // Multiplicative form keeps intermediates small
+    let k = r.min(n - r) as u64;
+    let mut result = 1.0_f64;
+    for i in 0..k {
+        result = result * (n - i as f64) / (i as f64 + 1.0);
+        if result.is_infinite() {
+            return Err(CalcError::Overflow);
+        }
+    }
+    Ok(result.round())
+}
+
+/// Permutations: n!/(n-r)!
+fn permutations(n: f64, r: f64) -> Result<f64, CalcError> {
+    check_choose_args(n, r)?;
+    if r > n {
+        return Ok(0.0);
+    }
+
+    let mut result = 1.0_f64;
+    for i in 0..(r as u64) {
+        result *= n - i as f64;
+        if result.is_infinite() {
+            return Err(CalcError::Overflow);
+        }
+    }
+    Ok(result)
+}
+
 /// Convert angle to radians from current mode
-fn to_radians(x: f64, mode: AngleMode) -> f64 {
+pub fn to_radians(x: f64, mode: AngleMode) -> f64 {
     match mode {
         AngleMode::Radians => x,
         AngleMode::Degrees => x.to_radians(),
@@ -348,7 +1088,7 @@ fn to_radians(x: f64, mode: AngleMode) -> f64 {
 }
 
 /// Convert radians to current angle mode
-fn from_radians(x: f64, mode: AngleMode) -> f64 {
+pub fn from_radians(x: f64, mode: AngleMode) -> f64 {
     match mode {
         AngleMode::Radians => x,
         AngleMode::Degrees => x.to_degrees(),
@@ -388,6 +1128,24 @@ fn factorial(x: f64) -> Result<f64, CalcError> {
     }
 }
 
+/// Double factorial n!! = n(n-2)(n-4)..., defined for integers ≥ -1
+fn double_factorial(x: f64) -> Result<f64, CalcError> {
+    if x < -1.0 || x != x.floor() {
+        return Err(CalcError::DomainError("n!! domain: integers ≥ -1"));
+    }
+
+    let mut result = 1.0_f64;
+    let mut n = x;
+    while n > 1.0 {
+        result *= n;
+        if result.is_infinite() {
+            return Err(CalcError::Overflow);
+        }
+        n -= 2.0;
+    }
+    Ok(result)
+}
+
 /// Gamma function approximation (Lanczos)
 fn gamma(x: f64) -> f64 {
     // Lanczos approximation coefficients
@@ -427,14 +1185,68 @@ pub enum Op {
     Div,
     Pow,
     Mod,
+    /// Juxtaposition (`2π`, `3(4+5)`): multiplies, but binds tighter than
+    /// `*` and `/` so that `1/2π` is `1/(2π)`
+    ImplicitMul,
+    // Bitwise, on the two's complement bit pattern
+    And,
+    Or,
+    Xor,
+    /// Logical shifts
+    Shl,
+    Shr,
+    /// Arithmetic (sign-extending) shift right
+    Asr,
+    Rol,
+    Ror,
+    // Comparisons, giving 1 for true and 0 for false
+    Less,
+    LessEq,
+    Greater,
+    GreaterEq,
+    Equal,
+    NotEqual,
+    /// Logical, treating any nonzero operand as true
+    LogicalAnd,
+    LogicalOr,
+    /// `r∠θ`: the complex number of magnitude r at angle θ
+    Polar,
 }
 
 impl Op {
+    /// Operators written as words (`5 xor 3`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "xor" => Some(Op::Xor),
+            "asr" => Some(Op::Asr),
+            "rol" => Some(Op::Rol),
+            "ror" => Some(Op::Ror),
+            "and" => Some(Op::LogicalAnd),
+            "or" => Some(Op::LogicalOr),
+            _ => None,
+        }
+    }
+
+    /// Binding strength, as in Python: `or` < `and` < `not` < comparisons
+    /// < `|` < `xor` < `&` < shifts < `+`. `not` sits at 3. Unary minus and
+    /// other prefix functions written without parentheses sit at 13,
+    /// between implicit multiplication and `^`, so `-2^2` is `-4` and
+    /// `2^3π` is `(2^3)π`.
     pub fn precedence(&self) -> u8 {
         match self {
-            Op::Add | Op::Sub => 1,
-            Op::Mul | Op::Div | Op::Mod => 2,
-            Op::Pow => 3,
+            Op::LogicalOr => 1,
+            Op::LogicalAnd => 2,
+            Op::Less | Op::LessEq | Op::Greater | Op::GreaterEq | Op::Equal | Op::NotEqual => 4,
+            Op::Or => 5,
+            Op::Xor => 6,
+            Op::And => 7,
+            Op::Shl | Op::Shr | Op::Asr | Op::Rol | Op::Ror => 8,
+            Op::Add | Op::Sub => 9,
+            Op::Mul | Op::Div | Op::Mod => 10,
+            // `1∠45 * 1∠45` multiplies two polar values
+            Op::Polar => 11,
+            Op::ImplicitMul => 12,
+            Op::Pow => 14,
         }
     }
 
@@ -442,14 +1254,32 @@ impl Op {
         !matches!(self, Op::Pow)
     }
 
-    pub fn symbol(&self) -> char {
+    /// Text inserted for the operator in algebraic mode
+    pub fn symbol(&self) -> &'static str {
         match self {
-            Op::Add => '+',
-            Op::Sub => '-',
-            Op::Mul => '×',
-            Op::Div => '÷',
-            Op::Pow => '^',
-            Op::Mod => '%',
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul | Op::ImplicitMul => "×",
+            Op::Div => "÷",
+            Op::Pow => "^",
+            Op::Mod => "%",
+            Op::And => "&",
+            Op::Or => "|",
+            Op::Xor => " xor ",
+            Op::Shl => "<<",
+            Op::Shr => ">>",
+            Op::Asr => " asr ",
+            Op::Rol => " rol ",
+            Op::Ror => " ror ",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Equal => "==",
+            Op::NotEqual => "!=",
+            Op::LogicalAnd => " and ",
+            Op::LogicalOr => " or ",
+            Op::Polar => "∠",
         }
     }
 
@@ -457,7 +1287,7 @@ impl Op {
         match self {
             Op::Add => Ok(a + b),
             Op::Sub => Ok(a - b),
-            Op::Mul => Ok(a * b),
+            Op::Mul | Op::ImplicitMul => Ok(a * b),
             Op::Div => {
                 if b == 0.0 {
                     Err(CalcError::DivideByZero)
@@ -482,8 +1312,510 @@ impl Op {
                     Ok(a % b)
                 }
             }
+            Op::Less => Ok(truth(a < b)),
+            Op::LessEq => Ok(truth(a <= b)),
+            Op::Greater => Ok(truth(a > b)),
+            Op::GreaterEq => Ok(truth(a >= b)),
+            Op::Equal => Ok(truth(a == b)),
+            Op::NotEqual => Ok(truth(a != b)),
+            Op::LogicalAnd => Ok(truth(a != 0.0 && b != 0.0)),
+            Op::LogicalOr => Ok(truth(a != 0.0 || b != 0.0)),
+            Op::Polar => Err(CalcError::DomainError("∠ needs complex numbers")),
+            _ => self.evaluate_bitwise(a, b, WordSize::default()).map(|(value, _)| value),
         }
     }
+
+    /// Evaluate on complex numbers: real ones give a real result, unless
+    /// only a complex one exists (`(-8)^(1/3)`) and `complex` allows it
+    pub fn evaluate_complex(&self, a: Complex, b: Complex, complex: ComplexMode) -> Result<Complex, CalcError> {
+        if a.is_real() && b.is_real() {
+            match self.evaluate(a.re, b.re) {
+                Err(CalcError::DomainError(_)) if complex.is_complex() && *self == Op::Pow => {}
+                result => return result.map(Complex::from),
+            }
+        }
+        match self {
+            Op::Add => Ok(a + b),
+            Op::Sub => Ok(a - b),
+            Op::Mul | Op::ImplicitMul => Ok(a * b),
+            Op::Div => a.checked_div(b),
+            Op::Pow => a.pow(b),
+            Op::Equal => Ok(Complex::from(truth(a == b))),
+            Op::NotEqual => Ok(Complex::from(truth(a != b))),
+            Op::LogicalAnd => Ok(Complex::from(truth(!a.is_zero() && !b.is_zero()))),
+            Op::LogicalOr => Ok(Complex::from(truth(!a.is_zero() || !b.is_zero()))),
+            _ => Err(CalcError::DomainError("needs real numbers")),
+        }
+    }
+
+    /// Evaluate on quantities: sums and comparisons need like dimensions,
+    /// products combine them, and scaling by a plain number keeps the unit.
+    /// The angle of `r∠θ` is read in `angle_mode` unless it has a unit.
+    pub fn evaluate_quantity(
+        &self,
+        a: Quantity,
+        b: Quantity,
+        angle_mode: AngleMode,
+        complex: ComplexMode,
+    ) -> Result<Quantity, CalcError> {
+        if *self == Op::Polar {
+            if !a.is_real() || !b.is_real() {
+                return Err(CalcError::DomainError("∠ needs real r and θ"));
+            }
+            let theta = match b.dims {
+                Dims::ANGLE => b.value,
+                _ => to_radians(b.plain()?, angle_mode),
+            };
+            return Ok(a.with_value(Complex::from_polar(a.value, theta)));
+        }
+        if a.is_plain() && b.is_plain() {
+            return self.evaluate_complex(a.complex(), b.complex(), complex).map(Quantity::from);
+        }
+        let (dims, unit) = match self {
+            Op::Add | Op::Sub | Op::Mod if a.dims != b.dims => {
+                return Err(CalcError::DimensionError("dimension mismatch"));
+            }
+            Op::Add | Op::Sub | Op::Mod => (a.dims, if a.unit == b.unit { a.unit } else { None }),
+            Op::Mul | Op::ImplicitMul => {
+                let unit = match (a.is_plain(), b.is_plain()) {
+                    (false, true) => a.unit,
+                    (true, false) => b.unit,
+                    _ => None,
+                };
+                (a.dims.times(b.dims)?, unit)
+            }
+            Op::Div => (a.dims.per(b.dims)?, if b.is_plain() { a.unit } else { None }),
+            Op::Pow => {
+                let n = b
+                    .plain()
+                    .map_err(|_| CalcError::DimensionError("exponent must be a plain number"))?;
+                (a.dims.pow(n)?, None)
+            }
+            Op::LogicalAnd | Op::LogicalOr => {
+                return self.evaluate(a.plain()?, b.plain()?).map(Quantity::from);
+            }
+            _ if self.is_logical() && a.dims != b.dims => {
+                return Err(CalcError::DimensionError("dimension mismatch"));
+            }
+            _ if self.is_logical() => {
+                return self.evaluate_complex(a.complex(), b.complex(), complex).map(Quantity::from);
+            }
+            _ => return Err(CalcError::DimensionError("bitwise needs plain numbers")),
+        };
+        let value = self.evaluate_complex(a.complex(), b.complex(), complex)?;
+        let result = Quantity {
+            dims,
+            unit,
+            ..Quantity::from(value)
+        };
+        Ok(result.linear())
+    }
+
+    /// Evaluate on numbers or matrices: matrices add and multiply as such,
+    /// scale by plain numbers, divide by inverting and take whole powers
+    pub fn evaluate_value(
+        &self,
+        a: Value,
+        b: Value,
+        angle_mode: AngleMode,
+        complex: ComplexMode,
+    ) -> Result<Value, CalcError> {
+        let result = match (self, a, b) {
+            (_, Value::Number(a), Value::Number(b)) => {
+                return self.evaluate_quantity(a, b, angle_mode, complex).map(Value::from);
+            }
+            (Op::Equal, a, b) => return Ok(Value::from(truth(a == b))),
+            (Op::NotEqual, a, b) => return Ok(Value::from(truth(a != b))),
+            (Op::Add, Value::Matrix(a), Value::Matrix(b)) => a.add(&b),
+            (Op::Sub, Value::Matrix(a), Value::Matrix(b)) => a.sub(&b),
+            (Op::Mul | Op::ImplicitMul, Value::Matrix(a), Value::Matrix(b)) => a.mul(&b),
+            (Op::Mul | Op::ImplicitMul, Value::Matrix(m), Value::Number(k))
+            | (Op::Mul | Op::ImplicitMul, Value::Number(k), Value::Matrix(m)) => m.scale(k.plain()?),
+            (Op::Div, Value::Matrix(m), Value::Number(k)) => match k.plain()? {
+                0.0 => Err(CalcError::DivideByZero),
+                k => m.scale(1.0 / k),
+            },
+            (Op::Div, Value::Matrix(a), Value::Matrix(b)) => a.mul(&b.inverse()?),
+            (Op::Div, Value::Number(k), Value::Matrix(m)) => m.inverse()?.scale(k.plain()?),
+            (Op::Pow, Value::Matrix(m), Value::Number(n)) => m.pow(n.plain()?),
+            _ => Err(CalcError::DomainError("not defined for matrices")),
+        };
+        result.map(Value::from)
+    }
+
+    /// Is this a comparison or logical operator, giving 1 or 0?
+    pub fn is_logical(&self) -> bool {
+        matches!(
+            self,
+            Op::Less
+                | Op::LessEq
+                | Op::Greater
+                | Op::GreaterEq
+                | Op::Equal
+                | Op::NotEqual
+                | Op::LogicalAnd
+                | Op::LogicalOr
+        )
+    }
+
+    /// Is this a bitwise, shift or rotate operator?
+    pub fn is_bitwise(&self) -> bool {
+        matches!(
+            self,
+            Op::And | Op::Or | Op::Xor | Op::Shl | Op::Shr | Op::Asr | Op::Rol | Op::Ror
+        )
+    }
+
+    /// Evaluate with exact integer arithmetic in a word
+    ///
+    /// Operands are truncated to integers. Division and remainder truncate
+    /// toward zero, and results wrap around to fit the word.
+    pub fn evaluate_word(
+        &self,
+        a: f64,
+        b: f64,
+        word: WordSize,
+    ) -> Result<(f64, IntFlags), CalcError> {
+        if self.is_bitwise() {
+            return self.evaluate_bitwise(a, b, word);
+        }
+        // Operands are already in the word, so compare their values
+        if self.is_logical() {
+            return Ok((self.evaluate(a, b)?, IntFlags::default()));
+        }
+        let (pa, pb) = (word.to_pattern(a.trunc())?, word.to_pattern(b.trunc())?);
+        let (x, y) = (word.to_int(pa), word.to_int(pb));
+        let mut flags = IntFlags::default();
+        let (exact, wrapped) = match self {
+            Op::Add => {
+                flags.carry = pa as u128 + pb as u128 > word.mask() as u128;
+                (x + y, false)
+            }
+            Op::Sub => {
+                // Borrow
+                flags.carry = pa < pb;
+                (x - y, false)
+            }
+            Op::Mul | Op::ImplicitMul => word.mul(x, y),
+            Op::Div | Op::Mod if y == 0 => return Err(CalcError::DivideByZero),
+            Op::Div => {
+                // Set when the quotient is inexact
+                flags.carry = x % y != 0;
+                (x / y, false)
+            }
+            Op::Mod => (x % y, false),
+            Op::Pow => word.pow(x, y)?,
+            Op::Polar => return Err(CalcError::DomainError("∠ needs complex numbers")),
+            _ => unreachable!(),
+        };
+        let (n, out_of_range) = word.wrap_int(exact);
+        flags.overflow = wrapped || out_of_range;
+        Ok((n as f64, flags))
+    }
+
+    fn evaluate_bitwise(
+        &self,
+        a: f64,
+        b: f64,
+        word: WordSize,
+    ) -> Result<(f64, IntFlags), CalcError> {
+        let x = word.to_pattern(a)?;
+        let bits = word.bits;
+        let bit = |pattern: u64, n: u32| n < 64 && pattern >> n & 1 == 1;
+        let mut flags = IntFlags::default();
+        // The carry receives the last bit shifted or rotated out
+        let pattern = match self {
+            Op::And => x & word.to_pattern(b)?,
+            Op::Or => x | word.to_pattern(b)?,
+            Op::Xor => x ^ word.to_pattern(b)?,
+            Op::Shl => {
+                let n = shift_count(b)?;
+                flags.carry = n > 0 && n <= bits && bit(x, bits - n);
+                x.checked_shl(n).unwrap_or(0)
+            }
+            Op::Shr => {
+                let n = shift_count(b)?;
+                flags.carry = n > 0 && bit(x, n - 1);
+                x.checked_shr(n).unwrap_or(0)
+            }
+            Op::Asr => {
+                // Sign-extend from the word's top bit, then shift
+                let n = shift_count(b)?.min(bits);
+                flags.carry = n > 0 && bit(x, (n - 1).min(bits - 1));
+                let extended = ((x << (64 - bits)) as i64) >> (64 - bits);
+                (extended >> n.min(bits - 1)) as u64
+            }
+            Op::Rol | Op::Ror => {
+                let n = shift_count(b)? % bits;
+                let n = if *self == Op::Ror { (bits - n) % bits } else { n };
+                let pattern = if n == 0 { x } else { (x << n) | (x >> (bits - n)) };
+                if shift_count(b)? > 0 {
+                    let out = if *self == Op::Rol { 0 } else { bits - 1 };
+                    flags.carry = bit(pattern & word.mask(), out);
+                }
+                pattern
+            }
+            _ => unreachable!(),
+        };
+        Ok((word.to_value(pattern & word.mask()), flags))
+    }
+}
+
+/// How an integer word represents negative numbers
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum SignMode {
+    #[default]
+    TwosComplement,
+    OnesComplement,
+    Unsigned,
+}
+
+impl SignMode {
+    pub fn cycle(&self) -> Self {
+        match self {
+            SignMode::TwosComplement => SignMode::OnesComplement,
+            SignMode::OnesComplement => SignMode::Unsigned,
+            SignMode::Unsigned => SignMode::TwosComplement,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SignMode::TwosComplement => "2C",
+            SignMode::OnesComplement => "1C",
+            SignMode::Unsigned => "U",
+        }
+    }
+
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            SignMode::TwosComplement => 0,
+            SignMode::OnesComplement => 1,
+            SignMode::Unsigned => 2,
+        }
+    }
+
+    pub fn from_u8(v: u8) -> Self {
+        match v {
+            1 => SignMode::OnesComplement,
+            2 => SignMode::Unsigned,
+            _ => SignMode::TwosComplement,
+        }
+    }
+}
+
+/// Integer word used by the non-decimal bases (HP-16C style)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WordSize {
+    /// 8, 16, 32 or 64
+    pub bits: u32,
+    pub sign: SignMode,
+}
+
+impl Default for WordSize {
+    fn default() -> Self {
+        Self::new(64, SignMode::TwosComplement)
+    }
+}
+
+impl WordSize {
+    pub fn new(bits: u32, sign: SignMode) -> Self {
+        let bits = match bits {
+            8 | 16 | 32 => bits,
+            _ => 64,
+        };
+        Self { bits, sign }
+    }
+
+    /// Next word size: 8 → 16 → 32 → 64 → 8
+    pub fn cycle_bits(&self) -> Self {
+        let bits = if self.bits == 64 { 8 } else { self.bits * 2 };
+        Self::new(bits, self.sign)
+    }
+
+    /// Status bar label, e.g. `32-2C`
+    pub fn label(&self) -> alloc::string::String {
+        alloc::format!("{}-{}", self.bits, self.sign.label())
+    }
+
+    pub fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.bits)
+    }
+
+    /// Bit pattern of an integer, wrapped to the word
+    pub fn to_pattern(&self, x: f64) -> Result<u64, CalcError> {
+        const TWO_64: f64 = 18446744073709551616.0;
+        if x.fract() != 0.0 || !x.is_finite() {
+            return Err(CalcError::DomainError("bitwise: integers only"));
+        }
+        if x.abs() > TWO_64 {
+            return Err(CalcError::Overflow);
+        }
+        // 2^64 is what u64::MAX rounds to, so it stands for all ones
+        let pattern = if x == TWO_64 {
+            u64::MAX
+        } else if x < 0.0 && self.sign == SignMode::OnesComplement {
+            !((-x) as u64)
+        } else {
+            x as i128 as u64
+        };
+        Ok(pattern & self.mask())
+    }
+
+    /// Value of a bit pattern in this word
+    pub fn to_value(&self, pattern: u64) -> f64 {
+        self.to_int(pattern) as f64
+    }
+
+    /// Exact value of a bit pattern in this word
+    pub fn to_int(&self, pattern: u64) -> i128 {
+        let pattern = pattern & self.mask();
+        let negative = pattern >> (self.bits - 1) & 1 == 1;
+        match self.sign {
+            SignMode::TwosComplement if negative => pattern as i128 - (1 << self.bits),
+            SignMode::OnesComplement if negative => -((!pattern & self.mask()) as i128),
+            _ => pattern as i128,
+        }
+    }
+
+    /// Smallest and largest values the word holds
+    pub fn range(&self) -> (i128, i128) {
+        let half = 1i128 << (self.bits - 1);
+        match self.sign {
+            SignMode::Unsigned => (0, self.mask() as i128),
+            SignMode::TwosComplement => (-half, half - 1),
+            SignMode::OnesComplement => (1 - half, half - 1),
+        }
+    }
+
+    /// Wrap an integer around to fit the word, noting whether it had to.
+    /// One's complement arithmetic is modulo 2^bits - 1 (end-around carry).
+    pub fn wrap_int(&self, n: i128) -> (i128, bool) {
+        let (min, max) = self.range();
+        if (min..=max).contains(&n) {
+            return (n, false);
+        }
+        let modulus = match self.sign {
+            SignMode::OnesComplement => (1 << self.bits) - 1,
+            _ => 1 << self.bits,
+        };
+        let r = n.rem_euclid(modulus);
+        (if r > max { r - modulus } else { r }, true)
+    }
+
+    /// Product within the word, noting whether it was out of range
+    fn mul(&self, x: i128, y: i128) -> (i128, bool) {
+        match x.checked_mul(y) {
+            Some(n) => self.wrap_int(n),
+            // Only 64-bit unsigned products get here; wrapping keeps the low bits
+            None => (self.wrap_int(x.wrapping_mul(y)).0, true),
+        }
+    }
+
+    /// Integer power within the word, truncating negative exponents
+    fn pow(&self, base: i128, exp: i128) -> Result<(i128, bool), CalcError> {
+        if exp < 0 {
+            return match base {
+                0 => Err(CalcError::DivideByZero),
+                1 => Ok((1, false)),
+                -1 => Ok((if exp % 2 == 0 { 1 } else { -1 }, false)),
+                _ => Ok((0, false)),
+            };
+        }
+        // Square and multiply; every partial result divides the exact power,
+        // so any wrap along the way means the power is out of range too
+        let (mut result, mut square, mut exp) = (1, base, exp);
+        let mut wrapped = false;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                let (n, w) = self.mul(result, square);
+                result = n;
+                wrapped |= w;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                let (n, w) = self.mul(square, square);
+                square = n;
+                wrapped |= w;
+            }
+        }
+        Ok((result, wrapped))
+    }
+
+    /// Truncate a value to an integer and wrap it around to fit the word
+    pub fn wrap(&self, x: f64) -> Result<f64, CalcError> {
+        Ok(self.to_value(self.to_pattern(x.trunc())?))
+    }
+
+    /// Fit the real result of a non-integer function into the word
+    pub fn fit_result(&self, x: f64) -> Result<(f64, IntFlags), CalcError> {
+        let (min, max) = self.range();
+        let overflow = x.trunc() < min as f64 || x.trunc() > max as f64;
+        Ok((self.wrap(x)?, IntFlags { carry: false, overflow }))
+    }
+}
+
+/// Status flags set by integer word arithmetic
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct IntFlags {
+    /// Carry out of (or borrow into) the top bit, an inexact quotient, or
+    /// the last bit shifted out
+    pub carry: bool,
+    /// The exact result did not fit the word
+    pub overflow: bool,
+}
+
+impl IntFlags {
+    /// Flags raised by either of two operations
+    pub fn union(self, other: IntFlags) -> IntFlags {
+        IntFlags {
+            carry: self.carry || other.carry,
+            overflow: self.overflow || other.overflow,
+        }
+    }
+}
+
+/// Bits `hi..=lo` of a pattern, shifted down to bit 0
+pub fn bit_field(pattern: u64, hi: u32, lo: u32) -> u64 {
+    (pattern >> lo) & (u64::MAX >> (63 - (hi - lo)))
+}
+
+/// A pattern with bits `hi..=lo` replaced by the low bits of `field`
+pub fn with_bit_field(pattern: u64, hi: u32, lo: u32, field: u64) -> u64 {
+    let mask = (u64::MAX >> (63 - (hi - lo))) << lo;
+    (pattern & !mask) | ((field << lo) & mask)
+}
+
+/// Shift or rotate distance
+fn shift_count(x: f64) -> Result<u32, CalcError> {
+    if x < 0.0 || x.fract() != 0.0 {
+        Err(CalcError::DomainError("shift count: integer ≥ 0"))
+    } else {
+        Ok(x.min(u32::MAX as f64) as u32)
+    }
+}
+
+/// Range of characters (not bytes) in an input line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Span covering both this one and `other`
+    pub fn to(self, other: Span) -> Self {
+        Self::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// Move the span right by `offset` characters
+    pub fn shift(self, offset: usize) -> Self {
+        Self::new(self.start + offset, self.end + offset)
+    }
 }
 
 /// Calculator errors
@@ -495,6 +1827,14 @@ pub enum CalcError {
     ParseError(alloc::string::String),
     SyntaxError(alloc::string::String),
     MemoryError,
+    /// Quantities whose dimensions do not fit the operation
+    DimensionError(&'static str),
+    /// User functions nested deeper than `userfunc::MAX_CALL_DEPTH`
+    RecursionLimit,
+    /// An iterative solver that gave up before settling on an answer
+    NoConvergence,
+    /// An error located at a span of the input line
+    At(Span, alloc::boxed::Box<CalcError>),
 }
 
 extern crate alloc;
@@ -508,6 +1848,36 @@ impl CalcError {
             CalcError::ParseError(_) => "ERR: PARSE",
             CalcError::SyntaxError(_) => "ERR: SYNTAX",
             CalcError::MemoryError => "ERR: MEMORY",
+            CalcError::DimensionError(_) => "ERR: DIMENSION",
+            CalcError::RecursionLimit => "ERR: RECURSION",
+            CalcError::NoConvergence => "ERR: NO CONVERGENCE",
+            CalcError::At(_, err) => err.message(),
+        }
+    }
+
+    /// Message including the parser's description of what went wrong
+    pub fn detail(&self) -> &str {
+        match self {
+            CalcError::ParseError(msg) | CalcError::SyntaxError(msg) => msg,
+            CalcError::DimensionError(msg) => msg,
+            CalcError::At(_, err) => err.detail(),
+            _ => self.message(),
+        }
+    }
+
+    /// Where in the input the error occurred, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CalcError::At(span, _) => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// Locate the error at `span`, replacing any previous location
+    pub fn at(self, span: Span) -> Self {
+        match self {
+            CalcError::At(_, err) => CalcError::At(span, err),
+            err => CalcError::At(span, alloc::boxed::Box::new(err)),
         }
     }
 }
@@ -531,4 +1901,251 @@ mod tests {
         assert_eq!(factorial(5.0).unwrap(), 120.0);
         assert_eq!(factorial(10.0).unwrap(), 3628800.0);
     }
+
+    #[test]
+    fn test_double_factorial() {
+        assert_eq!(double_factorial(-1.0).unwrap(), 1.0);
+        assert_eq!(double_factorial(0.0).unwrap(), 1.0);
+        assert_eq!(double_factorial(7.0).unwrap(), 105.0);
+        assert_eq!(double_factorial(8.0).unwrap(), 384.0);
+        assert!(double_factorial(2.5).is_err());
+    }
+
+    #[test]
+    fn test_multi_argument() {
+        let deg = AngleMode::Degrees;
+        assert!((Func::Atan2.evaluate_args(&[1.0, 1.0], deg).unwrap() - 45.0).abs() < 1e-10);
+        assert_eq!(Func::Max.evaluate_args(&[3.0, 7.0, 5.0], deg).unwrap(), 7.0);
+        assert_eq!(Func::Min.evaluate_args(&[3.0, 7.0, 5.0], deg).unwrap(), 3.0);
+        assert!((Func::Log.evaluate_args(&[8.0, 2.0], deg).unwrap() - 3.0).abs() < 1e-10);
+        assert!((Func::Root.evaluate_args(&[27.0, 3.0], deg).unwrap() - 3.0).abs() < 1e-10);
+        assert!((Func::Root.evaluate_args(&[-8.0, 3.0], deg).unwrap() + 2.0).abs() < 1e-10);
+        assert_eq!(Func::NCr.evaluate_args(&[10.0, 3.0], deg).unwrap(), 120.0);
+        assert_eq!(Func::NPr.evaluate_args(&[10.0, 3.0], deg).unwrap(), 720.0);
+        assert_eq!(Func::Hypot.evaluate_args(&[3.0, 4.0], deg).unwrap(), 5.0);
+
+        assert!(Func::Atan2.evaluate_args(&[1.0], deg).is_err());
+        assert!(Func::Max.evaluate_args(&[], deg).is_err());
+        assert!(Func::Root.evaluate_args(&[-4.0, 2.0], deg).is_err());
+
+        // Overflow stops the loop rather than running all k steps
+        let started = std::time::Instant::now();
+        assert!(matches!(Func::NCr.evaluate_args(&[1e18, 5e17], deg), Err(CalcError::Overflow)));
+        assert!(matches!(Func::NCr.evaluate_args(&[2e9, 1e9], deg), Err(CalcError::Overflow)));
+        assert!(started.elapsed().as_millis() < 100);
+    }
+
+    #[test]
+    fn test_bitwise() {
+        assert_eq!(Op::And.evaluate(255.0, 10.0).unwrap(), 10.0);
+        assert_eq!(Op::Or.evaluate(12.0, 3.0).unwrap(), 15.0);
+        assert_eq!(Op::Xor.evaluate(6.0, 3.0).unwrap(), 5.0);
+        assert_eq!(Op::And.evaluate(-1.0, 0xFF as f64).unwrap(), 255.0);
+        assert_eq!(Func::Not.evaluate(0.0, AngleMode::Degrees).unwrap(), -1.0);
+        assert_eq!(Func::Not.evaluate(5.0, AngleMode::Degrees).unwrap(), -6.0);
+
+        assert_eq!(Op::Shl.evaluate(1.0, 4.0).unwrap(), 16.0);
+        assert_eq!(Op::Shl.evaluate(1.0, 64.0).unwrap(), 0.0);
+        assert_eq!(Op::Shr.evaluate(-16.0, 60.0).unwrap(), 15.0);
+        assert_eq!(Op::Asr.evaluate(-16.0, 2.0).unwrap(), -4.0);
+        assert_eq!(Op::Asr.evaluate(-16.0, 100.0).unwrap(), -1.0);
+        assert_eq!(Op::Rol.evaluate(1.0, 65.0).unwrap(), 2.0);
+        assert_eq!(Op::Ror.evaluate(1.0, 1.0).unwrap(), i64::MIN as f64);
+
+        assert!(Op::And.evaluate(2.5, 1.0).is_err());
+        assert!(Op::Shl.evaluate(1.0, -1.0).is_err());
+        assert!(Op::Or.evaluate(1e30, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_logical() {
+        let deg = AngleMode::Degrees;
+        assert_eq!(Op::Less.evaluate(1.0, 2.0).unwrap(), 1.0);
+        assert_eq!(Op::GreaterEq.evaluate(1.0, 2.0).unwrap(), 0.0);
+        assert_eq!(Op::NotEqual.evaluate(1.0, 2.0).unwrap(), 1.0);
+        assert_eq!(Op::LogicalAnd.evaluate(3.0, -1.0).unwrap(), 1.0);
+        assert_eq!(Op::LogicalOr.evaluate(0.0, 0.0).unwrap(), 0.0);
+        assert_eq!(Func::LogicalNot.evaluate(0.5, deg).unwrap(), 0.0);
+        assert_eq!(Func::If.evaluate_args(&[0.0, 1.0, 2.0], deg).unwrap(), 2.0);
+        assert!(Func::If.evaluate_args(&[1.0, 2.0], deg).is_err());
+
+        // Word values compare as the word reads them
+        let s8 = WordSize::new(8, SignMode::TwosComplement);
+        let u8w = WordSize::new(8, SignMode::Unsigned);
+        assert_eq!(Op::Less.evaluate_word(-1.0, 0.0, s8).unwrap().0, 1.0);
+        assert_eq!(Op::Less.evaluate_word(255.0, 0.0, u8w).unwrap().0, 0.0);
+    }
+
+    #[test]
+    fn test_complex() {
+        let (deg, real, rect) = (AngleMode::Degrees, ComplexMode::Real, ComplexMode::Rectangular);
+        let close = |z: Result<Complex, CalcError>, re: f64, im: f64| {
+            let z = z.unwrap();
+            (z.re - re).abs() < 1e-10 && (z.im - im).abs() < 1e-10
+        };
+
+        // Real arguments only leave the real line in a complex mode
+        assert!(Func::Sqrt.evaluate_complex(Complex::from(-4.0), deg, real).is_err());
+        assert!(close(Func::Sqrt.evaluate_complex(Complex::from(-4.0), deg, rect), 0.0, 2.0));
+        assert!(close(Func::Ln.evaluate_complex(Complex::from(-1.0), deg, rect), 0.0, PI));
+        assert!(close(Func::Asin.evaluate_complex(Complex::from(1.0), deg, rect), 90.0, 0.0));
+        assert!(close(Op::Pow.evaluate_complex(Complex::from(-8.0), Complex::from(0.5), rect), 0.0, 8f64.sqrt()));
+        assert!(Op::Pow.evaluate_complex(Complex::from(-8.0), Complex::from(0.5), real).is_err());
+
+        // ... but complex arguments always give complex results
+        let z = Complex::new(3.0, -4.0);
+        assert!(close(Func::Sqrt.evaluate_complex(z, deg, real), 2.0, -1.0));
+        assert!(close(Func::Re.evaluate_complex(z, deg, real), 3.0, 0.0));
+        assert!(close(Func::Im.evaluate_complex(z, deg, real), -4.0, 0.0));
+        assert!(close(Func::Abs.evaluate_complex(z, deg, real), 5.0, 0.0));
+        assert!(close(Func::Conj.evaluate_complex(z, deg, real), 3.0, 4.0));
+        assert!(close(Func::Arg.evaluate_complex(Complex::I, deg, real), 90.0, 0.0));
+        assert!(close(Func::Sin.evaluate_complex(Complex::new(90.0, 0.0), deg, rect), 1.0, 0.0));
+        assert!(close(Func::Log.evaluate_args_complex(&[Complex::from(-8.0), Complex::from(2.0)], deg, rect), 3.0, PI / core::f64::consts::LN_2));
+        assert!(close(Func::Root.evaluate_args_complex(&[Complex::new(0.0, 8.0), Complex::from(3.0)], deg, real), 3f64.sqrt(), 1.0));
+        assert!(Func::Factorial.evaluate_complex(Complex::I, deg, rect).is_err());
+        assert!(Func::Max.evaluate_args_complex(&[Complex::I, Complex::ONE], deg, rect).is_err());
+        assert!(Op::Less.evaluate_complex(Complex::I, Complex::ONE, rect).is_err());
+        assert_eq!(Op::Equal.evaluate_complex(Complex::I, Complex::I, real).unwrap(), Complex::ONE);
+
+        // r∠θ reads θ in the angle mode, or in its own unit
+        let polar = |r: Quantity, theta: Quantity, mode| Op::Polar.evaluate_quantity(r, theta, mode, real).unwrap().complex();
+        assert!(close(Ok(polar(2.0.into(), 90.0.into(), deg)), 0.0, 2.0));
+        assert!(close(Ok(polar(2.0.into(), PI.into(), AngleMode::Radians)), -2.0, 0.0));
+        let half_turn = Quantity::new(PI, crate::quantity::Dims::ANGLE);
+        assert!(close(Ok(polar(1.0.into(), half_turn, deg)), -1.0, 0.0));
+        assert!(Op::Polar.evaluate_quantity(Complex::I.into(), 1.0.into(), deg, real).is_err());
+    }
+
+    #[test]
+    fn test_float_formats() {
+        use FloatFormat::*;
+        assert_eq!(Single.encode(1.5), 0x3FC0_0000);
+        assert_eq!(Double.encode(-2.0), 0xC000_0000_0000_0000);
+        assert_eq!(Half.encode(1.0), 0x3C00);
+        assert_eq!(Half.encode(-2.5), 0xC100);
+        assert_eq!(Half.encode(65504.0), 0x7BFF);
+        assert_eq!(Half.encode(65520.0), 0x7C00);
+        assert_eq!(Half.encode(2f64.powi(-24)), 0x0001);
+        // 1 + 2^-11 is halfway between two f16 values: ties to even
+        assert_eq!(Half.encode(1.0 + 2f64.powi(-11)), 0x3C00);
+        assert_eq!(Half.encode(f64::NAN), 0x7E00);
+
+        assert_eq!(Half.decode(0x3555), 0.333251953125);
+        assert_eq!(Half.decode(0x0400), 2f64.powi(-14));
+        assert_eq!(Half.decode(0xFC00), f64::NEG_INFINITY);
+        assert!(Half.decode(0x7C01).is_nan());
+        assert_eq!(Single.decode(0x4049_0FDB), core::f32::consts::PI as f64);
+        for x in [0.1, -7.25, 1e300] {
+            assert_eq!(Double.decode(Double.encode(x)), x);
+        }
+
+        assert_eq!(Half.fields(0xC100), (true, 16, 0x100));
+        assert_eq!(Single.fields(0x3FC0_0000), (false, 127, 0x40_0000));
+        assert_eq!(Double.bias(), 1023);
+        assert_eq!(Half.mantissa_bits(), 10);
+
+        let deg = AngleMode::Degrees;
+        assert_eq!(Func::FloatBits(Single).evaluate(1.5, deg).unwrap(), 0x3FC0_0000 as f64);
+        assert_eq!(Func::FromFloatBits(Single).evaluate(0x3FC0_0000 as f64, deg).unwrap(), 1.5);
+        assert_eq!(Func::FromFloatBits(Half).evaluate(0x3C00 as f64, deg).unwrap(), 1.0);
+        assert!(Func::FromFloatBits(Half).evaluate(0x10000 as f64, deg).is_err());
+        assert!(Func::FromFloatBits(Single).evaluate(1.5, deg).is_err());
+    }
+
+    #[test]
+    fn test_bit_fields() {
+        assert_eq!(bit_field(0xABCD, 15, 8), 0xAB);
+        assert_eq!(bit_field(0xABCD, 3, 0), 0xD);
+        assert_eq!(bit_field(u64::MAX, 63, 0), u64::MAX);
+        assert_eq!(with_bit_field(0xABCD, 11, 4, 0x12), 0xA12D);
+        assert_eq!(with_bit_field(0, 63, 63, 1), 1 << 63);
+        // Only the field's width is written
+        assert_eq!(with_bit_field(0, 3, 0, 0xFF), 0xF);
+
+        let deg = AngleMode::Degrees;
+        assert_eq!(Func::Bits.evaluate_args(&[0xABCD as f64, 11.0, 4.0], deg).unwrap(), 0xBC as f64);
+        assert_eq!(Func::Bits.evaluate_args(&[-1.0, 7.0, 0.0], deg).unwrap(), 255.0);
+        assert!(Func::Bits.evaluate_args(&[1.0, 2.0, 3.0], deg).is_err());
+        assert!(Func::Bits.evaluate_args(&[1.0, 64.0, 0.0], deg).is_err());
+        assert!(Func::Bits.evaluate_args(&[1.5, 3.0, 0.0], deg).is_err());
+    }
+
+    #[test]
+    fn test_word_size() {
+        let u8w = WordSize::new(8, SignMode::Unsigned);
+        let s8 = WordSize::new(8, SignMode::TwosComplement);
+        let c8 = WordSize::new(8, SignMode::OnesComplement);
+
+        assert_eq!(u8w.wrap(256.0).unwrap(), 0.0);
+        assert_eq!(u8w.wrap(-1.0).unwrap(), 255.0);
+        assert_eq!(s8.wrap(128.0).unwrap(), -128.0);
+        assert_eq!(s8.wrap(-129.0).unwrap(), 127.0);
+        assert_eq!(s8.wrap(3.7).unwrap(), 3.0);
+        assert_eq!(s8.to_pattern(-1.0).unwrap(), 0xFF);
+        assert_eq!(c8.to_pattern(-1.0).unwrap(), 0xFE);
+        assert_eq!(c8.to_value(0xFE), -1.0);
+        assert_eq!(c8.to_value(0xFF), 0.0);
+
+        assert_eq!(Op::Add.evaluate_word(255.0, 1.0, u8w).unwrap().0, 0.0);
+        assert_eq!(Op::Add.evaluate_word(127.0, 1.0, s8).unwrap().0, -128.0);
+        assert_eq!(Op::Shl.evaluate_word(0x81 as f64, 1.0, u8w).unwrap().0, 2.0);
+        assert_eq!(Op::Rol.evaluate_word(0x81 as f64, 1.0, u8w).unwrap().0, 3.0);
+        assert_eq!(Op::Ror.evaluate_word(1.0, 1.0, u8w).unwrap().0, 128.0);
+        assert_eq!(Op::Asr.evaluate_word(0x80 as f64, 2.0, u8w).unwrap().0, 0xE0 as f64);
+        assert_eq!(Op::Shr.evaluate_word(-128.0, 2.0, s8).unwrap().0, 32.0);
+        assert_eq!(Func::Not.evaluate_word(0.0, AngleMode::Degrees, u8w).unwrap().0, 255.0);
+        assert_eq!(Func::Not.evaluate_word(0.0, AngleMode::Degrees, s8).unwrap().0, -1.0);
+        assert_eq!(Func::Negate.evaluate_word(1.0, AngleMode::Degrees, u8w).unwrap().0, 255.0);
+
+        assert_eq!(WordSize::new(64, SignMode::Unsigned).cycle_bits().bits, 8);
+        assert_eq!(s8.label(), "8-2C");
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        let u8w = WordSize::new(8, SignMode::Unsigned);
+        let s8 = WordSize::new(8, SignMode::TwosComplement);
+        let c8 = WordSize::new(8, SignMode::OnesComplement);
+        let u64w = WordSize::new(64, SignMode::Unsigned);
+        let flags = |carry, overflow| IntFlags { carry, overflow };
+        let eval = |op: Op, a: f64, b: f64, word| op.evaluate_word(a, b, word).unwrap();
+
+        assert_eq!(eval(Op::Div, 7.0, 2.0, s8), (3.0, flags(true, false)));
+        assert_eq!(eval(Op::Div, -7.0, 2.0, s8), (-3.0, flags(true, false)));
+        assert_eq!(eval(Op::Div, 8.0, 2.0, s8), (4.0, flags(false, false)));
+        assert_eq!(eval(Op::Mod, -7.0, 2.0, s8), (-1.0, flags(false, false)));
+        assert_eq!(eval(Op::Div, -128.0, -1.0, s8), (-128.0, flags(false, true)));
+        assert!(Op::Div.evaluate_word(1.0, 0.0, s8).is_err());
+
+        assert_eq!(eval(Op::Add, 255.0, 1.0, u8w), (0.0, flags(true, true)));
+        assert_eq!(eval(Op::Add, -1.0, 1.0, s8), (0.0, flags(true, false)));
+        assert_eq!(eval(Op::Add, 127.0, 1.0, s8), (-128.0, flags(false, true)));
+        assert_eq!(eval(Op::Sub, 0.0, 1.0, u8w), (255.0, flags(true, true)));
+        assert_eq!(eval(Op::Sub, 0.0, 1.0, s8), (-1.0, flags(true, false)));
+        assert_eq!(eval(Op::Mul, 16.0, 16.0, u8w), (0.0, flags(false, true)));
+        assert_eq!(eval(Op::Mul, -8.0, 16.0, s8), (-128.0, flags(false, false)));
+        // One's complement wraps with an end-around carry
+        assert_eq!(eval(Op::Add, 127.0, 1.0, c8), (-127.0, flags(false, true)));
+
+        assert_eq!(eval(Op::Pow, 2.0, 7.0, u8w), (128.0, flags(false, false)));
+        assert_eq!(eval(Op::Pow, 2.0, 8.0, u8w), (0.0, flags(false, true)));
+        assert_eq!(eval(Op::Pow, 3.0, 5.0, u8w), (243.0, flags(false, false)));
+        assert_eq!(eval(Op::Pow, 2.0, -1.0, s8), (0.0, flags(false, false)));
+        assert_eq!(eval(Op::Pow, 2.0, 63.0, u64w).0, 9223372036854775808.0);
+        assert!(!eval(Op::Mul, 4294967296.0, 4294967295.0, u64w).1.overflow);
+        assert!(eval(Op::Mul, 4294967296.0, 4294967296.0, u64w).1.overflow);
+
+        assert_eq!(eval(Op::Shl, 0x81 as f64, 1.0, u8w), (2.0, flags(true, false)));
+        assert_eq!(eval(Op::Shr, 2.0, 2.0, u8w), (0.0, flags(true, false)));
+        assert_eq!(eval(Op::Ror, 1.0, 1.0, u8w), (128.0, flags(true, false)));
+
+        let neg = |x, word| Func::Negate.evaluate_word(x, AngleMode::Degrees, word).unwrap();
+        assert_eq!(neg(-128.0, s8), (-128.0, flags(false, true)));
+        assert_eq!(neg(5.0, s8), (-5.0, flags(false, false)));
+        assert_eq!(
+            Func::Sqrt.evaluate_word(10.0, AngleMode::Degrees, s8).unwrap(),
+            (3.0, flags(false, false))
+        );
+    }
 }
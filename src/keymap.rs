@@ -1,6 +1,7 @@
 //! Keyboard to operation mapping
 
 use crate::functions::{Func, Op};
+use crate::units::Dimension;
 
 /// Key action result
 #[derive(Debug, Clone)]
@@ -19,10 +20,27 @@ pub enum KeyAction {
     OpenParen,
     /// Close parenthesis
     CloseParen,
+    /// Open/close a matrix or vector literal (algebraic mode)
+    OpenBracket,
+    CloseBracket,
+    /// Argument and matrix element separator (algebraic mode)
+    Comma,
     /// Execute/Enter
     Execute,
+    /// `=` key: assigns after a bare variable name, otherwise executes
+    Equals,
+    /// Insert store arrow `→` (algebraic mode)
+    Assign,
     /// Backspace
     Backspace,
+    /// Delete the character under the cursor (algebraic mode)
+    Delete,
+    /// Move the input cursor left/right (algebraic mode)
+    CursorLeft,
+    CursorRight,
+    /// Move the input cursor to the start/end of the line (algebraic mode)
+    CursorHome,
+    CursorEnd,
     /// Clear entry (CLx)
     ClearEntry,
     /// Clear all (AC)
@@ -37,6 +55,30 @@ pub enum KeyAction {
     CycleAngle,
     /// Cycle number base (DEC/HEX/OCT/BIN)
     CycleBase,
+    /// Cycle integer word size (8/16/32/64 bits)
+    CycleWordSize,
+    /// Cycle integer sign mode (2C/1C/U)
+    CycleSignMode,
+    /// Cycle complex mode (REAL/a+bi/r∠θ)
+    CycleComplex,
+    /// Show the full bit pattern of the current value
+    BinaryView,
+    /// Open the bit-field inspector on the current value
+    BitInspector,
+    /// Browse the physical constants
+    Constants,
+    /// Open the grid editor on the current matrix, or a new one
+    MatrixEditor,
+    /// Show the current matrix full-screen
+    MatrixView,
+    /// Open the linear system solver
+    LinearSystem,
+    /// Open the polynomial root finder
+    Polynomial,
+    /// Open the equation solver
+    Equation,
+    /// Pick the units of a conversion, then convert
+    ConvertUnits(Dimension),
     /// RPN: Swap X↔Y
     SwapXY,
     /// RPN: Roll down
@@ -51,6 +93,10 @@ pub enum KeyAction {
     Recall,
     /// Open function menu (F1-F4)
     FnMenu(u8),
+    /// Open the user-defined function list
+    UserFunctions,
+    /// Start browsing the history tape
+    BrowseHistory,
     /// Menu selection (0-9)
     MenuSelect(u8),
     /// Cancel/Escape
@@ -147,16 +193,33 @@ pub fn map_key(c: char, state: &mut KeyState, is_rpn: bool) -> KeyAction {
         '/' | '÷' => KeyAction::Operator(Op::Div),
         '^' => KeyAction::Operator(Op::Pow),
         '%' => KeyAction::Operator(Op::Mod),
+        '&' => KeyAction::Operator(Op::And),
+        '|' => KeyAction::Operator(Op::Or),
+        '~' => KeyAction::Function(Func::Not),
+        '<' => KeyAction::Operator(Op::Less),
+        '>' => KeyAction::Operator(Op::Greater),
+        '!' => KeyAction::Function(Func::Factorial),
 
         // Parentheses
-        '(' | '[' => KeyAction::OpenParen,
-        ')' | ']' => KeyAction::CloseParen,
+        '(' => KeyAction::OpenParen,
+        ')' => KeyAction::CloseParen,
+
+        // Matrix literals and argument lists
+        '[' => KeyAction::OpenBracket,
+        ']' => KeyAction::CloseBracket,
+        ',' => KeyAction::Comma,
 
         // Control
-        '\r' | '\n' | '=' => KeyAction::Execute,
+        '\r' | '\n' => KeyAction::Execute,
+        '=' => KeyAction::Equals,
         '\u{0008}' => KeyAction::Backspace, // Backspace
         ' ' => KeyAction::ClearEntry,
 
+        // Arrow keys move the cursor through the input line
+        '←' => KeyAction::CursorLeft,
+        '→' => KeyAction::CursorRight,
+        '↑' | '↓' => KeyAction::BrowseHistory,
+
         // In RPN mode, letters are commands
         // In algebraic mode, lowercase letters are for function names (sin, cos, sqrt, etc.)
 
@@ -180,13 +243,14 @@ pub fn map_key(c: char, state: &mut KeyState, is_rpn: bool) -> KeyAction {
         // Ans - uppercase only in RPN, lowercase allowed in algebraic for expression
         'N' if is_rpn => KeyAction::Ans,
 
-        // In algebraic mode, pass lowercase letters through for function names
-        'a'..='z' if !is_rpn => KeyAction::Letter(c),
+        // In algebraic mode, pass lowercase letters and `_` through for names
+        'a'..='z' | '_' if !is_rpn => KeyAction::Letter(c),
 
         // In RPN mode, some letters are commands, others are ignored
         'a' if is_rpn => KeyAction::CycleAngle,
         'b' if is_rpn => KeyAction::CycleBase,
         'n' if is_rpn => KeyAction::Ans,
+        'i' if is_rpn => KeyAction::Function(Func::I),
 
         // Function keys (using F1-F4 scan codes may vary)
         '\u{F704}' => KeyAction::FnMenu(1), // F1
@@ -235,6 +299,14 @@ fn map_shifted_key(c: char) -> KeyAction {
         // Change sign
         '-' => KeyAction::ChangeSign,
 
+        // Store arrow for `expr→name`
+        '=' => KeyAction::Assign,
+
+        // Line editing
+        '\u{0008}' => KeyAction::Delete,
+        '←' => KeyAction::CursorHome,
+        '→' => KeyAction::CursorEnd,
+
         // More functions via letters
         's' | 'S' => KeyAction::Function(Func::Asin),
         'c' | 'C' => KeyAction::Function(Func::Acos),
@@ -244,6 +316,24 @@ fn map_shifted_key(c: char) -> KeyAction {
         'r' | 'R' => KeyAction::Function(Func::Cbrt),
         'f' | 'F' => KeyAction::Function(Func::Factorial),
         'a' | 'A' => KeyAction::Function(Func::Abs),
+        'p' | 'P' => KeyAction::Constants,
+        // Unit conversion menu
+        'u' | 'U' => KeyAction::FnMenu(7),
+        // Complex number menu
+        'i' | 'I' => KeyAction::FnMenu(8),
+        // Matrix menu
+        'm' | 'M' => KeyAction::FnMenu(9),
+        // Solver menu
+        'q' | 'Q' => KeyAction::FnMenu(10),
+
+        // Multi-argument function menu
+        '\u{F704}' => KeyAction::FnMenu(5), // Shift+F1
+        // User-defined functions
+        '\u{F705}' => KeyAction::UserFunctions, // Shift+F2
+        // Bitwise operator menu
+        '\u{F706}' => KeyAction::FnMenu(6), // Shift+F3
+        // Every bit of the current value
+        '\u{F707}' => KeyAction::BinaryView, // Shift+F4
 
         _ => KeyAction::None,
     }
@@ -290,6 +380,9 @@ pub fn map_fn_menu_key(menu: u8, key: u8) -> KeyAction {
                 1 => KeyAction::ToggleMode,
                 2 => KeyAction::CycleAngle,
                 3 => KeyAction::CycleBase,
+                4 => KeyAction::CycleWordSize,
+                5 => KeyAction::CycleSignMode,
+                6 => KeyAction::CycleComplex,
                 _ => KeyAction::None,
             }
         }
@@ -300,6 +393,87 @@ pub fn map_fn_menu_key(menu: u8, key: u8) -> KeyAction {
                 _ => KeyAction::None,
             }
         }
+        5 => {
+            // FUNC menu (multi-argument; RPN takes arguments from Y and X)
+            match key {
+                1 => KeyAction::Function(Func::Atan2),
+                2 => KeyAction::Function(Func::Max),
+                3 => KeyAction::Function(Func::Min),
+                4 => KeyAction::Function(Func::LogBase),
+                5 => KeyAction::Function(Func::Root),
+                6 => KeyAction::Function(Func::NCr),
+                7 => KeyAction::Function(Func::NPr),
+                8 => KeyAction::Function(Func::Hypot),
+                9 => KeyAction::Function(Func::DoubleFactorial),
+                0 => KeyAction::Function(Func::Percent),
+                _ => KeyAction::None,
+            }
+        }
+        6 => {
+            // BIT menu (bitwise operators; RPN shifts Y by X)
+            match key {
+                1 => KeyAction::Operator(Op::And),
+                2 => KeyAction::Operator(Op::Or),
+                3 => KeyAction::Operator(Op::Xor),
+                4 => KeyAction::Function(Func::Not),
+                5 => KeyAction::Operator(Op::Shl),
+                6 => KeyAction::Operator(Op::Shr),
+                7 => KeyAction::Operator(Op::Asr),
+                8 => KeyAction::Operator(Op::Rol),
+                9 => KeyAction::Operator(Op::Ror),
+                0 => KeyAction::BitInspector,
+                _ => KeyAction::None,
+            }
+        }
+        7 => {
+            // UNIT menu (pick a dimension, then the units to convert between)
+            match Dimension::ALL.get((key as usize).wrapping_sub(1)) {
+                Some(&dimension) => KeyAction::ConvertUnits(dimension),
+                // Unused keys close the menu rather than quitting
+                None => KeyAction::Cancel,
+            }
+        }
+        8 => {
+            // CPLX menu
+            match key {
+                1 => KeyAction::Function(Func::I),
+                2 => KeyAction::Operator(Op::Polar),
+                3 => KeyAction::Function(Func::Re),
+                4 => KeyAction::Function(Func::Im),
+                5 => KeyAction::Function(Func::Abs),
+                6 => KeyAction::Function(Func::Arg),
+                7 => KeyAction::Function(Func::Conj),
+                8 => KeyAction::CycleComplex,
+                // Unused keys close the menu rather than quitting
+                _ => KeyAction::Cancel,
+            }
+        }
+        9 => {
+            // MTRX menu
+            match key {
+                1 => KeyAction::Function(Func::Det),
+                2 => KeyAction::Function(Func::Transpose),
+                3 => KeyAction::Function(Func::Inverse),
+                4 => KeyAction::Function(Func::Rref),
+                5 => KeyAction::Function(Func::Identity),
+                6 => KeyAction::Function(Func::Dot),
+                7 => KeyAction::Function(Func::Cross),
+                8 => KeyAction::Function(Func::Norm),
+                9 => KeyAction::MatrixEditor,
+                0 => KeyAction::MatrixView,
+                _ => KeyAction::None,
+            }
+        }
+        10 => {
+            // SOLVE menu
+            match key {
+                1 => KeyAction::LinearSystem,
+                2 => KeyAction::Polynomial,
+                3 => KeyAction::Equation,
+                // Unused keys close the menu rather than quitting
+                _ => KeyAction::Cancel,
+            }
+        }
         _ => KeyAction::None,
     }
 }
@@ -334,10 +508,75 @@ pub fn get_menu_items(menu: u8) -> &'static [(&'static str, &'static str)] {
             ("1", "ALG/RPN"),
             ("2", "DEG/RAD"),
             ("3", "DEC/HEX"),
+            ("4", "WORD 8-64"),
+            ("5", "SIGN 2C/1C/U"),
+            ("6", "REAL/a+bi/r∠θ"),
         ],
         4 => &[
             ("0-9", "Recall M#"),
         ],
+        5 => &[
+            ("1", "atan2"),
+            ("2", "max"),
+            ("3", "min"),
+            ("4", "logb"),
+            ("5", "root"),
+            ("6", "nCr"),
+            ("7", "nPr"),
+            ("8", "hypot"),
+            ("9", "n!!"),
+            ("0", "x%"),
+        ],
+        6 => &[
+            ("1", "AND"),
+            ("2", "OR"),
+            ("3", "XOR"),
+            ("4", "NOT"),
+            ("5", "<<"),
+            ("6", ">>"),
+            ("7", "ASR"),
+            ("8", "ROL"),
+            ("9", "ROR"),
+            ("0", "FIELDS"),
+        ],
+        7 => &[
+            ("1", "Length"),
+            ("2", "Mass"),
+            ("3", "Time"),
+            ("4", "Temp"),
+            ("5", "Energy"),
+            ("6", "Pressure"),
+            ("7", "Speed"),
+            ("8", "Data"),
+            ("9", "Angle"),
+        ],
+        8 => &[
+            ("1", "i"),
+            ("2", "r∠θ"),
+            ("3", "re"),
+            ("4", "im"),
+            ("5", "abs"),
+            ("6", "arg"),
+            ("7", "conj"),
+            ("8", "REAL/a+bi/r∠θ"),
+        ],
+        9 => &[
+            ("1", "det"),
+            ("2", "transpose"),
+            ("3", "inv"),
+            ("4", "rref"),
+            ("5", "identity"),
+            ("6", "dot"),
+            ("7", "cross"),
+            ("8", "norm"),
+            ("9", "EDIT"),
+            ("0", "VIEW"),
+        ],
+        10 => &[
+            ("1", "Ax=b"),
+            ("2", "POLY"),
+            ("3", "ROOT"),
+        ],
         _ => &[],
     }
 }
@@ -0,0 +1,175 @@
+//! Linear system solver: `A·x = b` for 2 to 6 unknowns
+
+use crate::functions::CalcError;
+use crate::matrix::{Matrix, MatrixEditor, MAX_SIZE};
+
+/// Fewest unknowns the solver screen offers
+pub const MIN_UNKNOWNS: usize = 2;
+
+/// Condition number above which a solution is flagged as unreliable: fewer
+/// of its digits can be trusted than the display shows
+pub const ILL_CONDITIONED: f64 = 1e6;
+
+/// Solution of a system, with how far it can be trusted
+#[derive(Clone, Copy, Debug)]
+pub struct Solution {
+    /// The unknowns, as a vector
+    pub x: Matrix,
+    /// Condition number of `A` in the 1-norm
+    pub condition: f64,
+}
+
+impl Solution {
+    pub fn is_ill_conditioned(&self) -> bool {
+        self.condition > ILL_CONDITIONED
+    }
+
+    /// Significant digits left after rounding errors are magnified by the
+    /// condition number
+    pub fn reliable_digits(&self) -> u32 {
+        (-(self.condition * f64::EPSILON).log10()).max(0.0) as u32
+    }
+}
+
+/// Solve the system whose augmented matrix `[A | b]` has n rows and n+1
+/// columns. Systems so ill-conditioned that no digit survives count as
+/// singular.
+pub fn solve(augmented: &Matrix) -> Result<Solution, CalcError> {
+    let n = augmented.rows();
+    if augmented.cols() != n + 1 {
+        return Err(CalcError::DimensionError("needs n rows of n+1 numbers"));
+    }
+    let a = augmented.resize(n, n)?;
+    let mut b = Matrix::zeros(1, n)?;
+    for r in 0..n {
+        b.set(0, r, augmented.get(r, n));
+    }
+    let condition = a.condition()?;
+    if condition * f64::EPSILON >= 1.0 {
+        return Err(CalcError::DomainError("singular: no unique solution"));
+    }
+    let x = a.solve(&b).map_err(|e| match e {
+        CalcError::DomainError(_) => CalcError::DomainError("singular: no unique solution"),
+        e => e,
+    })?;
+    Ok(Solution { x, condition })
+}
+
+/// The solver screen: the augmented matrix being filled in, then its
+/// solution
+pub struct LinearSystem {
+    editor: MatrixEditor,
+    solution: Option<Solution>,
+}
+
+impl LinearSystem {
+    /// An empty system of `n` equations in `n` unknowns
+    pub fn new(n: usize) -> Result<Self, CalcError> {
+        if !(MIN_UNKNOWNS..MAX_SIZE + 1).contains(&n) {
+            return Err(CalcError::DomainError("system: 2 to 6 unknowns"));
+        }
+        Ok(Self {
+            editor: MatrixEditor::new(Matrix::zeros(n, n)?.augment(&Matrix::zeros(1, n)?)?),
+            solution: None,
+        })
+    }
+
+    pub fn unknowns(&self) -> usize {
+        self.editor.matrix().rows()
+    }
+
+    pub fn editor(&self) -> &MatrixEditor {
+        &self.editor
+    }
+
+    pub fn editor_mut(&mut self) -> &mut MatrixEditor {
+        &mut self.editor
+    }
+
+    pub fn solution(&self) -> Option<&Solution> {
+        self.solution.as_ref()
+    }
+
+    /// Commit the entry being typed and solve
+    pub fn solve(&mut self) -> Result<&Solution, CalcError> {
+        let augmented = self.editor.finish()?;
+        Ok(self.solution.insert(solve(&augmented)?))
+    }
+
+    /// Go back to editing the coefficients
+    pub fn edit(&mut self) {
+        self.solution = None;
+    }
+}
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn augmented(rows: &[&[f64]]) -> Matrix {
+        Matrix::from_rows(&rows.iter().map(|r| r.to_vec()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn test_solve() {
+        // 2x + y - z = 8, -3x - y + 2z = -11, -2x + y + 2z = -3
+        let system = augmented(&[&[2.0, 1.0, -1.0, 8.0], &[-3.0, -1.0, 2.0, -11.0], &[-2.0, 1.0, 2.0, -3.0]]);
+        let solution = solve(&system).unwrap();
+        for (x, expected) in solution.x.iter().zip([2.0, 3.0, -1.0]) {
+            assert!((x - expected).abs() < 1e-12, "{:?}", solution.x);
+        }
+        assert!(!solution.is_ill_conditioned());
+        assert!(solution.reliable_digits() >= 13);
+
+        // A zero pivot needs a row swap
+        let swapped = solve(&augmented(&[&[0.0, 1.0, 2.0], &[1.0, 0.0, 3.0]])).unwrap();
+        assert_eq!(swapped.x, augmented(&[&[3.0, 2.0]]));
+
+        assert!(solve(&augmented(&[&[1.0, 2.0], &[3.0, 4.0]])).is_err());
+    }
+
+    #[test]
+    fn test_conditioning() {
+        let singular = augmented(&[&[1.0, 2.0, 3.0], &[2.0, 4.0, 6.0]]);
+        assert!(matches!(solve(&singular), Err(CalcError::DomainError("singular: no unique solution"))));
+        let nearly = augmented(&[&[1.0, 1.0, 2.0], &[1.0, 1.0 + 1e-15, 2.0]]);
+        assert!(solve(&nearly).is_err());
+
+        // The 6×6 Hilbert matrix has a condition number near 3e7 in the
+        // 2-norm, 2.9e7 in the 1-norm
+        let mut hilbert = Matrix::zeros(6, 6).unwrap();
+        for r in 0..6 {
+            for c in 0..6 {
+                hilbert.set(r, c, 1.0 / (r + c + 1) as f64);
+            }
+        }
+        let ones = Matrix::from_rows(&[alloc::vec![1.0; 6]]).unwrap();
+        let solution = solve(&hilbert.augment(&ones).unwrap()).unwrap();
+        assert!((solution.condition / 2.907e7 - 1.0).abs() < 1e-3, "{}", solution.condition);
+        assert_eq!(solution.reliable_digits(), 8);
+        assert!(solution.is_ill_conditioned());
+    }
+
+    #[test]
+    fn test_screen() {
+        assert!(LinearSystem::new(1).is_err());
+        assert!(LinearSystem::new(7).is_err());
+        assert_eq!(LinearSystem::new(6).unwrap().editor().matrix().cols(), 7);
+        let mut system = LinearSystem::new(2).unwrap();
+        assert_eq!(system.unknowns(), 2);
+        // x + y = 3, x - y = 1, typed row by row
+        for entry in ["1", "1", "3", "1", "-1", "1"] {
+            entry.chars().for_each(|c| {
+                system.editor_mut().push(c);
+            });
+            system.editor_mut().advance().unwrap();
+        }
+        assert_eq!(system.solve().unwrap().x, augmented(&[&[2.0, 1.0]]));
+        assert!(system.solution().is_some());
+        system.edit();
+        assert!(system.solution().is_none());
+    }
+}
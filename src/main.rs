@@ -10,13 +10,24 @@ extern crate alloc;
 
 mod algebraic;
 mod app;
+mod bitfield;
+mod complex;
+mod constants;
 mod display;
+mod equation;
 mod functions;
 mod keymap;
+mod linsys;
+mod matrix;
 mod memory;
+mod poly;
+mod quantity;
 mod rpn;
 mod storage;
 mod ui;
+mod units;
+mod userfunc;
+mod value;
 
 use app::CalcApp;
 use num_traits::FromPrimitive;
@@ -0,0 +1,684 @@
+//! Matrices and vectors of real numbers
+
+use crate::functions::CalcError;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Most rows or columns a matrix may have
+pub const MAX_SIZE: usize = 6;
+
+/// Cells per row of storage: one more than `MAX_SIZE`, for the right-hand
+/// side of an augmented system
+const STRIDE: usize = MAX_SIZE + 1;
+
+/// A matrix of up to `MAX_SIZE`×`MAX_SIZE` reals; a vector is a single row
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    /// Row-major with a stride of `STRIDE`; unused cells stay zero
+    cells: [f64; MAX_SIZE * STRIDE],
+}
+
+fn at(r: usize, c: usize) -> usize {
+    r * STRIDE + c
+}
+
+impl Matrix {
+    pub fn zeros(rows: usize, cols: usize) -> Result<Self, CalcError> {
+        if rows == 0 || cols == 0 {
+            return Err(CalcError::DomainError("empty matrix"));
+        }
+        if rows > MAX_SIZE || cols > MAX_SIZE {
+            return Err(CalcError::DomainError("matrix larger than 6×6"));
+        }
+        Ok(Self::empty(rows, cols))
+    }
+
+    fn empty(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            cells: [0.0; MAX_SIZE * STRIDE],
+        }
+    }
+
+    /// The augmented matrix `[A | b]` of a system, with the vector `b` as
+    /// an extra column; it may be one column wider than `MAX_SIZE`
+    pub fn augment(&self, b: &Matrix) -> Result<Self, CalcError> {
+        let rhs = b.entries()?;
+        if rhs.len() != self.rows {
+            return Err(CalcError::DimensionError("right side has the wrong length"));
+        }
+        if self.cols >= STRIDE {
+            return Err(CalcError::DomainError("matrix larger than 6×6"));
+        }
+        let mut m = Self::empty(self.rows, self.cols + 1);
+        for (r, &x) in rhs.iter().enumerate() {
+            m.cells[at(r, 0)..at(r, self.cols)].copy_from_slice(self.row(r));
+            m.set(r, self.cols, x);
+        }
+        Ok(m)
+    }
+
+    pub fn identity(n: usize) -> Result<Self, CalcError> {
+        let mut m = Self::zeros(n, n)?;
+        for i in 0..n {
+            m.set(i, i, 1.0);
+        }
+        Ok(m)
+    }
+
+    /// The matrix with these rows, which must all be the same length
+    pub fn from_rows(rows: &[Vec<f64>]) -> Result<Self, CalcError> {
+        let cols = rows.first().map_or(0, |row| row.len());
+        let mut m = Self::zeros(rows.len(), cols)?;
+        for (r, row) in rows.iter().enumerate() {
+            if row.len() != cols {
+                return Err(CalcError::DimensionError("rows differ in length"));
+            }
+            for (c, &x) in row.iter().enumerate() {
+                m.set(r, c, x);
+            }
+        }
+        Ok(m)
+    }
+
+    pub fn to_rows(&self) -> Vec<Vec<f64>> {
+        (0..self.rows).map(|r| self.row(r).to_vec()).collect()
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    /// Is this a single row or a single column?
+    pub fn is_vector(&self) -> bool {
+        self.rows == 1 || self.cols == 1
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> f64 {
+        self.cells[at(r, c)]
+    }
+
+    pub fn set(&mut self, r: usize, c: usize, x: f64) {
+        self.cells[at(r, c)] = x;
+    }
+
+    pub fn row(&self, r: usize) -> &[f64] {
+        &self.cells[at(r, 0)..at(r, self.cols)]
+    }
+
+    /// Every entry, row by row
+    pub fn iter(&self) -> impl Iterator<Item = f64> + '_ {
+        (0..self.rows).flat_map(move |r| self.row(r).iter().copied())
+    }
+
+    /// The same entries in a matrix of another size, cut off or padded
+    /// with zeros
+    pub fn resize(&self, rows: usize, cols: usize) -> Result<Self, CalcError> {
+        let mut m = Self::zeros(rows, cols)?;
+        for r in 0..rows.min(self.rows) {
+            for c in 0..cols.min(self.cols) {
+                m.set(r, c, self.get(r, c));
+            }
+        }
+        Ok(m)
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = Self::empty(self.cols, self.rows);
+        for r in 0..self.rows {
+            for c in 0..self.cols {
+                m.set(c, r, self.get(r, c));
+            }
+        }
+        m
+    }
+
+    pub fn scale(&self, k: f64) -> Result<Self, CalcError> {
+        let mut m = *self;
+        m.cells.iter_mut().for_each(|x| *x *= k);
+        m.finite()
+    }
+
+    pub fn add(&self, other: &Matrix) -> Result<Self, CalcError> {
+        self.zip(other, |a, b| a + b)
+    }
+
+    pub fn sub(&self, other: &Matrix) -> Result<Self, CalcError> {
+        self.zip(other, |a, b| a - b)
+    }
+
+    fn zip(&self, other: &Matrix, f: impl Fn(f64, f64) -> f64) -> Result<Self, CalcError> {
+        if (self.rows, self.cols) != (other.rows, other.cols) {
+            return Err(CalcError::DimensionError("matrix sizes differ"));
+        }
+        let mut m = *self;
+        for (x, &y) in m.cells.iter_mut().zip(other.cells.iter()) {
+            *x = f(*x, y);
+        }
+        m.finite()
+    }
+
+    /// Matrix product. Since vectors are rows, a matrix times a vector of
+    /// matching length takes the vector as a column, giving a row again.
+    pub fn mul(&self, other: &Matrix) -> Result<Self, CalcError> {
+        if self.cols != other.rows {
+            if self.rows > 1 && other.rows == 1 && other.cols == self.cols {
+                return Ok(self.mul(&other.transpose())?.transpose());
+            }
+            return Err(CalcError::DimensionError("matrix sizes differ"));
+        }
+        let mut m = Self::zeros(self.rows, other.cols)?;
+        for r in 0..self.rows {
+            for c in 0..other.cols {
+                let sum = (0..self.cols).map(|k| self.get(r, k) * other.get(k, c)).sum();
+                m.set(r, c, sum);
+            }
+        }
+        m.finite()
+    }
+
+    /// Whole power of a square matrix; negative powers invert it
+    pub fn pow(&self, n: f64) -> Result<Self, CalcError> {
+        if !self.is_square() {
+            return Err(CalcError::DimensionError("needs a square matrix"));
+        }
+        if n.fract() != 0.0 || n.abs() > 64.0 {
+            return Err(CalcError::DomainError("matrix power must be whole"));
+        }
+        let mut square = if n < 0.0 { self.inverse()? } else { *self };
+        let mut result = Self::identity(self.rows)?;
+        let mut n = n.abs() as u32;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result.mul(&square)?;
+            }
+            square = square.mul(&square)?;
+            n >>= 1;
+        }
+        Ok(result)
+    }
+
+    /// Determinant, by elimination with partial pivoting
+    pub fn det(&self) -> Result<f64, CalcError> {
+        if !self.is_square() {
+            return Err(CalcError::DimensionError("needs a square matrix"));
+        }
+        let n = self.rows;
+        let mut a = *self;
+        let mut det = 1.0;
+        for k in 0..n {
+            let p = a.pivot_row(k, k);
+            if a.get(p, k) == 0.0 {
+                return Ok(0.0);
+            }
+            if p != k {
+                a.swap_rows(p, k);
+                det = -det;
+            }
+            let pivot = a.get(k, k);
+            det *= pivot;
+            for r in k + 1..n {
+                let factor = a.get(r, k) / pivot;
+                for c in k..n {
+                    a.set(r, c, a.get(r, c) - factor * a.get(k, c));
+                }
+            }
+        }
+        if det.is_finite() {
+            Ok(det)
+        } else {
+            Err(CalcError::Overflow)
+        }
+    }
+
+    /// Inverse, by Gauss-Jordan elimination; a pivot that is negligible
+    /// next to the largest entry counts as zero
+    pub fn inverse(&self) -> Result<Self, CalcError> {
+        if !self.is_square() {
+            return Err(CalcError::DimensionError("needs a square matrix"));
+        }
+        let n = self.rows;
+        let tolerance = self.tolerance();
+        let mut a = *self;
+        let mut inv = Self::identity(n)?;
+        for k in 0..n {
+            let p = a.pivot_row(k, k);
+            if a.get(p, k).abs() <= tolerance {
+                return Err(CalcError::DomainError("singular matrix"));
+            }
+            a.swap_rows(p, k);
+            inv.swap_rows(p, k);
+            let pivot = a.get(k, k);
+            for c in 0..n {
+                a.set(k, c, a.get(k, c) / pivot);
+                inv.set(k, c, inv.get(k, c) / pivot);
+            }
+            for r in (0..n).filter(|&r| r != k) {
+                let factor = a.get(r, k);
+                for c in 0..n {
+                    a.set(r, c, a.get(r, c) - factor * a.get(k, c));
+                    inv.set(r, c, inv.get(r, c) - factor * inv.get(k, c));
+                }
+            }
+        }
+        inv.finite()
+    }
+
+    /// Reduced row echelon form
+    pub fn rref(&self) -> Self {
+        let tolerance = self.tolerance();
+        let mut a = *self;
+        let mut lead = 0;
+        for c in 0..self.cols {
+            if lead == self.rows {
+                break;
+            }
+            let p = a.pivot_row(lead, c);
+            if a.get(p, c).abs() <= tolerance {
+                (lead..self.rows).for_each(|r| a.set(r, c, 0.0));
+                continue;
+            }
+            a.swap_rows(p, lead);
+            let pivot = a.get(lead, c);
+            for k in c..self.cols {
+                a.set(lead, k, a.get(lead, k) / pivot);
+            }
+            for r in (0..self.rows).filter(|&r| r != lead) {
+                let factor = a.get(r, c);
+                for k in c..self.cols {
+                    a.set(r, k, a.get(r, k) - factor * a.get(lead, k));
+                }
+            }
+            lead += 1;
+        }
+        a
+    }
+
+    /// Solution `x` of `self·x = b`, by elimination with partial pivoting;
+    /// `b` and `x` are vectors
+    pub fn solve(&self, b: &Matrix) -> Result<Self, CalcError> {
+        if !self.is_square() {
+            return Err(CalcError::DimensionError("needs a square matrix"));
+        }
+        let n = self.rows;
+        let tolerance = self.tolerance();
+        let mut a = self.augment(b)?;
+        for k in 0..n {
+            let p = a.pivot_row(k, k);
+            if a.get(p, k).abs() <= tolerance {
+                return Err(CalcError::DomainError("singular matrix"));
+            }
+            a.swap_rows(p, k);
+            let pivot = a.get(k, k);
+            for r in k + 1..n {
+                let factor = a.get(r, k) / pivot;
+                for c in k..=n {
+                    a.set(r, c, a.get(r, c) - factor * a.get(k, c));
+                }
+            }
+        }
+        let mut x = Self::zeros(1, n)?;
+        for k in (0..n).rev() {
+            let sum: f64 = (k + 1..n).map(|c| a.get(k, c) * x.get(0, c)).sum();
+            x.set(0, k, (a.get(k, n) - sum) / a.get(k, k));
+        }
+        x.finite()
+    }
+
+    /// Condition number in the 1-norm, `‖A‖·‖A⁻¹‖`: roughly how many
+    /// times input errors are magnified in a solution. Infinite when
+    /// singular.
+    pub fn condition(&self) -> Result<f64, CalcError> {
+        if !self.is_square() {
+            return Err(CalcError::DimensionError("needs a square matrix"));
+        }
+        Ok(match self.inverse() {
+            Ok(inv) => self.norm1() * inv.norm1(),
+            Err(_) => f64::INFINITY,
+        })
+    }
+
+    /// Largest column sum of magnitudes
+    fn norm1(&self) -> f64 {
+        (0..self.cols)
+            .map(|c| (0..self.rows).map(|r| self.get(r, c).abs()).sum::<f64>())
+            .fold(0.0, f64::max)
+    }
+
+    /// Entries of a vector, in order
+    fn entries(&self) -> Result<Vec<f64>, CalcError> {
+        if self.is_vector() {
+            Ok(self.iter().collect())
+        } else {
+            Err(CalcError::DimensionError("needs a vector"))
+        }
+    }
+
+    pub fn dot(&self, other: &Matrix) -> Result<f64, CalcError> {
+        let (a, b) = (self.entries()?, other.entries()?);
+        if a.len() != b.len() {
+            return Err(CalcError::DimensionError("vector lengths differ"));
+        }
+        Ok(a.iter().zip(b.iter()).map(|(x, y)| x * y).sum())
+    }
+
+    pub fn cross(&self, other: &Matrix) -> Result<Self, CalcError> {
+        let (a, b) = (self.entries()?, other.entries()?);
+        if a.len() != 3 || b.len() != 3 {
+            return Err(CalcError::DimensionError("cross needs 3-vectors"));
+        }
+        let mut m = Self::zeros(1, 3)?;
+        m.set(0, 0, a[1] * b[2] - a[2] * b[1]);
+        m.set(0, 1, a[2] * b[0] - a[0] * b[2]);
+        m.set(0, 2, a[0] * b[1] - a[1] * b[0]);
+        m.finite()
+    }
+
+    /// Euclidean length of a vector, Frobenius norm of a matrix
+    pub fn norm(&self) -> f64 {
+        self.iter().map(|x| x * x).sum::<f64>().sqrt()
+    }
+
+    /// Row at or below `from` with the largest entry in column `c`
+    fn pivot_row(&self, from: usize, c: usize) -> usize {
+        (from..self.rows)
+            .max_by(|&i, &j| self.get(i, c).abs().total_cmp(&self.get(j, c).abs()))
+            .unwrap_or(from)
+    }
+
+    fn swap_rows(&mut self, i: usize, j: usize) {
+        for c in 0..self.cols {
+            self.cells.swap(at(i, c), at(j, c));
+        }
+    }
+
+    /// Size below which elimination treats an entry as zero
+    fn tolerance(&self) -> f64 {
+        let largest = self.iter().fold(0.0, |m: f64, x| m.max(x.abs()));
+        largest * (self.rows.max(self.cols) as f64) * f64::EPSILON * 16.0
+    }
+
+    /// Reject results that overflowed
+    fn finite(self) -> Result<Self, CalcError> {
+        if self.cells.iter().all(|x| x.is_finite()) {
+            Ok(self)
+        } else {
+            Err(CalcError::Overflow)
+        }
+    }
+}
+
+/// A number being typed into a cell: digits, `.`, `e` for an exponent,
+/// and `-`, which flips the sign of the number or of its exponent
+#[derive(Default)]
+pub struct CellEntry(String);
+
+impl CellEntry {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Type a character; false if it cannot go in a number
+    pub fn push(&mut self, c: char) -> bool {
+        let entry = &mut self.0;
+        match c {
+            '0'..='9' => entry.push(c),
+            '.' if !entry.contains(['.', 'e']) => entry.push(c),
+            'e' | 'E' if !entry.contains('e') && entry.contains(|d: char| d.is_ascii_digit()) => entry.push('e'),
+            '-' => match entry.find('e') {
+                Some(i) if entry[i + 1..].starts_with('-') => {
+                    entry.remove(i + 1);
+                }
+                Some(i) => entry.insert(i + 1, '-'),
+                None if entry.starts_with('-') => {
+                    entry.remove(0);
+                }
+                None => entry.insert(0, '-'),
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn backspace(&mut self) {
+        self.0.pop();
+    }
+
+    /// The number typed, if any, leaving the entry empty
+    pub fn take(&mut self) -> Result<Option<f64>, CalcError> {
+        if self.0.is_empty() {
+            return Ok(None);
+        }
+        let x = self
+            .0
+            .parse::<f64>()
+            .map_err(|_| CalcError::ParseError(alloc::format!("Invalid number: {}", self.0)))?;
+        self.0.clear();
+        Ok(Some(x))
+    }
+}
+
+/// A matrix open in the grid editor, with a cursor and the entry being
+/// typed into the cell under it
+pub struct MatrixEditor {
+    matrix: Matrix,
+    row: usize,
+    col: usize,
+    entry: CellEntry,
+}
+
+impl MatrixEditor {
+    pub fn new(matrix: Matrix) -> Self {
+        Self {
+            matrix,
+            row: 0,
+            col: 0,
+            entry: CellEntry::default(),
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    /// Cell under the cursor, as (row, column)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    pub fn entry(&self) -> &str {
+        self.entry.as_str()
+    }
+
+    /// Type a character of a new entry; false if it cannot go in a number
+    pub fn push(&mut self, c: char) -> bool {
+        self.entry.push(c)
+    }
+
+    pub fn backspace(&mut self) {
+        self.entry.backspace();
+    }
+
+    /// Write the entry, if any, into the cell under the cursor
+    pub fn commit(&mut self) -> Result<(), CalcError> {
+        if let Some(x) = self.entry.take()? {
+            self.matrix.set(self.row, self.col, x);
+        }
+        Ok(())
+    }
+
+    /// Commit the entry and step to the next cell, row by row, wrapping
+    /// back to the first
+    pub fn advance(&mut self) -> Result<(), CalcError> {
+        self.commit()?;
+        self.col += 1;
+        if self.col == self.matrix.cols {
+            self.col = 0;
+            self.row = (self.row + 1) % self.matrix.rows;
+        }
+        Ok(())
+    }
+
+    /// Commit the entry and move the cursor, stopping at the edges
+    pub fn move_cursor(&mut self, rows: isize, cols: isize) -> Result<(), CalcError> {
+        self.commit()?;
+        self.row = self.row.saturating_add_signed(rows).min(self.matrix.rows - 1);
+        self.col = self.col.saturating_add_signed(cols).min(self.matrix.cols - 1);
+        Ok(())
+    }
+
+    /// Commit the entry and add or drop rows and columns at the ends
+    pub fn resize(&mut self, rows: isize, cols: isize) -> Result<(), CalcError> {
+        self.commit()?;
+        let new_rows = self.matrix.rows.saturating_add_signed(rows);
+        let new_cols = self.matrix.cols.saturating_add_signed(cols);
+        self.matrix = self.matrix.resize(new_rows, new_cols)?;
+        self.row = self.row.min(new_rows - 1);
+        self.col = self.col.min(new_cols - 1);
+        Ok(())
+    }
+
+    /// The matrix with the entry committed
+    pub fn finish(&mut self) -> Result<Matrix, CalcError> {
+        self.commit()?;
+        Ok(self.matrix)
+    }
+}
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn m(rows: &[&[f64]]) -> Matrix {
+        Matrix::from_rows(&rows.iter().map(|r| r.to_vec()).collect::<Vec<_>>()).unwrap()
+    }
+
+    fn close(a: &Matrix, b: &Matrix) -> bool {
+        a.sub(b).unwrap().norm() < 1e-12
+    }
+
+    #[test]
+    fn test_shape() {
+        let a = m(&[&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]]);
+        assert_eq!((a.rows(), a.cols()), (2, 3));
+        assert_eq!(a.transpose().get(2, 1), 6.0);
+        assert_eq!(a.to_rows(), vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+        assert_eq!(a.resize(3, 2).unwrap(), m(&[&[1.0, 2.0], &[4.0, 5.0], &[0.0, 0.0]]));
+        assert!(Matrix::zeros(7, 1).is_err());
+        assert!(Matrix::from_rows(&[vec![1.0], vec![1.0, 2.0]]).is_err());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = m(&[&[1.0, 2.0], &[3.0, 4.0]]);
+        let b = m(&[&[5.0, 6.0], &[7.0, 8.0]]);
+        assert_eq!(a.mul(&b).unwrap(), m(&[&[19.0, 22.0], &[43.0, 50.0]]));
+        assert_eq!(a.add(&b).unwrap(), m(&[&[6.0, 8.0], &[10.0, 12.0]]));
+        assert!(a.add(&m(&[&[1.0, 2.0]])).is_err());
+
+        // A vector on the right is a column
+        assert_eq!(a.mul(&m(&[&[1.0, 1.0]])).unwrap(), m(&[&[3.0, 7.0]]));
+        assert_eq!(m(&[&[1.0, 1.0]]).mul(&a).unwrap(), m(&[&[4.0, 6.0]]));
+        assert!(m(&[&[1.0, 1.0]]).mul(&m(&[&[1.0, 1.0]])).is_err());
+
+        assert_eq!(a.pow(2.0).unwrap(), a.mul(&a).unwrap());
+        assert_eq!(a.pow(0.0).unwrap(), Matrix::identity(2).unwrap());
+        assert!(close(&a.pow(-1.0).unwrap().mul(&a).unwrap(), &Matrix::identity(2).unwrap()));
+        assert!(a.pow(0.5).is_err());
+    }
+
+    #[test]
+    fn test_det_inverse() {
+        let a = m(&[&[2.0, 1.0, 1.0], &[1.0, 3.0, 2.0], &[1.0, 0.0, 0.0]]);
+        assert!((a.det().unwrap() - -1.0).abs() < 1e-12);
+        let inv = a.inverse().unwrap();
+        assert!(close(&a.mul(&inv).unwrap(), &Matrix::identity(3).unwrap()));
+
+        let singular = m(&[&[1.0, 2.0], &[2.0, 4.0]]);
+        assert_eq!(singular.det().unwrap(), 0.0);
+        assert!(matches!(singular.inverse(), Err(CalcError::DomainError("singular matrix"))));
+        assert!(m(&[&[1.0, 2.0]]).det().is_err());
+
+        // Scale doesn't fool the singularity test
+        assert!(singular.scale(1e-20).unwrap().inverse().is_err());
+        assert!(Matrix::identity(3).unwrap().scale(1e-20).unwrap().inverse().is_ok());
+    }
+
+    #[test]
+    fn test_rref() {
+        let a = m(&[&[1.0, 2.0, -1.0, -4.0], &[2.0, 3.0, -1.0, -11.0], &[-2.0, 0.0, -3.0, 22.0]]);
+        let expected = m(&[&[1.0, 0.0, 0.0, -8.0], &[0.0, 1.0, 0.0, 1.0], &[0.0, 0.0, 1.0, -2.0]]);
+        assert!(close(&a.rref(), &expected));
+
+        let rank_one = m(&[&[1.0, 2.0], &[2.0, 4.0]]);
+        assert_eq!(rank_one.rref(), m(&[&[1.0, 2.0], &[0.0, 0.0]]));
+    }
+
+    #[test]
+    fn test_solve() {
+        let a = m(&[&[4.0, -2.0], &[1.0, 1.0]]);
+        assert!(close(&a.solve(&m(&[&[2.0, 3.0]])).unwrap(), &m(&[&[4.0 / 3.0, 5.0 / 3.0]])));
+        assert!(a.solve(&m(&[&[1.0, 2.0, 3.0]])).is_err());
+        assert!((a.condition().unwrap() - 5.0).abs() < 1e-12);
+        assert_eq!(m(&[&[1.0, 2.0], &[2.0, 4.0]]).condition().unwrap(), f64::INFINITY);
+
+        let wide = Matrix::identity(6).unwrap().augment(&m(&[&[1.0; 6]])).unwrap();
+        assert_eq!((wide.rows(), wide.cols()), (6, 7));
+        assert_eq!(wide.get(5, 6), 1.0);
+        assert!(wide.augment(&m(&[&[1.0; 6]])).is_err());
+    }
+
+    #[test]
+    fn test_editor() {
+        let mut editor = MatrixEditor::new(Matrix::zeros(2, 2).unwrap());
+        for c in "1.5".chars() {
+            editor.push(c);
+        }
+        editor.push('-');
+        editor.advance().unwrap();
+        "2e3".chars().for_each(|c| assert!(editor.push(c)));
+        editor.push('-');
+        assert_eq!(editor.entry(), "2e-3");
+        assert!(!editor.push('x'));
+        editor.move_cursor(1, 0).unwrap();
+        assert_eq!(editor.cursor(), (1, 1));
+        editor.move_cursor(5, -5).unwrap();
+        assert_eq!(editor.cursor(), (1, 0));
+        editor.advance().unwrap();
+        editor.advance().unwrap();
+        assert_eq!(editor.cursor(), (0, 0));
+
+        editor.resize(1, 0).unwrap();
+        editor.push('9');
+        assert_eq!(editor.finish().unwrap(), m(&[&[9.0, 0.002], &[0.0, 0.0], &[0.0, 0.0]]));
+        editor.resize(-2, -1).unwrap();
+        assert_eq!(*editor.matrix(), m(&[&[9.0]]));
+        assert!(editor.resize(-1, 0).is_err());
+    }
+
+    #[test]
+    fn test_vectors() {
+        let u = m(&[&[1.0, 0.0, 0.0]]);
+        let v = m(&[&[0.0, 1.0, 0.0]]);
+        assert_eq!(u.cross(&v).unwrap(), m(&[&[0.0, 0.0, 1.0]]));
+        assert_eq!(u.dot(&v).unwrap(), 0.0);
+        assert_eq!(m(&[&[1.0, 2.0, 3.0]]).dot(&m(&[&[4.0], &[5.0], &[6.0]])).unwrap(), 32.0);
+        assert_eq!(m(&[&[3.0, 4.0]]).norm(), 5.0);
+        assert!(u.dot(&m(&[&[1.0, 2.0]])).is_err());
+        assert!(m(&[&[1.0, 2.0]]).cross(&m(&[&[3.0, 4.0]])).is_err());
+    }
+}
@@ -1,8 +1,16 @@
 //! Memory registers and storage
 
-/// Memory registers (10 like TI-85)
+use crate::complex::Complex;
+use crate::functions::{CalcError, Func, Op};
+use crate::quantity::Quantity;
+use crate::value::Value;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+/// Memory registers (10 like TI-85), holding plain numbers that may be
+/// complex, or matrices
 pub struct Memory {
-    registers: [f64; 10],
+    registers: [Value; 10],
 }
 
 impl Default for Memory {
@@ -14,14 +22,14 @@ impl Default for Memory {
 impl Memory {
     pub fn new() -> Self {
         Self {
-            registers: [0.0; 10],
+            registers: [Value::default(); 10],
         }
     }
 
     /// Store value in register (0-9)
-    pub fn store(&mut self, register: usize, value: f64) -> bool {
+    pub fn store(&mut self, register: usize, value: impl Into<Value>) -> bool {
         if register < 10 {
-            self.registers[register] = value;
+            self.registers[register] = value.into();
             true
         } else {
             false
@@ -29,7 +37,7 @@ impl Memory {
     }
 
     /// Recall value from register (0-9)
-    pub fn recall(&self, register: usize) -> Option<f64> {
+    pub fn recall(&self, register: usize) -> Option<Value> {
         if register < 10 {
             Some(self.registers[register])
         } else {
@@ -37,30 +45,32 @@ impl Memory {
         }
     }
 
-    /// Add to register (M+)
-    pub fn add(&mut self, register: usize, value: f64) -> bool {
-        if register < 10 {
-            self.registers[register] += value;
-            true
-        } else {
-            false
+    /// Add to register (M+); fails on a matrix
+    pub fn add(&mut self, register: usize, value: impl Into<Complex>) -> bool {
+        match self.registers.get_mut(register) {
+            Some(Value::Number(q)) => {
+                *q = Quantity::from(q.complex() + value.into());
+                true
+            }
+            _ => false,
         }
     }
 
-    /// Subtract from register (M-)
-    pub fn subtract(&mut self, register: usize, value: f64) -> bool {
-        if register < 10 {
-            self.registers[register] -= value;
-            true
-        } else {
-            false
+    /// Subtract from register (M-); fails on a matrix
+    pub fn subtract(&mut self, register: usize, value: impl Into<Complex>) -> bool {
+        match self.registers.get_mut(register) {
+            Some(Value::Number(q)) => {
+                *q = Quantity::from(q.complex() - value.into());
+                true
+            }
+            _ => false,
         }
     }
 
     /// Clear a register
     pub fn clear(&mut self, register: usize) -> bool {
         if register < 10 {
-            self.registers[register] = 0.0;
+            self.registers[register] = Value::default();
             true
         } else {
             false
@@ -69,27 +79,27 @@ impl Memory {
 
     /// Clear all registers
     pub fn clear_all(&mut self) {
-        self.registers = [0.0; 10];
+        self.registers = [Value::default(); 10];
     }
 
     /// Check if any register is non-zero (for indicator)
     pub fn has_stored_value(&self) -> bool {
-        self.registers.iter().any(|&v| v != 0.0)
+        self.registers.iter().any(|v| !v.is_zero())
     }
 
     /// Get all registers
-    pub fn get_all(&self) -> &[f64; 10] {
+    pub fn get_all(&self) -> &[Value; 10] {
         &self.registers
     }
 
     /// Set all registers (for loading from storage)
-    pub fn set_all(&mut self, values: [f64; 10]) {
+    pub fn set_all(&mut self, values: [Value; 10]) {
         self.registers = values;
     }
 
     /// Get register labels with values for display
-    pub fn get_display_list(&self) -> [(usize, f64); 10] {
-        let mut result = [(0usize, 0.0f64); 10];
+    pub fn get_display_list(&self) -> [(usize, Value); 10] {
+        let mut result = [(0usize, Value::default()); 10];
         for (i, &v) in self.registers.iter().enumerate() {
             result[i] = (i, v);
         }
@@ -97,16 +107,94 @@ impl Memory {
     }
 }
 
+/// Named user variables, shared by algebraic and RPN modes
+///
+/// Names are case-insensitive and stored lowercase, since the tokenizer
+/// resolves function names the same way.
+#[derive(Default)]
+pub struct Variables {
+    values: BTreeMap<String, Value>,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Can this name be assigned? Must be an identifier that does not
+    /// shadow `ans`, a function, a word operator or a built-in constant.
+    pub fn is_valid_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        let starts_alpha = chars.next().is_some_and(|c| c.is_ascii_alphabetic());
+        starts_alpha
+            && chars.all(|c| c.is_ascii_alphanumeric())
+            && !name.eq_ignore_ascii_case("ans")
+            && Func::from_name(name).is_none()
+            && Op::from_name(name).is_none()
+    }
+
+    /// Store a value, which may be a quantity or a matrix, under a name
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) -> Result<(), CalcError> {
+        if !Self::is_valid_name(name) {
+            return Err(CalcError::SyntaxError(alloc::format!(
+                "Invalid variable name: {}",
+                name
+            )));
+        }
+        self.values.insert(name.to_lowercase(), value.into());
+        Ok(())
+    }
+
+    /// Look up a variable
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.values.get(&name.to_lowercase()).copied()
+    }
+
+    /// Delete a variable
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.values.remove(&name.to_lowercase()).is_some()
+    }
+
+    /// Variables in name order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Value)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+
+    /// Get the whole table (for saving to storage)
+    pub fn get_all(&self) -> &BTreeMap<String, Value> {
+        &self.values
+    }
+
+    /// Replace the whole table (for loading from storage)
+    pub fn set_all(&mut self, values: BTreeMap<String, Value>) {
+        self.values = values
+            .into_iter()
+            .filter(|(name, _)| Self::is_valid_name(name))
+            .map(|(name, value)| (name.to_lowercase(), value))
+            .collect();
+    }
+}
+
+extern crate alloc;
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::matrix::Matrix;
 
     #[test]
     fn test_store_recall() {
         let mut mem = Memory::new();
         mem.store(0, 42.0);
-        assert_eq!(mem.recall(0), Some(42.0));
-        assert_eq!(mem.recall(1), Some(0.0));
+        assert_eq!(mem.recall(0), Some(42.0.into()));
+        assert_eq!(mem.recall(1), Some(Value::default()));
+        mem.store(2, Complex::new(3.0, -4.0));
+        assert_eq!(mem.recall(2), Some(Complex::new(3.0, -4.0).into()));
+
+        let m = Matrix::identity(2).unwrap();
+        mem.store(3, m);
+        assert_eq!(mem.recall(3), Some(Value::Matrix(m)));
+        assert!(!mem.add(3, 1.0));
     }
 
     #[test]
@@ -114,9 +202,11 @@ mod tests {
         let mut mem = Memory::new();
         mem.store(0, 10.0);
         mem.add(0, 5.0);
-        assert_eq!(mem.recall(0), Some(15.0));
+        assert_eq!(mem.recall(0), Some(15.0.into()));
         mem.subtract(0, 3.0);
-        assert_eq!(mem.recall(0), Some(12.0));
+        assert_eq!(mem.recall(0), Some(12.0.into()));
+        mem.add(0, Complex::I);
+        assert_eq!(mem.recall(0), Some(Complex::new(12.0, 1.0).into()));
     }
 
     #[test]
@@ -126,4 +216,23 @@ mod tests {
         mem.store(5, 1.0);
         assert!(mem.has_stored_value());
     }
+
+    #[test]
+    fn test_variables() {
+        let mut vars = Variables::new();
+        vars.set("r", 2.5).unwrap();
+        vars.set("Rate", 0.05).unwrap();
+        assert_eq!(vars.get("r"), Some(2.5.into()));
+        assert_eq!(vars.get("RATE"), Some(0.05.into()));
+        assert_eq!(vars.get("x"), None);
+
+        assert!(vars.set("sin", 1.0).is_err());
+        assert!(vars.set("pi", 1.0).is_err());
+        assert!(vars.set("i", 1.0).is_err());
+        assert!(vars.set("ans", 1.0).is_err());
+        assert!(vars.set("2x", 1.0).is_err());
+
+        assert!(vars.remove("r"));
+        assert_eq!(vars.get("r"), None);
+    }
 }
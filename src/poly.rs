@@ -0,0 +1,471 @@
+//! Polynomial roots: closed forms up to cubics, Durand-Kerner beyond
+
+use crate::complex::Complex;
+use crate::functions::CalcError;
+use crate::matrix::CellEntry;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+
+/// Highest degree the root finder takes
+pub const MAX_DEGREE: usize = 10;
+
+/// Sweeps Durand-Kerner may take before settling for what it has
+const MAX_ITERATIONS: usize = 500;
+
+/// Part of a root, relative to its size, below which it is rounding noise:
+/// the root is taken as real, or as purely imaginary
+const ON_AXIS: f64 = 1e-9;
+
+/// Distance, relative to their size, within which iterated roots are
+/// tried as one multiple root
+const CLUSTER: f64 = 1e-2;
+
+/// Every root of the polynomial with coefficients `coeffs`, highest power
+/// first, repeated by multiplicity: the real roots in ascending order, then
+/// the complex ones by real part
+pub fn roots(coeffs: &[f64]) -> Result<Vec<Complex>, CalcError> {
+    if coeffs.iter().any(|c| !c.is_finite()) {
+        return Err(CalcError::Overflow);
+    }
+    let Some(first) = coeffs.iter().position(|&c| c != 0.0) else {
+        return Err(CalcError::DomainError("poly: every coefficient is zero"));
+    };
+    let coeffs = &coeffs[first..];
+    if coeffs.len() > MAX_DEGREE + 1 {
+        return Err(CalcError::DomainError("poly: degree 1 to 10"));
+    }
+    if coeffs.len() == 1 {
+        return Err(CalcError::DomainError("poly: a constant has no roots"));
+    }
+
+    // Scale by a power of two, which is exact, so that the largest
+    // coefficient is near 1 and the formulas below cannot overflow
+    let largest = coeffs.iter().fold(0.0, |m: f64, c| m.max(c.abs()));
+    let scale = f64::from_bits(largest.to_bits() & 0xFFF0_0000_0000_0000).max(f64::MIN_POSITIVE);
+    let scaled: Vec<f64> = coeffs.iter().map(|c| c / scale).collect();
+    let mut coeffs = &scaled[..];
+
+    // Roots at zero factor out exactly
+    let mut roots = Vec::new();
+    while let [rest @ .., 0.0] = coeffs {
+        roots.push(Complex::ZERO);
+        coeffs = rest;
+    }
+    match *coeffs {
+        [_] => {}
+        [a, b] => roots.push(Complex::from(-b / a)),
+        [a, b, c] => roots.extend(quadratic(a, b, c)),
+        [a, b, c, d] => roots.extend(cubic(a, b, c, d)),
+        _ => {
+            let found = durand_kerner(coeffs).into_iter().map(|z| polish(coeffs, z)).collect();
+            roots.extend(settle(coeffs, found));
+        }
+    }
+    for z in roots.iter_mut() {
+        if !z.is_finite() {
+            return Err(CalcError::Overflow);
+        }
+        if z.im.abs() <= ON_AXIS * z.abs() {
+            z.im = 0.0;
+        } else if z.re.abs() <= ON_AXIS * z.abs() {
+            z.re = 0.0;
+        }
+    }
+    roots.sort_by(|a, b| {
+        (a.im != 0.0)
+            .cmp(&(b.im != 0.0))
+            .then(a.re.total_cmp(&b.re))
+            .then(a.im.total_cmp(&b.im))
+    });
+    Ok(roots)
+}
+
+/// Roots of `ax² + bx + c`, avoiding the cancellation in the textbook
+/// formula when `b²` dwarfs `4ac`
+fn quadratic(a: f64, b: f64, c: f64) -> [Complex; 2] {
+    let disc = b * b - 4.0 * a * c;
+    if disc >= 0.0 {
+        let q = -0.5 * (b + disc.sqrt().copysign(b));
+        if q == 0.0 {
+            return [Complex::ZERO; 2];
+        }
+        [Complex::from(q / a), Complex::from(c / q)]
+    } else {
+        let re = -b / (2.0 * a);
+        let im = ((-disc).sqrt() / (2.0 * a)).abs();
+        [Complex::new(re, -im), Complex::new(re, im)]
+    }
+}
+
+/// Roots of `ax³ + bx² + cx + d`, by Cardano's formula when there is one
+/// real root and the trigonometric form when there are three
+fn cubic(a: f64, b: f64, c: f64, d: f64) -> [Complex; 3] {
+    let (b, c, d) = (b / a, c / a, d / a);
+    // x = t - b/3 gives t³ + pt + q
+    let shift = b / 3.0;
+    let p = c - b * shift;
+    let q = d - c * shift + 2.0 * shift * shift * shift;
+    let disc = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+    if disc > 0.0 || p == 0.0 {
+        // Take the cube root of the larger of the two terms, and the other
+        // from it, so that nothing cancels
+        let u = (-q / 2.0 - disc.max(0.0).sqrt().copysign(q)).cbrt();
+        let v = if u == 0.0 { 0.0 } else { -p / (3.0 * u) };
+        let re = -(u + v) / 2.0 - shift;
+        let im = ((u - v) * 3f64.sqrt() / 2.0).abs();
+        [Complex::from(u + v - shift), Complex::new(re, -im), Complex::new(re, im)]
+    } else {
+        let r = 2.0 * (-p / 3.0).sqrt();
+        let cos3 = (3.0 * q / (p * r)).clamp(-1.0, 1.0);
+        let phi = cos3.acos() / 3.0;
+        [0.0, 1.0, 2.0].map(|k| Complex::from(r * (phi - 2.0 * PI * k / 3.0).cos() - shift))
+    }
+}
+
+/// Value of the polynomial at `z`, by Horner's rule
+fn evaluate(coeffs: &[f64], z: Complex) -> Complex {
+    coeffs.iter().fold(Complex::ZERO, |acc, &c| acc * z + Complex::from(c))
+}
+
+/// Every root at once, each refined against all the others until none
+/// moves
+fn durand_kerner(coeffs: &[f64]) -> Vec<Complex> {
+    let n = coeffs.len() - 1;
+    let monic: Vec<f64> = coeffs.iter().map(|&c| c / coeffs[0]).collect();
+    // Start on a circle about the size of the roots, turned off the real
+    // axis so that no two guesses are conjugates
+    let radius = (1..=n)
+        .map(|k| monic[k].abs().powf(1.0 / k as f64))
+        .fold(0.0, f64::max)
+        .max(f64::MIN_POSITIVE);
+    let mut z: Vec<Complex> = (0..n)
+        .map(|k| Complex::from_polar(radius, 2.0 * PI * k as f64 / n as f64 + 0.4))
+        .collect();
+    for _ in 0..MAX_ITERATIONS {
+        let mut moved = 0.0f64;
+        for i in 0..n {
+            let spread = (0..n)
+                .filter(|&j| j != i)
+                .fold(Complex::ONE, |acc, j| acc * (z[i] - z[j]));
+            if let Ok(step) = evaluate(&monic, z[i]).checked_div(spread) {
+                z[i] = z[i] - step;
+                moved = moved.max(step.abs() / z[i].abs().max(1.0));
+            }
+        }
+        if moved <= f64::EPSILON {
+            break;
+        }
+    }
+    z
+}
+
+/// A few Newton steps on a root, kept only while they shrink the residual
+fn polish(coeffs: &[f64], mut z: Complex) -> Complex {
+    let derivative = derivative(coeffs);
+    let mut residual = evaluate(coeffs, z).abs();
+    for _ in 0..3 {
+        let Ok(step) = evaluate(coeffs, z).checked_div(evaluate(&derivative, z)) else {
+            break;
+        };
+        let next = z - step;
+        let next_residual = evaluate(coeffs, next).abs();
+        if next_residual >= residual {
+            break;
+        }
+        (z, residual) = (next, next_residual);
+    }
+    z
+}
+
+/// Coefficients of the derivative, highest power first
+fn derivative(coeffs: &[f64]) -> Vec<f64> {
+    let n = coeffs.len() - 1;
+    coeffs[..n].iter().enumerate().map(|(i, &c)| c * (n - i) as f64).collect()
+}
+
+/// Tidy roots found by iteration. A cluster of `m` roots is tried as one
+/// root of multiplicity `m`, which is a simple root of the `m-1`th
+/// derivative, and kept if that is a root to within rounding; then each
+/// complex root is paired with its conjugate, and one left without a
+/// partner is real.
+fn settle(coeffs: &[f64], mut z: Vec<Complex>) -> Vec<Complex> {
+    let n = z.len();
+    let mut merged = alloc::vec![false; n];
+    for i in 0..n {
+        if merged[i] {
+            continue;
+        }
+        let near: Vec<usize> = (i..n)
+            .filter(|&j| !merged[j] && (z[j] - z[i]).abs() <= CLUSTER * z[i].abs().max(1.0))
+            .collect();
+        if near.len() < 2 {
+            continue;
+        }
+        let mean = near.iter().fold(Complex::ZERO, |acc, &j| acc + z[j]).scale(1.0 / near.len() as f64);
+        let derived = (1..near.len()).fold(coeffs.to_vec(), |d, _| derivative(&d));
+        let centre = polish(&derived, mean);
+        if evaluate(coeffs, centre).abs() <= rounding(coeffs, centre) {
+            for &j in &near {
+                z[j] = centre;
+                merged[j] = true;
+            }
+        }
+    }
+
+    // A partner must be nearer the conjugate than the conjugate is to the
+    // root, so that two real roots a little off the axis stay apart
+    let mut paired = alloc::vec![false; n];
+    for i in 0..n {
+        if paired[i] || z[i].im <= 0.0 {
+            continue;
+        }
+        let target = z[i].conj();
+        let partner = (0..n)
+            .filter(|&j| !paired[j] && z[j].im < 0.0 && (z[j] - target).abs() < z[i].im)
+            .min_by(|&a, &b| (z[a] - target).abs().total_cmp(&(z[b] - target).abs()));
+        if let Some(j) = partner {
+            let mid = (z[i] + z[j].conj()).scale(0.5);
+            (z[i], z[j]) = (mid, mid.conj());
+            (paired[i], paired[j]) = (true, true);
+        }
+    }
+    for (z, &paired) in z.iter_mut().zip(&paired) {
+        if !paired {
+            z.im = 0.0;
+        }
+    }
+    z
+}
+
+/// Bound on the rounding error of `evaluate(coeffs, z)`
+fn rounding(coeffs: &[f64], z: Complex) -> f64 {
+    let r = z.abs();
+    let size = coeffs.iter().fold(0.0, |acc, &c| acc * r + c.abs());
+    4.0 * coeffs.len() as f64 * f64::EPSILON * size
+}
+
+/// The POLY screen: coefficients being filled in, then the roots to browse
+pub struct PolySolver {
+    /// Highest power first
+    coeffs: Vec<f64>,
+    /// Coefficient under the cursor
+    index: usize,
+    entry: CellEntry,
+    roots: Option<Vec<Complex>>,
+    /// Root picked for recall
+    selected: usize,
+}
+
+impl PolySolver {
+    /// A polynomial of `degree` with every coefficient zero
+    pub fn new(degree: usize) -> Result<Self, CalcError> {
+        if !(1..=MAX_DEGREE).contains(&degree) {
+            return Err(CalcError::DomainError("poly: degree 1 to 10"));
+        }
+        Ok(Self {
+            coeffs: alloc::vec![0.0; degree + 1],
+            index: 0,
+            entry: CellEntry::default(),
+            roots: None,
+            selected: 0,
+        })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coeffs.len() - 1
+    }
+
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coeffs
+    }
+
+    /// Index of the coefficient under the cursor, from the highest power
+    pub fn cursor(&self) -> usize {
+        self.index
+    }
+
+    pub fn entry(&self) -> &str {
+        self.entry.as_str()
+    }
+
+    /// Type a character of a new coefficient; false if it cannot go in a
+    /// number
+    pub fn push(&mut self, c: char) -> bool {
+        self.entry.push(c)
+    }
+
+    pub fn backspace(&mut self) {
+        self.entry.backspace();
+    }
+
+    /// Write the entry, if any, into the coefficient under the cursor
+    pub fn commit(&mut self) -> Result<(), CalcError> {
+        if let Some(x) = self.entry.take()? {
+            self.coeffs[self.index] = x;
+        }
+        Ok(())
+    }
+
+    /// Commit the entry and move the cursor, stopping at the ends
+    pub fn move_cursor(&mut self, delta: isize) -> Result<(), CalcError> {
+        self.commit()?;
+        self.index = self.index.saturating_add_signed(delta).min(self.degree());
+        Ok(())
+    }
+
+    /// Commit the entry and step to the next coefficient, wrapping back to
+    /// the first
+    pub fn advance(&mut self) -> Result<(), CalcError> {
+        self.commit()?;
+        self.index = (self.index + 1) % self.coeffs.len();
+        Ok(())
+    }
+
+    /// Commit the entry and find the roots
+    pub fn solve(&mut self) -> Result<&[Complex], CalcError> {
+        self.commit()?;
+        self.selected = 0;
+        Ok(self.roots.insert(roots(&self.coeffs)?))
+    }
+
+    pub fn roots(&self) -> Option<&[Complex]> {
+        self.roots.as_deref()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Move the selection through the roots, stopping at the ends
+    pub fn select(&mut self, delta: isize) {
+        let last = self.roots.as_ref().map_or(0, |r| r.len().saturating_sub(1));
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+
+    pub fn selected_root(&self) -> Option<Complex> {
+        self.roots.as_ref()?.get(self.selected).copied()
+    }
+
+    /// Go back to editing the coefficients
+    pub fn edit(&mut self) {
+        self.roots = None;
+    }
+}
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(z: Complex, re: f64, im: f64, tolerance: f64) -> bool {
+        (z.re - re).abs() <= tolerance && (z.im - im).abs() <= tolerance
+    }
+
+    fn check(coeffs: &[f64], expected: &[(f64, f64)], tolerance: f64) {
+        let found = roots(coeffs).unwrap();
+        assert_eq!(found.len(), expected.len(), "{:?}: {:?}", coeffs, found);
+        for (z, &(re, im)) in found.iter().zip(expected) {
+            assert!(close(*z, re, im, tolerance), "{:?}: {:?}", coeffs, found);
+        }
+    }
+
+    #[test]
+    fn test_closed_forms() {
+        check(&[2.0, -4.0], &[(2.0, 0.0)], 0.0);
+        check(&[1.0, -3.0, 2.0], &[(1.0, 0.0), (2.0, 0.0)], 1e-15);
+        check(&[1.0, 0.0, 1.0], &[(0.0, -1.0), (0.0, 1.0)], 0.0);
+        check(&[1.0, 2.0, 5.0], &[(-1.0, -2.0), (-1.0, 2.0)], 1e-15);
+        // The small root survives a huge middle coefficient
+        let tiny = roots(&[1.0, -1e8, 1.0]).unwrap()[0].re;
+        assert!((tiny - 1e-8).abs() < 1e-22, "{}", tiny);
+
+        check(&[1.0, -6.0, 11.0, -6.0], &[(1.0, 0.0), (2.0, 0.0), (3.0, 0.0)], 1e-12);
+        let half = 3f64.sqrt() / 2.0;
+        check(&[1.0, 0.0, 0.0, -1.0], &[(1.0, 0.0), (-0.5, -half), (-0.5, half)], 1e-15);
+        check(&[1.0, -3.0, 3.0, -1.0], &[(1.0, 0.0); 3], 1e-15);
+        check(&[2.0, 0.0, -2.0, 0.0], &[(-1.0, 0.0), (0.0, 0.0), (1.0, 0.0)], 1e-15);
+        check(&[0.0, 0.0, 1.0, 0.0, 0.0], &[(0.0, 0.0), (0.0, 0.0)], 0.0);
+    }
+
+    #[test]
+    fn test_durand_kerner() {
+        check(&[1.0, 0.0, 0.0, 0.0, -1.0], &[(-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0)], 1e-12);
+        // (x-1)(x-2)...(x-10)
+        let mut wilkinson = alloc::vec![1.0];
+        for k in 1..=10 {
+            let mut next = wilkinson.clone();
+            next.push(0.0);
+            for (i, c) in wilkinson.iter().enumerate() {
+                next[i + 1] -= c * k as f64;
+            }
+            wilkinson = next;
+        }
+        let expected: Vec<(f64, f64)> = (1..=10).map(|k| (k as f64, 0.0)).collect();
+        check(&wilkinson, &expected, 1e-6);
+        // x⁵ - x - 1, whose real root is 1.1673039782614187
+        let found = roots(&[1.0, 0.0, 0.0, 0.0, -1.0, -1.0]).unwrap();
+        assert!((found[0].re - 1.167_303_978_261_418_7).abs() < 1e-13 && found[0].im == 0.0);
+        assert!(found[1..].iter().all(|z| z.im != 0.0 && evaluate(&[1.0, 0.0, 0.0, 0.0, -1.0, -1.0], *z).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_multiple_roots() {
+        // (x-1)⁴ and (x+2)²(x-3)³ come back real, each root repeated
+        check(&[1.0, -4.0, 6.0, -4.0, 1.0], &[(1.0, 0.0); 4], 1e-12);
+        check(
+            &[1.0, -5.0, -5.0, 45.0, 0.0, -108.0],
+            &[(-2.0, 0.0), (-2.0, 0.0), (3.0, 0.0), (3.0, 0.0), (3.0, 0.0)],
+            1e-10,
+        );
+        // (x²+1)²: each of ±i twice, as exact conjugates
+        let found = roots(&[1.0, 0.0, 2.0, 0.0, 1.0]).unwrap();
+        assert!(found.iter().all(|z| close(*z, 0.0, z.im.signum(), 1e-12)), "{:?}", found);
+        assert!(found[0] == found[1] && found[2] == found[3] && found[0] == found[2].conj(), "{:?}", found);
+        // (x-1)(x-1.0001)(x²+1) keeps its two close real roots apart
+        check(
+            &[1.0, -2.0001, 2.0001, -2.0001, 1.0001],
+            &[(1.0, 0.0), (1.0001, 0.0), (0.0, -1.0), (0.0, 1.0)],
+            1e-9,
+        );
+    }
+
+    #[test]
+    fn test_scaling() {
+        let half = 3f64.sqrt() / 2.0;
+        check(&[1e300, 1e300, 1e300], &[(-0.5, -half), (-0.5, half)], 1e-15);
+        check(&[1e-300, -3e-300, 2e-300], &[(1.0, 0.0), (2.0, 0.0)], 1e-15);
+        check(&[1e300, -6e300, 11e300, -6e300], &[(1.0, 0.0), (2.0, 0.0), (3.0, 0.0)], 1e-12);
+        check(&[1e300, 0.0, 0.0, 0.0, -1e300], &[(-1.0, 0.0), (1.0, 0.0), (0.0, -1.0), (0.0, 1.0)], 1e-12);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(roots(&[0.0, 0.0]).is_err());
+        assert!(roots(&[0.0, 3.0]).is_err());
+        assert!(roots(&[1.0; 12]).is_err());
+        assert!(roots(&[1.0, f64::NAN]).is_err());
+        assert!(PolySolver::new(0).is_err());
+        assert!(PolySolver::new(11).is_err());
+    }
+
+    #[test]
+    fn test_screen() {
+        let mut poly = PolySolver::new(2).unwrap();
+        for entry in ["1", "0", "-4"] {
+            entry.chars().for_each(|c| {
+                poly.push(c);
+            });
+            poly.advance().unwrap();
+        }
+        assert_eq!(poly.coefficients(), &[1.0, 0.0, -4.0]);
+        assert_eq!(poly.cursor(), 0);
+        assert_eq!(poly.solve().unwrap(), &[Complex::from(-2.0), Complex::from(2.0)]);
+        poly.select(5);
+        assert_eq!(poly.selected_root(), Some(Complex::from(2.0)));
+        poly.edit();
+        assert!(poly.roots().is_none());
+        poly.move_cursor(-3).unwrap();
+        assert_eq!(poly.cursor(), 0);
+    }
+}
@@ -0,0 +1,379 @@
+//! Quantities: values that carry physical dimensions through arithmetic
+
+use crate::complex::Complex;
+use crate::functions::CalcError;
+use crate::units::UNITS;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use serde::{Deserialize, Serialize};
+
+/// Base units, in the order of their exponents in `Dims`. Angles get a
+/// base of their own so that `sin(90 deg)` means degrees in any mode.
+/// Amperes and moles come last, so that settings saved with six
+/// exponents still read.
+pub const BASE_UNITS: [&str; 8] = ["m", "kg", "s", "K", "B", "rad", "A", "mol"];
+
+/// Exponents of the base units; all zero for a plain number
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<i8>")]
+pub struct Dims(pub [i8; 8]);
+
+/// Settings saved before amperes and moles have fewer exponents
+impl From<Vec<i8>> for Dims {
+    fn from(exponents: Vec<i8>) -> Self {
+        let mut dims = Dims::NONE;
+        for (e, x) in dims.0.iter_mut().zip(exponents) {
+            *e = x;
+        }
+        dims
+    }
+}
+
+/// Coherent derived units, shown in place of their base form
+const DERIVED: [(&str, Dims); 5] = [
+    ("N", Dims([1, 1, -2, 0, 0, 0, 0, 0])),
+    ("J", Dims([2, 1, -2, 0, 0, 0, 0, 0])),
+    ("W", Dims([2, 1, -3, 0, 0, 0, 0, 0])),
+    ("Pa", Dims([-1, 1, -2, 0, 0, 0, 0, 0])),
+    ("C", Dims([0, 0, 1, 0, 0, 0, 1, 0])),
+];
+
+impl Dims {
+    pub const NONE: Dims = Dims([0; 8]);
+    pub const LENGTH: Dims = Dims([1, 0, 0, 0, 0, 0, 0, 0]);
+    pub const MASS: Dims = Dims([0, 1, 0, 0, 0, 0, 0, 0]);
+    pub const TIME: Dims = Dims([0, 0, 1, 0, 0, 0, 0, 0]);
+    pub const TEMPERATURE: Dims = Dims([0, 0, 0, 1, 0, 0, 0, 0]);
+    pub const DATA: Dims = Dims([0, 0, 0, 0, 1, 0, 0, 0]);
+    pub const ANGLE: Dims = Dims([0, 0, 0, 0, 0, 1, 0, 0]);
+    pub const ENERGY: Dims = DERIVED[1].1;
+    pub const PRESSURE: Dims = DERIVED[3].1;
+    pub const SPEED: Dims = Dims([1, 0, -1, 0, 0, 0, 0, 0]);
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Dimensions of a product
+    pub fn times(self, other: Dims) -> Result<Dims, CalcError> {
+        self.zip(other, |a, b| a.checked_add(b))
+    }
+
+    /// Dimensions of a quotient
+    pub fn per(self, other: Dims) -> Result<Dims, CalcError> {
+        self.zip(other, |a, b| a.checked_sub(b))
+    }
+
+    /// Dimensions of a power; every exponent must stay whole, so
+    /// `(4 m^2)^0.5` is a length but `(2 m)^0.5` is an error
+    pub fn pow(self, n: f64) -> Result<Dims, CalcError> {
+        let mut result = self;
+        for e in result.0.iter_mut() {
+            let scaled = *e as f64 * n;
+            if scaled.fract() != 0.0 {
+                return Err(CalcError::DimensionError("fractional power of a unit"));
+            }
+            if scaled.abs() > i8::MAX as f64 {
+                return Err(CalcError::Overflow);
+            }
+            *e = scaled as i8;
+        }
+        Ok(result)
+    }
+
+    fn zip(self, other: Dims, f: impl Fn(i8, i8) -> Option<i8>) -> Result<Dims, CalcError> {
+        let mut result = Dims::NONE;
+        for (i, e) in result.0.iter_mut().enumerate() {
+            *e = f(self.0[i], other.0[i]).ok_or(CalcError::Overflow)?;
+        }
+        Ok(result)
+    }
+
+    /// SI unit of these dimensions: `N`, `m/s²`, `kg/(m s²)`, `1/s`
+    pub fn label(&self) -> String {
+        if let Some((name, _)) = DERIVED.iter().find(|(_, dims)| dims == self) {
+            return String::from(*name);
+        }
+        let part = |sign: i8| {
+            let mut buf = String::new();
+            for (name, &e) in BASE_UNITS.iter().zip(self.0.iter()) {
+                if e.signum() == sign {
+                    if !buf.is_empty() {
+                        buf.push(' ');
+                    }
+                    buf.push_str(name);
+                    push_exponent(&mut buf, e.unsigned_abs());
+                }
+            }
+            buf
+        };
+        let (num, den) = (part(1), part(-1));
+        match (num.is_empty(), den.is_empty(), den.contains(' ')) {
+            (_, true, _) => num,
+            (true, false, _) => alloc::format!("1/{}", den),
+            (false, false, false) => alloc::format!("{}/{}", num, den),
+            (false, false, true) => alloc::format!("{}/({})", num, den),
+        }
+    }
+}
+
+/// Append `e` as a superscript, leaving out a power of one
+fn push_exponent(buf: &mut String, e: u8) {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    if e > 1 {
+        let mut digits = String::new();
+        write!(digits, "{}", e).ok();
+        buf.extend(digits.bytes().map(|d| DIGITS[(d - b'0') as usize]));
+    }
+}
+
+/// A value with dimensions, kept in SI base units; it may be complex
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Quantity {
+    /// Value in SI base units (its real part)
+    pub value: f64,
+    /// Imaginary part, in the same units
+    pub im: f64,
+    pub dims: Dims,
+    /// Unit the value is shown in, by index into `UNITS`; SI if none
+    pub unit: Option<usize>,
+}
+
+impl From<f64> for Quantity {
+    fn from(value: f64) -> Self {
+        Self::new(value, Dims::NONE)
+    }
+}
+
+impl From<Complex> for Quantity {
+    fn from(z: Complex) -> Self {
+        Self {
+            im: z.im,
+            ..Self::from(z.re)
+        }
+    }
+}
+
+impl Quantity {
+    pub fn new(value: f64, dims: Dims) -> Self {
+        Self {
+            value,
+            im: 0.0,
+            dims,
+            unit: None,
+        }
+    }
+
+    /// One of a catalog unit, as `ft` means in an expression. Temperature
+    /// scales with a shifted zero have no such meaning: is `2 degC` twice
+    /// a temperature or a difference?
+    pub fn of_unit(unit: usize) -> Result<Self, CalcError> {
+        let u = UNITS.get(unit).ok_or(CalcError::DomainError("convert: unknown unit"))?;
+        if u.offset != 0.0 {
+            return Err(CalcError::DimensionError("degC, degF: use → to convert"));
+        }
+        Ok(Self {
+            unit: Some(unit),
+            ..Self::new(u.scale, u.dimension.dims())
+        })
+    }
+
+    /// The reading `x` on a unit's scale, offset included
+    pub fn in_unit(x: f64, unit: usize) -> Result<Self, CalcError> {
+        let u = UNITS.get(unit).ok_or(CalcError::DomainError("convert: unknown unit"))?;
+        Ok(Self {
+            unit: Some(unit),
+            ..Self::new((x + u.offset) * u.scale, u.dimension.dims())
+        })
+    }
+
+    /// Is this a plain number, without dimensions?
+    pub fn is_plain(&self) -> bool {
+        self.dims.is_none()
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im == 0.0
+    }
+
+    /// The value as a complex number, in SI base units
+    pub fn complex(&self) -> Complex {
+        Complex::new(self.value, self.im)
+    }
+
+    /// The same dimensions and unit with another value
+    pub fn with_value(self, z: Complex) -> Self {
+        Self {
+            value: z.re,
+            im: z.im,
+            ..self
+        }
+    }
+
+    /// The number, if this is a plain one
+    pub fn plain_complex(&self) -> Result<Complex, CalcError> {
+        if self.is_plain() {
+            Ok(self.complex())
+        } else {
+            Err(CalcError::DimensionError("needs a plain number"))
+        }
+    }
+
+    /// The number, if this is a plain real one
+    pub fn plain(&self) -> Result<f64, CalcError> {
+        let z = self.plain_complex()?;
+        if z.is_real() {
+            Ok(z.re)
+        } else {
+            Err(CalcError::DomainError("needs a real number"))
+        }
+    }
+
+    /// The same quantity shown in `unit`, which must measure the same thing
+    pub fn express_in(self, unit: usize) -> Result<Self, CalcError> {
+        let u = UNITS.get(unit).ok_or(CalcError::DomainError("convert: unknown unit"))?;
+        if u.dimension.dims() != self.dims {
+            return Err(CalcError::DimensionError("convert: incompatible units"));
+        }
+        Ok(Self {
+            unit: Some(unit),
+            ..self
+        })
+    }
+
+    /// The same quantity shown in SI units
+    pub fn simplified(self) -> Self {
+        Self { unit: None, ..self }
+    }
+
+    /// The same quantity in SI if its unit has a shifted zero, as the
+    /// result of arithmetic: `2 × 100 degC` is not 200 degC
+    pub fn linear(self) -> Self {
+        match self.unit.and_then(|i| UNITS.get(i)) {
+            Some(u) if u.offset != 0.0 => self.simplified(),
+            _ => self,
+        }
+    }
+
+    /// The number shown: the value read on the scale of its unit
+    pub fn shown(&self) -> f64 {
+        self.shown_complex().re
+    }
+
+    /// The complex number shown; only the real part has a shifted zero
+    pub fn shown_complex(&self) -> Complex {
+        match self.unit.and_then(|i| UNITS.get(i)) {
+            Some(u) => Complex::new(self.value / u.scale - u.offset, self.im / u.scale),
+            None => self.complex(),
+        }
+    }
+
+    /// Name of the unit shown, empty for a plain number
+    pub fn label(&self) -> String {
+        match self.unit.and_then(|i| UNITS.get(i)) {
+            Some(u) => String::from(u.name),
+            None => self.dims.label(),
+        }
+    }
+
+    /// Expression that reads back as this quantity: `(5*ft)`, `(2*m*s^-1)`,
+    /// `(3-4i)`
+    pub fn to_source(&self) -> String {
+        let mut buf = String::new();
+        match self.unit.and_then(|i| UNITS.get(i)) {
+            _ if self.is_plain() => push_source(&mut buf, self.complex()),
+            Some(u) if u.offset == 0.0 => {
+                buf.push('(');
+                push_source(&mut buf, self.shown_complex());
+                write!(buf, "*{})", u.name).ok();
+            }
+            _ => {
+                buf.push('(');
+                push_source(&mut buf, self.complex());
+                for (name, &e) in BASE_UNITS.iter().zip(self.dims.0.iter()) {
+                    match e {
+                        0 => {}
+                        1 => {
+                            write!(buf, "*{}", name).ok();
+                        }
+                        e => {
+                            write!(buf, "*{}^{}", name, e).ok();
+                        }
+                    }
+                }
+                buf.push(')');
+            }
+        }
+        buf
+    }
+}
+
+/// Append a number as an expression: `2.5`, or `(3-4i)` if complex
+fn push_source(buf: &mut String, z: Complex) {
+    if z.is_real() {
+        write!(buf, "{}", z.re).ok();
+    } else {
+        let sign = if z.im.is_sign_negative() { '-' } else { '+' };
+        write!(buf, "({}{}{}i)", z.re, sign, z.im.abs()).ok();
+    }
+}
+
+extern crate alloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::units;
+
+    fn of(name: &str) -> Quantity {
+        Quantity::of_unit(units::index_of(name).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_labels() {
+        assert_eq!(Dims::NONE.label(), "");
+        assert_eq!(Dims::SPEED.label(), "m/s");
+        assert_eq!(Dims::ENERGY.label(), "J");
+        assert_eq!(Dims([1, 0, -2, 0, 0, 0, 0, 0]).label(), "m/s²");
+        assert_eq!(Dims([0, 0, -1, 0, 0, 0, 0, 0]).label(), "1/s");
+        assert_eq!(Dims([3, -1, -2, 0, 0, 0, 0, 0]).label(), "m³/(kg s²)");
+        assert_eq!(Dims([12, 0, 0, 0, 0, 0, 0, 0]).label(), "m¹²");
+        assert_eq!(of("ft").label(), "ft");
+    }
+
+    #[test]
+    fn test_dims() {
+        assert_eq!(Dims::LENGTH.per(Dims::TIME).unwrap(), Dims::SPEED);
+        assert_eq!(Dims::SPEED.pow(2.0).unwrap().times(Dims::MASS).unwrap(), Dims::ENERGY);
+        assert_eq!(Dims::LENGTH.pow(2.0).unwrap().pow(0.5).unwrap(), Dims::LENGTH);
+        assert!(Dims::LENGTH.pow(0.5).is_err());
+        assert!(Dims::LENGTH.pow(200.0).is_err());
+        // Saved before amperes and moles
+        assert_eq!(serde_json::from_str::<Dims>("[1,0,-1,0,0,0]").unwrap(), Dims::SPEED);
+    }
+
+    #[test]
+    fn test_units() {
+        let ft = of("ft");
+        assert_eq!(ft.value, 0.3048);
+        assert_eq!(ft.shown(), 1.0);
+        assert!(Quantity::of_unit(units::index_of("degC").unwrap()).is_err());
+
+        let boiling = Quantity::in_unit(100.0, units::index_of("degC").unwrap()).unwrap();
+        assert!((boiling.value - 373.15).abs() < 1e-9);
+        let f = boiling.express_in(units::index_of("degF").unwrap()).unwrap();
+        assert!((f.shown() - 212.0).abs() < 1e-9);
+        assert!(ft.express_in(units::index_of("s").unwrap()).is_err());
+        let inches = ft.express_in(units::index_of("in").unwrap()).unwrap();
+        assert!((inches.shown() - 12.0).abs() < 1e-12);
+        assert_eq!(ft.simplified().label(), "m");
+
+        assert_eq!(Quantity::from(2.5).to_source(), "2.5");
+        assert_eq!(ft.to_source(), "(1*ft)");
+        assert_eq!(Quantity::new(2.0, Dims::SPEED).to_source(), "(2*m*s^-1)");
+        assert_eq!(Quantity::from(Complex::new(3.0, -4.0)).to_source(), "(3-4i)");
+        assert_eq!(ft.with_value(Complex::new(0.6096, 0.3048)).to_source(), "((2+1i)*ft)");
+        assert!(Quantity::from(Complex::I).plain().is_err());
+    }
+}
@@ -1,14 +1,16 @@
 //! RPN (Reverse Polish Notation) stack machine
 
-use crate::functions::{AngleMode, CalcError, Func, Op};
+use crate::functions::{AngleMode, CalcError, ComplexMode, Func, Op};
+use crate::quantity::Quantity;
+use crate::value::Value;
 use alloc::string::String;
 
 /// Classic 4-level RPN stack (X, Y, Z, T)
 pub struct RpnStack {
     /// Stack registers (index 0 = X/bottom, 3 = T/top)
-    stack: [f64; 4],
+    stack: [Value; 4],
     /// Last X value for recall
-    last_x: f64,
+    last_x: Value,
     /// Currently entering a number
     entering: bool,
     /// Entry buffer for number being typed
@@ -26,8 +28,8 @@ impl Default for RpnStack {
 impl RpnStack {
     pub fn new() -> Self {
         Self {
-            stack: [0.0; 4],
-            last_x: 0.0,
+            stack: [Value::default(); 4],
+            last_x: Value::default(),
             entering: false,
             entry_buffer: String::new(),
             entry_started: false,
@@ -36,26 +38,31 @@ impl RpnStack {
 
     /// Get X register (bottom of stack)
     pub fn x(&self) -> f64 {
-        self.stack[0]
+        real(&self.stack[0])
     }
 
     /// Get Y register
     pub fn y(&self) -> f64 {
-        self.stack[1]
+        real(&self.stack[1])
     }
 
     /// Get Z register
     pub fn z(&self) -> f64 {
-        self.stack[2]
+        real(&self.stack[2])
     }
 
     /// Get T register (top of stack)
     pub fn t(&self) -> f64 {
-        self.stack[3]
+        real(&self.stack[3])
     }
 
     /// Get last X value
     pub fn last_x(&self) -> f64 {
+        real(&self.last_x)
+    }
+
+    /// Get last X with its dimensions, or as a matrix
+    pub fn last_x_value(&self) -> Value {
         self.last_x
     }
 
@@ -69,19 +76,19 @@ impl RpnStack {
         &self.entry_buffer
     }
 
-    /// Push value onto stack (lift stack)
-    pub fn push(&mut self, value: f64) {
+    /// Push a number, quantity or matrix onto stack (lift stack)
+    pub fn push(&mut self, value: impl Into<Value>) {
         // T is lost, others shift up
         self.stack[3] = self.stack[2];
         self.stack[2] = self.stack[1];
         self.stack[1] = self.stack[0];
-        self.stack[0] = value;
+        self.stack[0] = value.into();
         self.entering = false;
         self.entry_buffer.clear();
     }
 
     /// Pop value from stack (drop stack)
-    pub fn pop(&mut self) -> f64 {
+    pub fn pop(&mut self) -> Value {
         let value = self.stack[0];
         // Others shift down, T duplicates
         self.stack[0] = self.stack[1];
@@ -92,8 +99,8 @@ impl RpnStack {
     }
 
     /// Set X register directly (no stack lift)
-    pub fn set_x(&mut self, value: f64) {
-        self.stack[0] = value;
+    pub fn set_x(&mut self, value: impl Into<Value>) {
+        self.stack[0] = value.into();
         self.entering = false;
         self.entry_buffer.clear();
     }
@@ -126,7 +133,7 @@ impl RpnStack {
 
     /// Clear X register
     pub fn clear_x(&mut self) {
-        self.stack[0] = 0.0;
+        self.stack[0] = Value::default();
         self.entering = false;
         self.entry_buffer.clear();
         self.entry_started = false;
@@ -134,8 +141,8 @@ impl RpnStack {
 
     /// Clear all registers
     pub fn clear_all(&mut self) {
-        self.stack = [0.0; 4];
-        self.last_x = 0.0;
+        self.stack = [Value::default(); 4];
+        self.last_x = Value::default();
         self.entering = false;
         self.entry_buffer.clear();
         self.entry_started = false;
@@ -176,7 +183,7 @@ impl RpnStack {
         self.entry_buffer.push(c);
         // Update X register with current entry
         if let Ok(value) = self.entry_buffer.parse::<f64>() {
-            self.stack[0] = value;
+            self.stack[0] = value.into();
         }
     }
 
@@ -200,10 +207,13 @@ impl RpnStack {
                 self.entry_buffer.insert(0, '-');
             }
             if let Ok(value) = self.entry_buffer.parse::<f64>() {
-                self.stack[0] = value;
+                self.stack[0] = value.into();
             }
         } else {
-            self.stack[0] = -self.stack[0];
+            self.stack[0] = match self.stack[0] {
+                Value::Number(x) => x.with_value(-x.complex()).into(),
+                Value::Matrix(m) => m.scale(-1.0).map_or(Value::Matrix(m), Value::from),
+            };
         }
     }
 
@@ -212,9 +222,9 @@ impl RpnStack {
         if self.entering && !self.entry_buffer.is_empty() {
             self.entry_buffer.pop();
             if self.entry_buffer.is_empty() || self.entry_buffer == "-" {
-                self.stack[0] = 0.0;
+                self.stack[0] = Value::default();
             } else if let Ok(value) = self.entry_buffer.parse::<f64>() {
-                self.stack[0] = value;
+                self.stack[0] = value.into();
             }
         }
     }
@@ -223,7 +233,7 @@ impl RpnStack {
     fn finish_entry(&mut self) {
         if self.entering {
             if let Ok(value) = self.entry_buffer.parse::<f64>() {
-                self.stack[0] = value;
+                self.stack[0] = value.into();
             }
             self.entering = false;
             self.entry_buffer.clear();
@@ -231,22 +241,86 @@ impl RpnStack {
     }
 
     /// Apply unary function to X
-    pub fn apply_unary(&mut self, func: Func, angle_mode: AngleMode) -> Result<(), CalcError> {
-        self.finish_entry();
-        self.last_x = self.stack[0];
-        let result = func.evaluate(self.stack[0], angle_mode)?;
-        self.stack[0] = result;
-        self.entry_started = false;
-        Ok(())
+    pub fn apply_unary(
+        &mut self,
+        func: Func,
+        angle_mode: AngleMode,
+        complex: ComplexMode,
+    ) -> Result<(), CalcError> {
+        self.apply_values(1, |args| func.evaluate_value(args[0], angle_mode, complex))
     }
 
     /// Apply binary operator: Y op X → X
-    pub fn apply_binary(&mut self, op: Op) -> Result<(), CalcError> {
+    pub fn apply_binary(&mut self, op: Op, angle_mode: AngleMode, complex: ComplexMode) -> Result<(), CalcError> {
+        self.apply_values(2, |args| op.evaluate_value(args[0], args[1], angle_mode, complex))
+    }
+
+    /// Apply a function consuming `n` stack levels: f(.., Y, X) → X
+    ///
+    /// The deepest level is the first argument, so `atan2` takes y from Y
+    /// and x from X just like the binary operators.
+    pub fn apply_nary(
+        &mut self,
+        func: Func,
+        n: usize,
+        angle_mode: AngleMode,
+        complex: ComplexMode,
+    ) -> Result<(), CalcError> {
+        self.apply_values(n, |args| func.evaluate_args_value(args, angle_mode, complex))
+    }
+
+    /// Apply any computation on plain numbers over `n` stack levels
+    /// (deepest first) → X
+    pub fn apply_with(
+        &mut self,
+        n: usize,
+        f: impl FnOnce(&[f64]) -> Result<f64, CalcError>,
+    ) -> Result<(), CalcError> {
+        self.apply_quantities(n, |args| {
+            let mut plain = [0.0; 4];
+            for (arg, q) in plain.iter_mut().zip(args) {
+                *arg = q.plain()?;
+            }
+            f(&plain[..args.len()]).map(Quantity::from)
+        })
+    }
+
+    /// Apply any computation on numbers over `n` stack levels (deepest
+    /// first) → X
+    pub fn apply_quantities(
+        &mut self,
+        n: usize,
+        f: impl FnOnce(&[Quantity]) -> Result<Quantity, CalcError>,
+    ) -> Result<(), CalcError> {
+        self.apply_values(n, |args| {
+            let mut numbers = [Quantity::default(); 4];
+            for (arg, v) in numbers.iter_mut().zip(args) {
+                *arg = v.number()?;
+            }
+            f(&numbers[..args.len()]).map(Value::from)
+        })
+    }
+
+    /// Apply any computation over `n` stack levels (deepest first) → X;
+    /// a failed one leaves the stack untouched
+    pub fn apply_values(
+        &mut self,
+        n: usize,
+        f: impl FnOnce(&[Value]) -> Result<Value, CalcError>,
+    ) -> Result<(), CalcError> {
+        if n == 0 || n > self.stack.len() {
+            return Err(CalcError::SyntaxError("Not enough stack levels".into()));
+        }
         self.finish_entry();
+        let mut args = [Value::default(); 4];
+        for (i, arg) in args[..n].iter_mut().enumerate() {
+            *arg = self.stack[n - 1 - i];
+        }
+        let result = f(&args[..n])?;
         self.last_x = self.stack[0];
-        let x = self.pop();
-        let y = self.stack[0];
-        let result = op.evaluate(y, x)?;
+        for _ in 1..n {
+            self.pop();
+        }
         self.stack[0] = result;
         self.entry_started = false;
         Ok(())
@@ -258,16 +332,28 @@ impl RpnStack {
     }
 
     /// Get all stack values for display [X, Y, Z, T]
-    pub fn get_stack(&self) -> [f64; 4] {
+    pub fn get_stack(&self) -> [Value; 4] {
         self.stack
     }
 }
 
+/// Real part of a number, NaN for a matrix
+fn real(v: &Value) -> f64 {
+    v.number().map_or(f64::NAN, |q| q.value)
+}
+
 extern crate alloc;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::complex::Complex;
+    use crate::matrix::Matrix;
+
+    /// Number in X
+    fn x_of(stack: &RpnStack) -> Quantity {
+        stack.get_stack()[0].number().unwrap()
+    }
 
     #[test]
     fn test_basic_operations() {
@@ -277,7 +363,7 @@ mod tests {
         stack.digit('2');
         stack.enter();
         stack.digit('3');
-        stack.apply_binary(Op::Add).unwrap();
+        stack.apply_binary(Op::Add, AngleMode::Degrees, ComplexMode::Real).unwrap();
 
         assert_eq!(stack.x(), 5.0);
     }
@@ -301,6 +387,91 @@ mod tests {
         assert_eq!(stack.y(), 4.0);
     }
 
+    #[test]
+    fn test_nary_function() {
+        let mut stack = RpnStack::new();
+        stack.push(9.0);
+        stack.push(10.0);
+        stack.push(3.0);
+        stack.apply_nary(Func::NCr, 2, AngleMode::Degrees, ComplexMode::Real).unwrap();
+        assert_eq!(stack.x(), 120.0);
+        assert_eq!(stack.y(), 9.0);
+        assert_eq!(stack.last_x(), 3.0);
+
+        // A failed call leaves the stack untouched
+        stack.push(0.0);
+        assert!(stack.apply_nary(Func::Root, 2, AngleMode::Degrees, ComplexMode::Real).is_err());
+        assert_eq!(stack.x(), 0.0);
+        assert_eq!(stack.y(), 120.0);
+    }
+
+    #[test]
+    fn test_quantities() {
+        let unit = |name| Quantity::of_unit(crate::units::index_of(name).unwrap()).unwrap();
+        let mut stack = RpnStack::new();
+        stack.push(3.0);
+        stack.push(unit("m"));
+        stack.apply_binary(Op::Mul, AngleMode::Degrees, ComplexMode::Real).unwrap();
+        stack.push(unit("s"));
+        stack.apply_binary(Op::Div, AngleMode::Degrees, ComplexMode::Real).unwrap();
+        let x = x_of(&stack);
+        assert_eq!((x.value, x.label().as_str()), (3.0, "m/s"));
+
+        // A dimension error leaves the stack untouched
+        stack.push(unit("kg"));
+        assert!(stack.apply_binary(Op::Add, AngleMode::Degrees, ComplexMode::Real).is_err());
+        assert!(stack.apply_unary(Func::Ln, AngleMode::Degrees, ComplexMode::Real).is_err());
+        assert!(stack.apply_nary(Func::Max, 2, AngleMode::Degrees, ComplexMode::Real).is_err());
+        assert_eq!(stack.get_stack()[..2], [unit("kg").into(), x.into()]);
+
+        stack.change_sign();
+        assert_eq!(x_of(&stack).shown(), -1.0);
+        assert_eq!(x_of(&stack).label(), "kg");
+    }
+
+    #[test]
+    fn test_complex() {
+        let (deg, complex) = (AngleMode::Degrees, ComplexMode::Rectangular);
+        let mut stack = RpnStack::new();
+        stack.push(-4.0);
+        assert!(stack.apply_unary(Func::Sqrt, deg, ComplexMode::Real).is_err());
+        stack.apply_unary(Func::Sqrt, deg, complex).unwrap();
+        assert_eq!(x_of(&stack).complex(), Complex::new(0.0, 2.0));
+
+        // 2∠90 + 2i = 4i
+        stack.push(2.0);
+        stack.push(90.0);
+        stack.apply_binary(Op::Polar, deg, complex).unwrap();
+        stack.apply_binary(Op::Add, deg, complex).unwrap();
+        let x = x_of(&stack).complex();
+        assert!(x.re.abs() < 1e-15 && x.im == 4.0);
+
+        stack.change_sign();
+        assert_eq!(x_of(&stack).im, -4.0);
+        stack.apply_unary(Func::Abs, deg, complex).unwrap();
+        assert_eq!(stack.x(), 4.0);
+    }
+
+    #[test]
+    fn test_matrices() {
+        let (deg, real) = (AngleMode::Degrees, ComplexMode::Real);
+        let a = Matrix::from_rows(&[alloc::vec![1.0, 2.0], alloc::vec![3.0, 4.0]]).unwrap();
+        let mut stack = RpnStack::new();
+        stack.push(a);
+        stack.push(2.0);
+        stack.apply_binary(Op::Mul, deg, real).unwrap();
+        assert_eq!(stack.get_stack()[0], Value::Matrix(a.scale(2.0).unwrap()));
+        stack.apply_unary(Func::Det, deg, real).unwrap();
+        assert_eq!(stack.x(), -8.0);
+
+        // Matrices don't mix with scalar-only operations
+        stack.push(a);
+        assert!(stack.apply_unary(Func::Ln, deg, real).is_err());
+        assert!(stack.apply_with(1, |args| Ok(args[0])).is_err());
+        stack.change_sign();
+        assert_eq!(stack.get_stack()[0], Value::Matrix(a.scale(-1.0).unwrap()));
+    }
+
     #[test]
     fn test_change_sign() {
         let mut stack = RpnStack::new();
@@ -1,6 +1,13 @@
 //! PDDB settings persistence
 
-use crate::functions::{AngleMode, NumberBase};
+use crate::complex::Complex;
+use crate::functions::{AngleMode, ComplexMode, NumberBase, SignMode, WordSize};
+use crate::matrix::Matrix;
+use crate::quantity::{Dims, Quantity};
+use crate::userfunc::FunctionDef;
+use crate::value::Value;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Seek, SeekFrom, Write};
@@ -8,9 +15,15 @@ use std::io::{Read, Seek, SeekFrom, Write};
 const DICT_NAME: &str = "calc.settings";
 const KEY_NAME: &str = "state";
 
+/// Layout of the saved settings: 1 saves each value whole
+pub const VERSION: u8 = 1;
+
 /// Persistent calculator settings
 #[derive(Serialize, Deserialize, Default)]
 pub struct Settings {
+    /// Layout the settings were saved in; 0 before layouts were numbered
+    #[serde(default)]
+    pub version: u8,
     /// 0 = Algebraic, 1 = RPN
     pub mode: u8,
     /// 0 = DEG, 1 = RAD, 2 = GRAD
@@ -18,12 +31,88 @@ pub struct Settings {
     /// 0 = DEC, 1 = HEX, 2 = OCT, 3 = BIN
     pub number_base: u8,
     /// Memory registers
-    pub memory: [f64; 10],
+    #[serde(default)]
+    pub registers: [Value; 10],
     /// Last answer
-    pub ans: f64,
+    #[serde(default)]
+    pub ans_value: Value,
+    /// Named user variables
+    #[serde(default)]
+    pub variable_values: BTreeMap<String, Value>,
+    /// Values as layout 0 saved them, read only to migrate
+    #[serde(flatten, skip_serializing)]
+    pub legacy: LegacyValues,
+    /// User-defined functions (source form)
+    #[serde(default)]
+    pub functions: Vec<FunctionDef>,
+    /// Integer word size in bits: 8, 16, 32 or 64 (0 = 64)
+    #[serde(default)]
+    pub word_bits: u8,
+    /// 0 = two's complement, 1 = one's complement, 2 = unsigned
+    #[serde(default)]
+    pub sign_mode: u8,
+    /// 0 = real only, 1 = a+bi, 2 = r∠θ
+    #[serde(default)]
+    pub complex_mode: u8,
+}
+
+/// Layout 0 spread each value over parallel fields: real part, imaginary
+/// part, dimensions, and rows if it was a matrix
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct LegacyValues {
+    memory: [f64; 10],
+    memory_im: [f64; 10],
+    /// Keyed by register number as text, since flattened maps only read string keys
+    memory_matrices: BTreeMap<String, Vec<Vec<f64>>>,
+    ans: f64,
+    ans_im: f64,
+    ans_dims: Dims,
+    ans_matrix: Option<Vec<Vec<f64>>>,
+    variables: BTreeMap<String, f64>,
+    variable_im: BTreeMap<String, f64>,
+    variable_dims: BTreeMap<String, Dims>,
+    variable_matrices: BTreeMap<String, Vec<Vec<f64>>>,
+}
+
+impl LegacyValues {
+    /// One value from its parts; rows that do not make a matrix are
+    /// ignored
+    fn value(re: f64, im: f64, dims: Dims, rows: Option<&Vec<Vec<f64>>>) -> Value {
+        match rows.map(|rows| Matrix::from_rows(rows)) {
+            Some(Ok(m)) => m.into(),
+            _ => Quantity::new(0.0, dims).with_value(Complex::new(re, im)).into(),
+        }
+    }
 }
 
 impl Settings {
+    /// Bring settings saved in an older layout up to `VERSION`
+    pub fn migrate(&mut self) {
+        if self.version == 0 {
+            let old = core::mem::take(&mut self.legacy);
+            for (i, register) in self.registers.iter_mut().enumerate() {
+                *register = LegacyValues::value(old.memory[i], old.memory_im[i], Dims::NONE, old.memory_matrices.get(&alloc::format!("{}", i)));
+            }
+            self.ans_value = LegacyValues::value(old.ans, old.ans_im, old.ans_dims, old.ans_matrix.as_ref());
+            self.variable_values = old
+                .variables
+                .iter()
+                .map(|(name, &re)| {
+                    let im = old.variable_im.get(name).copied().unwrap_or_default();
+                    let dims = old.variable_dims.get(name).copied().unwrap_or_default();
+                    (name.clone(), LegacyValues::value(re, im, dims, None))
+                })
+                .collect();
+            for (name, rows) in &old.variable_matrices {
+                if let Ok(m) = Matrix::from_rows(rows) {
+                    self.variable_values.insert(name.clone(), m.into());
+                }
+            }
+        }
+        self.version = VERSION;
+    }
+
     pub fn is_rpn(&self) -> bool {
         self.mode == 1
     }
@@ -47,6 +136,23 @@ impl Settings {
     pub fn set_number_base(&mut self, base: NumberBase) {
         self.number_base = base.to_u8();
     }
+
+    pub fn get_complex_mode(&self) -> ComplexMode {
+        ComplexMode::from_u8(self.complex_mode)
+    }
+
+    pub fn set_complex_mode(&mut self, mode: ComplexMode) {
+        self.complex_mode = mode.to_u8();
+    }
+
+    pub fn get_word_size(&self) -> WordSize {
+        WordSize::new(self.word_bits as u32, SignMode::from_u8(self.sign_mode))
+    }
+
+    pub fn set_word_size(&mut self, word: WordSize) {
+        self.word_bits = word.bits as u8;
+        self.sign_mode = word.sign.to_u8();
+    }
 }
 
 /// Storage manager
@@ -76,7 +182,8 @@ impl Storage {
                 let mut data = Vec::new();
                 key.seek(SeekFrom::Start(0)).ok();
                 if key.read_to_end(&mut data).is_ok() {
-                    if let Ok(settings) = serde_json::from_slice::<Settings>(&data) {
+                    if let Ok(mut settings) = serde_json::from_slice::<Settings>(&data) {
+                        settings.migrate();
                         return settings;
                     }
                 }
@@ -119,3 +226,44 @@ impl Storage {
 }
 
 extern crate alloc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_layout_0() {
+        let old = r#"{"mode":0,"angle_mode":0,"number_base":0,
+            "memory":[1.0,2.0,0.0,0.0,0.0,0.0,0.0,0.0,0.0,0.0],
+            "memory_im":[0.0,3.0,0.0,0.0,0.0,0.0,0.0,0.0,0.0,0.0],
+            "memory_matrices":{"2":[[1.0,2.0],[3.0,4.0]]},
+            "ans":5.0,"ans_dims":[1,0,0,0,0,0],
+            "variables":{"v":2.0},"variable_dims":{"v":[1,0,-1,0,0,0]},
+            "variable_matrices":{"a":[[1.0],[2.0]]}}"#;
+        let mut settings: Settings = serde_json::from_str(old).unwrap();
+        settings.migrate();
+        assert_eq!(settings.version, VERSION);
+        assert_eq!(settings.registers[0], Value::from(1.0));
+        assert_eq!(settings.registers[1], Value::from(Complex::new(2.0, 3.0)));
+        assert_eq!(settings.registers[2], Value::from(Matrix::from_rows(&[vec![1.0, 2.0], vec![3.0, 4.0]]).unwrap()));
+        assert_eq!(settings.ans_value, Value::from(Quantity::new(5.0, Dims::LENGTH)));
+        assert_eq!(settings.variable_values["v"], Value::from(Quantity::new(2.0, Dims::SPEED)));
+        assert_eq!(settings.variable_values["a"], Value::from(Matrix::from_rows(&[vec![1.0], vec![2.0]]).unwrap()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = Settings { version: VERSION, ..Settings::default() };
+        settings.registers[3] = Complex::new(1.5, -2.0).into();
+        settings.registers[4] = Matrix::from_rows(&[vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap().into();
+        settings.ans_value = Quantity::new(9.81, Dims::ENERGY).into();
+        settings.variable_values.insert(String::from("x"), Value::from(4.0));
+        let json = serde_json::to_string(&settings).unwrap();
+        assert!(!json.contains("memory_im"));
+        let mut read: Settings = serde_json::from_str(&json).unwrap();
+        read.migrate();
+        assert_eq!(read.registers, settings.registers);
+        assert_eq!(read.ans_value, settings.ans_value);
+        assert_eq!(read.variable_values, settings.variable_values);
+    }
+}
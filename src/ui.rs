@@ -2,6 +2,8 @@
 
 use gam::menu::*;
 use gam::{Gam, GlyphStyle};
+use crate::functions::{IntFlags, Span};
+use alloc::vec::Vec;
 
 // Screen dimensions (Precursor)
 pub const SCREEN_WIDTH: isize = 336;
@@ -58,12 +60,16 @@ pub fn draw_separator(gam: &Gam, gid: gam::Gid, y: isize) {
 }
 
 /// Draw status bar at top
+#[allow(clippy::too_many_arguments)]
 pub fn draw_status_bar(
     gam: &Gam,
     gid: gam::Gid,
     mode_label: &str,
     angle_label: &str,
     base_label: &str,
+    word_label: Option<&str>,
+    complex_label: Option<&str>,
+    flags: IntFlags,
     has_memory: bool,
 ) {
     // Clear status area
@@ -114,6 +120,45 @@ pub fn draw_status_bar(
     write!(tv.text, "{}", base_label).ok();
     gam.post_textview(&mut tv).ok();
 
+    // Word size [8-U]..[64-2C], only meaningful in the integer bases
+    if let Some(word_label) = word_label {
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(180, 2, 240, STATUS_HEIGHT)),
+        );
+        tv.style = GlyphStyle::Small;
+        tv.draw_border = true;
+        tv.border_width = 1;
+        tv.margin = Point::new(2, 0);
+        write!(tv.text, "{}", word_label).ok();
+        gam.post_textview(&mut tv).ok();
+
+        // Carry and overflow flags from the last integer operation
+        if flags.carry || flags.overflow {
+            let mut tv = TextView::new(
+                gid,
+                TextBounds::BoundingBox(Rectangle::new_coords(245, 2, 290, STATUS_HEIGHT)),
+            );
+            tv.style = GlyphStyle::Small;
+            let carry = if flags.carry { "C" } else { "" };
+            let overflow = if flags.overflow { "V" } else { "" };
+            write!(tv.text, "{} {}", carry, overflow).ok();
+            gam.post_textview(&mut tv).ok();
+        }
+    } else if let Some(complex_label) = complex_label {
+        // Complex mode [a+bi]/[r∠θ], in the word size slot it never shares
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(180, 2, 240, STATUS_HEIGHT)),
+        );
+        tv.style = GlyphStyle::Small;
+        tv.draw_border = true;
+        tv.border_width = 1;
+        tv.margin = Point::new(2, 0);
+        write!(tv.text, "{}", complex_label).ok();
+        gam.post_textview(&mut tv).ok();
+    }
+
     // Memory indicator
     if has_memory {
         let mut tv = TextView::new(
@@ -128,13 +173,42 @@ pub fn draw_status_bar(
     draw_separator(gam, gid, STATUS_HEIGHT);
 }
 
+/// Monospace columns across the expression line
+const EXPR_COLUMNS: usize = 36;
+
+/// Characters kept visible to the right of the cursor when scrolling
+const SCROLL_LOOKAHEAD: usize = 4;
+
+/// First column to show of an expression line `len` characters long so
+/// that position `anchor` stays in view
+fn expr_scroll(len: usize, anchor: usize) -> usize {
+    let end = (anchor + SCROLL_LOOKAHEAD).min(len.saturating_sub(1)) + 1;
+    end.saturating_sub(EXPR_COLUMNS)
+}
+
+/// Grouped binary rows shown in the result area or X register; the full
+/// view (Shift+F4) shows the rest of a wider word
+const INLINE_BINARY_ROWS: usize = 2;
+
+/// Height of a monospace row of bits
+const BINARY_ROW_HEIGHT: isize = 16;
+
 /// Draw the main display area (algebraic mode)
+///
+/// `preview` is the provisional value of the expression being typed; it is
+/// drawn outlined in place of the last result. `result_rows`, when not
+/// empty, replaces the result with grouped binary rows.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_algebraic_display(
     gam: &Gam,
     gid: gam::Gid,
     expression: &str,
+    cursor: usize,
     result: &str,
+    result_rows: &[String],
+    preview: Option<&str>,
     error: Option<&str>,
+    error_span: Option<Span>,
 ) {
     let y_start = STATUS_HEIGHT + 2;
     let y_end = STATUS_HEIGHT + DISPLAY_HEIGHT;
@@ -150,7 +224,7 @@ pub fn draw_algebraic_display(
     )
     .ok();
 
-    // Expression (right-aligned, regular size)
+    // Expression in monospace so columns (cursor, error caret) line up
     let mut tv = TextView::new(
         gid,
         TextBounds::BoundingBox(Rectangle::new_coords(
@@ -160,13 +234,51 @@ pub fn draw_algebraic_display(
             y_start + 30,
         )),
     );
-    tv.style = GlyphStyle::Regular;
+    tv.style = GlyphStyle::Monospace;
     use core::fmt::Write;
-    // Right-align by padding
-    let expr_display = if expression.is_empty() { "0" } else { expression };
-    write!(tv.text, "{}_", expr_display).ok();
+
+    let shown = if expression.is_empty() { "0" } else { expression };
+    let shown_len = shown.chars().count();
+    // The cursor is drawn as `_` in the line; errors show a caret instead
+    let cursor = if expression.is_empty() { shown_len } else { cursor.min(shown_len) };
+    let cursor_mark = if error_span.is_none() { Some('_') } else { None };
+    let line = shown
+        .chars()
+        .take(cursor)
+        .chain(cursor_mark)
+        .chain(shown.chars().skip(cursor));
+    let line_len = shown_len + cursor_mark.map_or(0, |_| 1);
+    let first = expr_scroll(line_len, error_span.map_or(cursor, |span| span.start));
+    for c in line.skip(first).take(EXPR_COLUMNS) {
+        write!(tv.text, "{}", c).ok();
+    }
     gam.post_textview(&mut tv).ok();
 
+    if let Some(span) = error_span {
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(
+                MARGIN,
+                y_start + 20,
+                SCREEN_WIDTH - MARGIN,
+                y_start + 38,
+            )),
+        );
+        tv.style = GlyphStyle::Monospace;
+        let last = (first + EXPR_COLUMNS).min(span.end.max(span.start + 1));
+        for col in first..last {
+            write!(tv.text, "{}", if col < span.start { ' ' } else { '^' }).ok();
+        }
+        gam.post_textview(&mut tv).ok();
+    }
+
+    if error.is_none() && preview.is_none() && !result_rows.is_empty() {
+        let skip = result_rows.len().saturating_sub(INLINE_BINARY_ROWS);
+        draw_binary_rows(gam, gid, &result_rows[skip..], MARGIN, y_start + 40);
+        draw_separator(gam, gid, y_end);
+        return;
+    }
+
     // Result or error (right-aligned, large)
     let mut tv = TextView::new(
         gid,
@@ -181,6 +293,12 @@ pub fn draw_algebraic_display(
     if let Some(err) = error {
         tv.style = GlyphStyle::Bold;
         write!(tv.text, "{}", err).ok();
+    } else if let Some(preview) = preview {
+        tv.style = GlyphStyle::Regular;
+        tv.draw_border = true;
+        tv.border_width = 1;
+        tv.margin = Point::new(4, 2);
+        write!(tv.text, "= {}", preview).ok();
     } else {
         tv.style = GlyphStyle::Large;
         write!(tv.text, "= {}", result).ok();
@@ -190,11 +308,30 @@ pub fn draw_algebraic_display(
     draw_separator(gam, gid, y_end);
 }
 
+/// Draw rows of bits in monospace so the nibbles line up
+fn draw_binary_rows(gam: &Gam, gid: gam::Gid, rows: &[String], x: isize, y: isize) {
+    use core::fmt::Write;
+    for (i, row) in rows.iter().enumerate() {
+        let row_y = y + i as isize * BINARY_ROW_HEIGHT;
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(x, row_y, SCREEN_WIDTH - MARGIN, row_y + BINARY_ROW_HEIGHT)),
+        );
+        tv.style = GlyphStyle::Monospace;
+        write!(tv.text, "{}", row).ok();
+        gam.post_textview(&mut tv).ok();
+    }
+}
+
 /// Draw RPN stack display
+///
+/// `x_rows`, when not empty, shows X as grouped binary rows.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_rpn_display(
     gam: &Gam,
     gid: gam::Gid,
     stack: [&str; 4], // [X, Y, Z, T]
+    x_rows: &[String],
     entry: &str,
     entering: bool,
     last_x: &str,
@@ -233,18 +370,36 @@ pub fn draw_rpn_display(
     draw_stack_register(gam, gid, "Y:", stack[1], y_start + 50, false);
 
     // X register (current entry, highlighted)
-    let x_display = if entering {
-        entry
+    let x_rows: &[String] = if entering {
+        &[]
     } else {
-        stack[0]
+        &x_rows[x_rows.len().saturating_sub(INLINE_BINARY_ROWS)..]
     };
-    draw_stack_register(gam, gid, "X:", x_display, y_start + 66, true);
+    if x_rows.is_empty() {
+        let x_display = if entering {
+            entry
+        } else {
+            stack[0]
+        };
+        draw_stack_register(gam, gid, "X:", x_display, y_start + 66, true);
+    } else {
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(MARGIN, y_start + 66, MARGIN + 20, y_start + 80)),
+        );
+        tv.style = GlyphStyle::Bold;
+        write!(tv.text, "X:").ok();
+        gam.post_textview(&mut tv).ok();
+        draw_binary_rows(gam, gid, x_rows, MARGIN + 24, y_start + 66);
+    }
+    // Taller when X takes more than one row
+    let below_x = y_start + 69 + BINARY_ROW_HEIGHT * x_rows.len().max(1) as isize;
 
     // Error display
     if let Some(err) = error {
         let mut tv = TextView::new(
             gid,
-            TextBounds::BoundingBox(Rectangle::new_coords(MARGIN, y_start + 85, SCREEN_WIDTH - MARGIN, y_end - 5)),
+            TextBounds::BoundingBox(Rectangle::new_coords(MARGIN, below_x, SCREEN_WIDTH - MARGIN, y_end - 5)),
         );
         tv.style = GlyphStyle::Bold;
         write!(tv.text, "{}", err).ok();
@@ -253,7 +408,7 @@ pub fn draw_rpn_display(
         // LastX
         let mut tv = TextView::new(
             gid,
-            TextBounds::BoundingBox(Rectangle::new_coords(MARGIN, y_start + 85, SCREEN_WIDTH - MARGIN, y_end - 5)),
+            TextBounds::BoundingBox(Rectangle::new_coords(MARGIN, below_x, SCREEN_WIDTH - MARGIN, y_end - 5)),
         );
         tv.style = GlyphStyle::Small;
         write!(tv.text, "LastX: {}", last_x).ok();
@@ -291,7 +446,9 @@ fn draw_stack_register(gam: &Gam, gid: gam::Gid, label: &str, value: &str, y: is
 }
 
 /// Draw history tape
-pub fn draw_history(gam: &Gam, gid: gam::Gid, entries: &[&str]) {
+///
+/// `selected` highlights an entry while the tape is being browsed.
+pub fn draw_history(gam: &Gam, gid: gam::Gid, entries: &[&str], selected: Option<usize>) {
     let y_start = STATUS_HEIGHT + DISPLAY_HEIGHT + 4;
     let y_end = SCREEN_HEIGHT - MENU_HEIGHT - 4;
 
@@ -311,10 +468,14 @@ pub fn draw_history(gam: &Gam, gid: gam::Gid, entries: &[&str]) {
     // History label
     let mut tv = TextView::new(
         gid,
-        TextBounds::BoundingBox(Rectangle::new_coords(MARGIN, y_start, 80, y_start + 14)),
+        TextBounds::BoundingBox(Rectangle::new_coords(MARGIN, y_start, SCREEN_WIDTH - MARGIN, y_start + 14)),
     );
     tv.style = GlyphStyle::Small;
-    write!(tv.text, "History:").ok();
+    if selected.is_some() {
+        write!(tv.text, "History: ↑↓ select, Enter recall, = result, ESC").ok();
+    } else {
+        write!(tv.text, "History:").ok();
+    }
     gam.post_textview(&mut tv).ok();
 
     // History entries
@@ -333,6 +494,7 @@ pub fn draw_history(gam: &Gam, gid: gam::Gid, entries: &[&str]) {
             )),
         );
         tv.style = GlyphStyle::Small;
+        tv.invert = selected == Some(i);
         write!(tv.text, "{}", entry).ok();
         gam.post_textview(&mut tv).ok();
     }
@@ -456,3 +618,380 @@ pub fn draw_fn_menu(gam: &Gam, gid: gam::Gid, title: &str, items: &[(&str, &str)
     write!(tv.text, "Press 0-9 or ESC to cancel").ok();
     gam.post_textview(&mut tv).ok();
 }
+
+/// Draw a single-column list overlay (for entries too long for columns)
+pub fn draw_list_menu(gam: &Gam, gid: gam::Gid, title: &str, items: &[(&str, &str)], hint: &str) {
+    let line_height = 18;
+    let menu_width = SCREEN_WIDTH - 2 * MARGIN - 8;
+    let menu_height = 24 + 8 + (items.len().max(1) as isize) * line_height + 24;
+    let x = (SCREEN_WIDTH - menu_width) / 2;
+    let y = (SCREEN_HEIGHT - menu_height) / 2;
+
+    // Background
+    gam.draw_rectangle(
+        gid,
+        Rectangle::new_with_style(
+            Point::new(x, y),
+            Point::new(x + menu_width, y + menu_height),
+            light_style(),
+        ),
+    )
+    .ok();
+
+    // Border
+    gam.draw_rectangle(
+        gid,
+        Rectangle::new_with_style(
+            Point::new(x, y),
+            Point::new(x + menu_width, y + menu_height),
+            outline_style(),
+        ),
+    )
+    .ok();
+
+    use core::fmt::Write;
+
+    // Title
+    let mut tv = TextView::new(
+        gid,
+        TextBounds::BoundingBox(Rectangle::new_coords(x + 4, y + 4, x + menu_width - 4, y + 22)),
+    );
+    tv.style = GlyphStyle::Bold;
+    write!(tv.text, "[{}]", title).ok();
+    gam.post_textview(&mut tv).ok();
+
+    gam.draw_line(
+        gid,
+        Line::new_with_style(
+            Point::new(x + 4, y + 24),
+            Point::new(x + menu_width - 4, y + 24),
+            dark_style(),
+        ),
+    )
+    .ok();
+
+    let start_y = y + 28;
+    if items.is_empty() {
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(x + 4, start_y, x + menu_width - 4, start_y + line_height)),
+        );
+        tv.style = GlyphStyle::Small;
+        write!(tv.text, "(empty)").ok();
+        gam.post_textview(&mut tv).ok();
+    }
+    for (i, (key, label)) in items.iter().enumerate() {
+        let item_y = start_y + (i as isize) * line_height;
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(x + 4, item_y, x + menu_width - 4, item_y + line_height)),
+        );
+        tv.style = GlyphStyle::Small;
+        if key.is_empty() {
+            write!(tv.text, "{}", label).ok();
+        } else {
+            write!(tv.text, "{}: {}", key, label).ok();
+        }
+        gam.post_textview(&mut tv).ok();
+    }
+
+    // Key hint
+    let mut tv = TextView::new(
+        gid,
+        TextBounds::BoundingBox(Rectangle::new_coords(x + 4, y + menu_height - 20, x + menu_width - 4, y + menu_height - 4)),
+    );
+    tv.style = GlyphStyle::Small;
+    write!(tv.text, "{}", hint).ok();
+    gam.post_textview(&mut tv).ok();
+}
+
+/// Rows of bits visible at once in the full binary view
+pub const BINARY_VIEW_ROWS: usize = 6;
+
+/// Draw the full binary view overlay: `rows` from `first` on, one byte per
+/// row, each followed by its value in hex
+pub fn draw_binary_view(gam: &Gam, gid: gam::Gid, title: &str, rows: &[(String, String)], first: usize) {
+    let line_height = 20;
+    let menu_width = SCREEN_WIDTH - 2 * MARGIN - 8;
+    let menu_height = 24 + 8 + BINARY_VIEW_ROWS as isize * line_height + 24;
+    let x = (SCREEN_WIDTH - menu_width) / 2;
+    let y = (SCREEN_HEIGHT - menu_height) / 2;
+
+    // Background
+    gam.draw_rectangle(
+        gid,
+        Rectangle::new_with_style(
+            Point::new(x, y),
+            Point::new(x + menu_width, y + menu_height),
+            light_style(),
+        ),
+    )
+    .ok();
+
+    // Border
+    gam.draw_rectangle(
+        gid,
+        Rectangle::new_with_style(
+            Point::new(x, y),
+            Point::new(x + menu_width, y + menu_height),
+            outline_style(),
+        ),
+    )
+    .ok();
+
+    use core::fmt::Write;
+
+    // Title
+    let mut tv = TextView::new(
+        gid,
+        TextBounds::BoundingBox(Rectangle::new_coords(x + 4, y + 4, x + menu_width - 4, y + 22)),
+    );
+    tv.style = GlyphStyle::Bold;
+    write!(tv.text, "[{}]", title).ok();
+    gam.post_textview(&mut tv).ok();
+
+    gam.draw_line(
+        gid,
+        Line::new_with_style(
+            Point::new(x + 4, y + 24),
+            Point::new(x + menu_width - 4, y + 24),
+            dark_style(),
+        ),
+    )
+    .ok();
+
+    let start_y = y + 28;
+    for (i, (bits, hex)) in rows.iter().skip(first).take(BINARY_VIEW_ROWS).enumerate() {
+        let item_y = start_y + (i as isize) * line_height;
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(x + 8, item_y, x + menu_width - 4, item_y + line_height)),
+        );
+        tv.style = GlyphStyle::Monospace;
+        write!(tv.text, "{}  {}", bits, hex).ok();
+        gam.post_textview(&mut tv).ok();
+    }
+
+    // Key hint, with how much lies outside the view
+    let mut tv = TextView::new(
+        gid,
+        TextBounds::BoundingBox(Rectangle::new_coords(x + 4, y + menu_height - 20, x + menu_width - 4, y + menu_height - 4)),
+    );
+    tv.style = GlyphStyle::Small;
+    let above = if first > 0 { "▲ " } else { "" };
+    let below = if first + BINARY_VIEW_ROWS < rows.len() { "▼ " } else { "" };
+    write!(tv.text, "{}{}↑↓ scroll, ESC close", above, below).ok();
+    gam.post_textview(&mut tv).ok();
+}
+
+/// Draw the bit-field inspector overlay: the grid `rows` (bits over marks),
+/// then `lines` describing the field and any value being typed
+pub fn draw_bit_inspector(
+    gam: &Gam,
+    gid: gam::Gid,
+    title: &str,
+    rows: &[(String, String)],
+    lines: &[&str],
+) {
+    let bit_height = 16;
+    let mark_height = 12;
+    let line_height = 18;
+    let menu_width = SCREEN_WIDTH - 2 * MARGIN - 8;
+    let menu_height = 24 + 8
+        + rows.len() as isize * (bit_height + mark_height)
+        + lines.len() as isize * line_height
+        + 24;
+    let x = (SCREEN_WIDTH - menu_width) / 2;
+    let y = (SCREEN_HEIGHT - menu_height) / 2;
+
+    // Background
+    gam.draw_rectangle(
+        gid,
+        Rectangle::new_with_style(
+            Point::new(x, y),
+            Point::new(x + menu_width, y + menu_height),
+            light_style(),
+        ),
+    )
+    .ok();
+
+    // Border
+    gam.draw_rectangle(
+        gid,
+        Rectangle::new_with_style(
+            Point::new(x, y),
+            Point::new(x + menu_width, y + menu_height),
+            outline_style(),
+        ),
+    )
+    .ok();
+
+    use core::fmt::Write;
+
+    // Title
+    let mut tv = TextView::new(
+        gid,
+        TextBounds::BoundingBox(Rectangle::new_coords(x + 4, y + 4, x + menu_width - 4, y + 22)),
+    );
+    tv.style = GlyphStyle::Bold;
+    write!(tv.text, "[{}]", title).ok();
+    gam.post_textview(&mut tv).ok();
+
+    gam.draw_line(
+        gid,
+        Line::new_with_style(
+            Point::new(x + 4, y + 24),
+            Point::new(x + menu_width - 4, y + 24),
+            dark_style(),
+        ),
+    )
+    .ok();
+
+    // Grid, in monospace so the marks sit under their bits
+    let mut item_y = y + 28;
+    for (bits, marks) in rows {
+        for (text, height) in [(bits, bit_height), (marks, mark_height)] {
+            let mut tv = TextView::new(
+                gid,
+                TextBounds::BoundingBox(Rectangle::new_coords(x + 8, item_y, x + menu_width - 4, item_y + height)),
+            );
+            tv.style = GlyphStyle::Monospace;
+            write!(tv.text, "{}", text).ok();
+            gam.post_textview(&mut tv).ok();
+            item_y += height;
+        }
+    }
+
+    for line in lines {
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(x + 4, item_y, x + menu_width - 4, item_y + line_height)),
+        );
+        tv.style = GlyphStyle::Regular;
+        write!(tv.text, "{}", line).ok();
+        gam.post_textview(&mut tv).ok();
+        item_y += line_height;
+    }
+
+    // Key hint
+    let mut tv = TextView::new(
+        gid,
+        TextBounds::BoundingBox(Rectangle::new_coords(x + 4, y + menu_height - 20, x + menu_width - 4, y + menu_height - 4)),
+    );
+    tv.style = GlyphStyle::Small;
+    write!(tv.text, "SPC flip, h/l field, p part, i read, x, Enter, ESC").ok();
+    gam.post_textview(&mut tv).ok();
+}
+
+/// Columns of a matrix visible at once in the editor and viewer
+pub const MATRIX_VIEW_COLS: usize = 3;
+
+/// Characters per matrix cell, not counting the brackets marking the cursor
+pub const MATRIX_CELL_WIDTH: usize = 9;
+
+/// Draw a matrix overlay: the cells of `rows` from column `first_col` on,
+/// the cell at `cursor` in brackets, then `lines` and the key `hint`
+#[allow(clippy::too_many_arguments)]
+pub fn draw_matrix(
+    gam: &Gam,
+    gid: gam::Gid,
+    title: &str,
+    rows: &[Vec<String>],
+    first_col: usize,
+    cursor: Option<(usize, usize)>,
+    lines: &[&str],
+    hint: &str,
+) {
+    let line_height = 20;
+    let menu_width = SCREEN_WIDTH - 2 * MARGIN - 8;
+    let menu_height = 24 + 8 + (rows.len() + lines.len()) as isize * line_height + 24;
+    let x = (SCREEN_WIDTH - menu_width) / 2;
+    let y = (SCREEN_HEIGHT - menu_height) / 2;
+
+    // Background
+    gam.draw_rectangle(
+        gid,
+        Rectangle::new_with_style(
+            Point::new(x, y),
+            Point::new(x + menu_width, y + menu_height),
+            light_style(),
+        ),
+    )
+    .ok();
+
+    // Border
+    gam.draw_rectangle(
+        gid,
+        Rectangle::new_with_style(
+            Point::new(x, y),
+            Point::new(x + menu_width, y + menu_height),
+            outline_style(),
+        ),
+    )
+    .ok();
+
+    use core::fmt::Write;
+
+    // Title
+    let mut tv = TextView::new(
+        gid,
+        TextBounds::BoundingBox(Rectangle::new_coords(x + 4, y + 4, x + menu_width - 4, y + 22)),
+    );
+    tv.style = GlyphStyle::Bold;
+    write!(tv.text, "[{}]", title).ok();
+    gam.post_textview(&mut tv).ok();
+
+    gam.draw_line(
+        gid,
+        Line::new_with_style(
+            Point::new(x + 4, y + 24),
+            Point::new(x + menu_width - 4, y + 24),
+            dark_style(),
+        ),
+    )
+    .ok();
+
+    // Cells, in monospace so the columns line up
+    let mut item_y = y + 28;
+    for (r, row) in rows.iter().enumerate() {
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(x + 4, item_y, x + menu_width - 4, item_y + line_height)),
+        );
+        tv.style = GlyphStyle::Monospace;
+        for (c, cell) in row.iter().enumerate().skip(first_col).take(MATRIX_VIEW_COLS) {
+            if cursor == Some((r, c)) {
+                write!(tv.text, "[{:>w$}]", cell, w = MATRIX_CELL_WIDTH).ok();
+            } else {
+                write!(tv.text, " {:>w$} ", cell, w = MATRIX_CELL_WIDTH).ok();
+            }
+        }
+        gam.post_textview(&mut tv).ok();
+        item_y += line_height;
+    }
+
+    for line in lines {
+        let mut tv = TextView::new(
+            gid,
+            TextBounds::BoundingBox(Rectangle::new_coords(x + 4, item_y, x + menu_width - 4, item_y + line_height)),
+        );
+        tv.style = GlyphStyle::Regular;
+        write!(tv.text, "{}", line).ok();
+        gam.post_textview(&mut tv).ok();
+        item_y += line_height;
+    }
+
+    // Key hint, with whether columns lie outside the view
+    let cols = rows.first().map_or(0, |row| row.len());
+    let mut tv = TextView::new(
+        gid,
+        TextBounds::BoundingBox(Rectangle::new_coords(x + 4, y + menu_height - 20, x + menu_width - 4, y + menu_height - 4)),
+    );
+    tv.style = GlyphStyle::Small;
+    let left = if first_col > 0 { "◀ " } else { "" };
+    let right = if first_col + MATRIX_VIEW_COLS < cols { "▶ " } else { "" };
+    write!(tv.text, "{}{}{}", left, right, hint).ok();
+    gam.post_textview(&mut tv).ok();
+}
+
+extern crate alloc;
@@ -0,0 +1,273 @@
+//! Unit catalog and conversions
+
+use crate::functions::CalcError;
+use crate::quantity::Dims;
+
+/// What a unit measures; only units of the same dimension convert
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Temperature,
+    Energy,
+    Pressure,
+    Speed,
+    Data,
+    Angle,
+}
+
+impl Dimension {
+    /// Every dimension, in menu order
+    pub const ALL: [Dimension; 9] = [
+        Dimension::Length,
+        Dimension::Mass,
+        Dimension::Time,
+        Dimension::Temperature,
+        Dimension::Energy,
+        Dimension::Pressure,
+        Dimension::Speed,
+        Dimension::Data,
+        Dimension::Angle,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Dimension::Length => "Length",
+            Dimension::Mass => "Mass",
+            Dimension::Time => "Time",
+            Dimension::Temperature => "Temperature",
+            Dimension::Energy => "Energy",
+            Dimension::Pressure => "Pressure",
+            Dimension::Speed => "Speed",
+            Dimension::Data => "Data",
+            Dimension::Angle => "Angle",
+        }
+    }
+
+    /// Exponents of the SI base units this dimension is made of
+    pub fn dims(&self) -> Dims {
+        match self {
+            Dimension::Length => Dims::LENGTH,
+            Dimension::Mass => Dims::MASS,
+            Dimension::Time => Dims::TIME,
+            Dimension::Temperature => Dims::TEMPERATURE,
+            Dimension::Energy => Dims::ENERGY,
+            Dimension::Pressure => Dims::PRESSURE,
+            Dimension::Speed => Dims::SPEED,
+            Dimension::Data => Dims::DATA,
+            Dimension::Angle => Dims::ANGLE,
+        }
+    }
+}
+
+/// A unit: a value `x` in it is `(x + offset) * scale` in the base unit of
+/// its dimension (m, kg, s, K, J, Pa, m/s, byte, rad)
+pub struct Unit {
+    /// Name in expressions, matched ignoring case
+    pub name: &'static str,
+    pub dimension: Dimension,
+    pub scale: f64,
+    /// Nonzero only for temperature scales with a shifted zero
+    pub offset: f64,
+}
+
+const fn unit(name: &'static str, dimension: Dimension, scale: f64) -> Unit {
+    Unit {
+        name,
+        dimension,
+        scale,
+        offset: 0.0,
+    }
+}
+
+const fn affine(name: &'static str, dimension: Dimension, scale: f64, offset: f64) -> Unit {
+    Unit {
+        name,
+        dimension,
+        scale,
+        offset,
+    }
+}
+
+use Dimension::*;
+
+/// Every unit, grouped by dimension, at most ten per dimension so that
+/// each fits one page of the picker
+pub const UNITS: &[Unit] = &[
+    unit("m", Length, 1.0),
+    unit("km", Length, 1e3),
+    unit("cm", Length, 1e-2),
+    unit("mm", Length, 1e-3),
+    unit("um", Length, 1e-6),
+    unit("in", Length, 0.0254),
+    unit("ft", Length, 0.3048),
+    unit("yd", Length, 0.9144),
+    unit("mi", Length, 1609.344),
+    unit("nmi", Length, 1852.0),
+    unit("kg", Mass, 1.0),
+    unit("g", Mass, 1e-3),
+    unit("mg", Mass, 1e-6),
+    unit("t", Mass, 1e3),
+    unit("lb", Mass, 0.453_592_37),
+    unit("oz", Mass, 0.453_592_37 / 16.0),
+    unit("st", Mass, 0.453_592_37 * 14.0),
+    unit("s", Time, 1.0),
+    unit("ms", Time, 1e-3),
+    unit("us", Time, 1e-6),
+    unit("min", Time, 60.0),
+    unit("h", Time, 3600.0),
+    unit("day", Time, 86_400.0),
+    unit("wk", Time, 604_800.0),
+    // Julian year
+    unit("yr", Time, 31_557_600.0),
+    unit("K", Temperature, 1.0),
+    affine("degC", Temperature, 1.0, 273.15),
+    affine("degF", Temperature, 5.0 / 9.0, 459.67),
+    unit("degR", Temperature, 5.0 / 9.0),
+    unit("J", Energy, 1.0),
+    unit("kJ", Energy, 1e3),
+    // Thermochemical calorie
+    unit("cal", Energy, 4.184),
+    unit("kcal", Energy, 4184.0),
+    unit("Wh", Energy, 3600.0),
+    unit("kWh", Energy, 3.6e6),
+    unit("eV", Energy, 1.602_176_634e-19),
+    // International Table BTU
+    unit("BTU", Energy, 1_055.055_852_62),
+    unit("Pa", Pressure, 1.0),
+    unit("kPa", Pressure, 1e3),
+    unit("MPa", Pressure, 1e6),
+    unit("bar", Pressure, 1e5),
+    unit("atm", Pressure, 101_325.0),
+    unit("psi", Pressure, 0.453_592_37 * 9.806_65 / (0.0254 * 0.0254)),
+    unit("mmHg", Pressure, 133.322_387_415),
+    unit("torr", Pressure, 101_325.0 / 760.0),
+    unit("mps", Speed, 1.0),
+    unit("kmh", Speed, 1000.0 / 3600.0),
+    unit("mph", Speed, 1609.344 / 3600.0),
+    unit("kn", Speed, 1852.0 / 3600.0),
+    unit("fps", Speed, 0.3048),
+    unit("bit", Data, 0.125),
+    unit("B", Data, 1.0),
+    unit("kB", Data, 1e3),
+    unit("MB", Data, 1e6),
+    unit("GB", Data, 1e9),
+    unit("TB", Data, 1e12),
+    unit("KiB", Data, 1024.0),
+    unit("MiB", Data, 1_048_576.0),
+    unit("GiB", Data, 1_073_741_824.0),
+    unit("TiB", Data, 1_099_511_627_776.0),
+    unit("rad", Angle, 1.0),
+    unit("deg", Angle, core::f64::consts::PI / 180.0),
+    unit("grad", Angle, core::f64::consts::PI / 200.0),
+    unit("turn", Angle, core::f64::consts::TAU),
+    unit("arcmin", Angle, core::f64::consts::PI / 10_800.0),
+    unit("arcsec", Angle, core::f64::consts::PI / 648_000.0),
+];
+
+/// Index of the unit called `name`, ignoring case
+pub fn index_of(name: &str) -> Option<usize> {
+    UNITS.iter().position(|u| u.name.eq_ignore_ascii_case(name))
+}
+
+/// Indices of the units of one dimension, in catalog order
+pub fn of_dimension(dimension: Dimension) -> impl Iterator<Item = usize> {
+    (0..UNITS.len()).filter(move |&i| UNITS[i].dimension == dimension)
+}
+
+/// Convert `x` from one unit to another of the same dimension
+pub fn convert(x: f64, from: usize, to: usize) -> Result<f64, CalcError> {
+    let (from, to) = match (UNITS.get(from), UNITS.get(to)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Err(CalcError::DomainError("convert: unknown unit")),
+    };
+    if from.dimension != to.dimension {
+        return Err(CalcError::DomainError("convert: incompatible units"));
+    }
+    Ok((x + from.offset) * from.scale / to.scale - to.offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conv(x: f64, from: &str, to: &str) -> f64 {
+        convert(x, index_of(from).unwrap(), index_of(to).unwrap()).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() <= 1e-9 * b.abs().max(1e-300)
+    }
+
+    #[test]
+    fn test_reference_values() {
+        // NIST SP 811 Appendix B and the SI brochure
+        let table: &[(f64, &str, &str, f64)] = &[
+            (1.0, "ft", "m", 0.3048),
+            (1.0, "mi", "km", 1.609_344),
+            (1.0, "nmi", "ft", 6_076.115_485_564_304),
+            (12.0, "in", "ft", 1.0),
+            (1.0, "lb", "kg", 0.453_592_37),
+            (1.0, "oz", "g", 28.349_523_125),
+            (1.0, "st", "lb", 14.0),
+            (1.0, "day", "min", 1440.0),
+            (1.0, "yr", "day", 365.25),
+            (1.0, "cal", "J", 4.184),
+            (1.0, "kWh", "J", 3.6e6),
+            (1.0, "BTU", "J", 1_055.055_852_62),
+            (1.0, "eV", "J", 1.602_176_634e-19),
+            (1.0, "atm", "Pa", 101_325.0),
+            (1.0, "atm", "torr", 760.0),
+            (1.0, "psi", "Pa", 6_894.757_293_168),
+            (1.0, "mmHg", "Pa", 133.322_387_415),
+            (1.0, "bar", "psi", 14.503_773_773),
+            (1.0, "mph", "mps", 0.447_04),
+            (1.0, "kn", "kmh", 1.852),
+            (1.0, "B", "bit", 8.0),
+            (1.0, "MiB", "KiB", 1024.0),
+            (1.0, "GB", "MB", 1000.0),
+            (180.0, "deg", "rad", core::f64::consts::PI),
+            (1.0, "turn", "grad", 400.0),
+            (1.0, "deg", "arcsec", 3600.0),
+        ];
+        for &(x, from, to, expected) in table {
+            let result = conv(x, from, to);
+            assert!(close(result, expected), "{} {} → {}: {}", x, from, to, result);
+        }
+    }
+
+    #[test]
+    fn test_temperatures() {
+        let table: &[(f64, &str, &str, f64)] = &[
+            (0.0, "degC", "K", 273.15),
+            (100.0, "degC", "degF", 212.0),
+            (-40.0, "degF", "degC", -40.0),
+            (32.0, "degF", "K", 273.15),
+            (0.0, "K", "degF", -459.67),
+            (491.67, "degR", "degC", 0.0),
+            (98.6, "degF", "degC", 37.0),
+        ];
+        for &(x, from, to, expected) in table {
+            let result = conv(x, from, to);
+            assert!((result - expected).abs() < 1e-9, "{} {} → {}: {}", x, from, to, result);
+        }
+    }
+
+    #[test]
+    fn test_catalog() {
+        assert_eq!(index_of("DEGF"), index_of("degF"));
+        assert!(index_of("furlong").is_none());
+        assert!(convert(1.0, index_of("m").unwrap(), index_of("kg").unwrap()).is_err());
+
+        // Names are unique ignoring case, and every dimension fits one page
+        for (i, unit) in UNITS.iter().enumerate() {
+            assert_eq!(index_of(unit.name), Some(i), "{}", unit.name);
+            assert!(unit.name.chars().all(|c| c.is_ascii_alphabetic()), "{}", unit.name);
+        }
+        for dimension in Dimension::ALL {
+            let count = of_dimension(dimension).count();
+            assert!((1..=10).contains(&count), "{:?}", dimension);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! User-defined functions
+
+use crate::algebraic::{AlgebraicParser, Spanned};
+use crate::functions::{CalcError, NumberBase};
+use crate::memory::Variables;
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// Maximum nesting of user function calls before evaluation gives up
+pub const MAX_CALL_DEPTH: usize = 32;
+
+/// Maximum parameters per function (an RPN call takes one stack level each)
+pub const MAX_PARAMS: usize = 4;
+
+/// Source form of a definition, as persisted to PDDB
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FunctionDef {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: String,
+}
+
+/// A user-defined function with its body parsed to postfix
+pub struct UserFunction {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: String,
+    pub postfix: Vec<Spanned>,
+}
+
+impl UserFunction {
+    /// Definition as typed: `f(x,y)=body`
+    pub fn definition(&self) -> String {
+        let mut buf = String::new();
+        buf.push_str(&self.name);
+        buf.push('(');
+        buf.push_str(&self.params.join(","));
+        buf.push_str(")=");
+        buf.push_str(self.body.trim());
+        buf
+    }
+}
+
+/// Table of user-defined functions
+///
+/// Bodies are re-parsed whenever the set of names changes, so a definition
+/// may call functions defined after it (and itself).
+#[derive(Default)]
+pub struct UserFunctions {
+    functions: Vec<UserFunction>,
+}
+
+impl UserFunctions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define or replace a function
+    pub fn define(&mut self, name: &str, params: &[&str], body: &str) -> Result<(), CalcError> {
+        let name = name.to_lowercase();
+        if !Variables::is_valid_name(&name)
+            || params.len() > MAX_PARAMS
+            || !params.iter().all(|p| Variables::is_valid_name(p))
+        {
+            return Err(CalcError::SyntaxError(alloc::format!(
+                "Invalid function definition: {}",
+                name
+            )));
+        }
+
+        let params: Vec<String> = params.iter().map(|p| p.to_lowercase()).collect();
+        let body = String::from(body.trim());
+
+        // Check the body parses before touching the table
+        Self::parse(&body, &self.names_with(&name))?;
+
+        let function = UserFunction {
+            name,
+            params,
+            body,
+            postfix: Vec::new(),
+        };
+        match self.functions.iter().position(|f| f.name == function.name) {
+            Some(i) => self.functions[i] = function,
+            None => self.functions.push(function),
+        }
+        self.reparse();
+        Ok(())
+    }
+
+    /// Remove a function by name
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.functions.len();
+        self.functions.retain(|f| !f.name.eq_ignore_ascii_case(name));
+        let removed = self.functions.len() != before;
+        if removed {
+            self.reparse();
+        }
+        removed
+    }
+
+    /// Look up a function by name
+    pub fn get(&self, name: &str) -> Option<&UserFunction> {
+        self.functions.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Is this the name of a user function?
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Functions in definition order
+    pub fn iter(&self) -> impl Iterator<Item = &UserFunction> {
+        self.functions.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Source definitions (for saving to storage)
+    pub fn to_defs(&self) -> Vec<FunctionDef> {
+        self.functions
+            .iter()
+            .map(|f| FunctionDef {
+                name: f.name.clone(),
+                params: f.params.clone(),
+                body: f.body.clone(),
+            })
+            .collect()
+    }
+
+    /// Replace the table from source definitions (for loading from storage)
+    pub fn set_defs(&mut self, defs: Vec<FunctionDef>) {
+        self.functions = defs
+            .into_iter()
+            .map(|def| UserFunction {
+                name: def.name,
+                params: def.params,
+                body: def.body,
+                postfix: Vec::new(),
+            })
+            .collect();
+        self.reparse();
+        // Drop anything that no longer parses rather than failing every call
+        self.functions.retain(|f| {
+            if f.postfix.is_empty() {
+                log::warn!("Dropping unparseable function {}", f.name);
+            }
+            !f.postfix.is_empty()
+        });
+    }
+
+    /// Names of all functions plus one more
+    fn names_with(&self, extra: &str) -> Vec<String> {
+        let mut names: Vec<String> = self.functions.iter().map(|f| f.name.clone()).collect();
+        names.push(String::from(extra));
+        names
+    }
+
+    /// Parse a body, resolving calls against the given function names
+    fn parse(body: &str, names: &[String]) -> Result<Vec<Spanned>, CalcError> {
+        // Bodies are base-independent: non-decimal literals need a prefix
+        let tokens = AlgebraicParser::tokenize_with(body, NumberBase::Decimal, &|n| {
+            names.iter().any(|name| name == n)
+        })?;
+        AlgebraicParser::to_postfix(tokens)
+    }
+
+    /// Re-parse every body against the current set of names
+    fn reparse(&mut self) {
+        let names: Vec<String> = self.functions.iter().map(|f| f.name.clone()).collect();
+        for f in self.functions.iter_mut() {
+            f.postfix = Self::parse(&f.body, &names).unwrap_or_default();
+        }
+    }
+}
+
+extern crate alloc;
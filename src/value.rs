@@ -0,0 +1,157 @@
+//! Values on the stack and in registers: numbers or matrices
+
+use crate::complex::Complex;
+use crate::functions::CalcError;
+use crate::matrix::Matrix;
+use crate::quantity::{Dims, Quantity};
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// A calculator value; matrices are held inline so that values stay `Copy`
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(into = "Saved", from = "Saved")]
+#[allow(clippy::large_enum_variant)]
+pub enum Value {
+    Number(Quantity),
+    Matrix(Matrix),
+}
+
+/// A value as saved: a number by its parts, a matrix by its rows. The unit
+/// a quantity is shown in is not kept.
+#[derive(Serialize, Deserialize)]
+enum Saved {
+    Number {
+        re: f64,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        im: f64,
+        #[serde(default, skip_serializing_if = "Dims::is_none")]
+        dims: Dims,
+    },
+    Matrix(Vec<Vec<f64>>),
+}
+
+fn is_zero(x: &f64) -> bool {
+    *x == 0.0
+}
+
+impl From<Value> for Saved {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Number(q) => Saved::Number { re: q.value, im: q.im, dims: q.dims },
+            Value::Matrix(m) => Saved::Matrix(m.to_rows()),
+        }
+    }
+}
+
+/// A saved matrix of the wrong shape reads as zero
+impl From<Saved> for Value {
+    fn from(saved: Saved) -> Self {
+        match saved {
+            Saved::Number { re, im, dims } => Quantity { im, ..Quantity::new(re, dims) }.into(),
+            Saved::Matrix(rows) => Matrix::from_rows(&rows).map_or_else(|_| Value::default(), Value::from),
+        }
+    }
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Number(Quantity::default())
+    }
+}
+
+impl From<Quantity> for Value {
+    fn from(q: Quantity) -> Self {
+        Value::Number(q)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self {
+        Value::Number(Quantity::from(x))
+    }
+}
+
+impl From<Complex> for Value {
+    fn from(z: Complex) -> Self {
+        Value::Number(Quantity::from(z))
+    }
+}
+
+impl From<Matrix> for Value {
+    fn from(m: Matrix) -> Self {
+        Value::Matrix(m)
+    }
+}
+
+impl Value {
+    /// The number, if this is one
+    pub fn number(&self) -> Result<Quantity, CalcError> {
+        match self {
+            Value::Number(q) => Ok(*q),
+            Value::Matrix(_) => Err(CalcError::DomainError("needs a number, not a matrix")),
+        }
+    }
+
+    /// The matrix, if this is one
+    pub fn matrix(&self) -> Option<&Matrix> {
+        match self {
+            Value::Matrix(m) => Some(m),
+            Value::Number(_) => None,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Value::Number(q) => q.complex().is_zero(),
+            Value::Matrix(m) => m.iter().all(|x| x == 0.0),
+        }
+    }
+
+    /// The matrix written `[a, b, ...]`: a row of plain numbers, or rows
+    /// of equal length stacked
+    pub fn bracket(items: &[Value]) -> Result<Value, CalcError> {
+        let rows = if items.iter().all(|v| matches!(v, Value::Number(_))) {
+            let row = items.iter().map(|v| v.number()?.plain()).collect::<Result<Vec<_>, _>>()?;
+            alloc::vec![row]
+        } else {
+            let mut rows = Vec::new();
+            for item in items {
+                match item {
+                    Value::Matrix(m) if m.rows() == 1 => rows.push(m.row(0).to_vec()),
+                    _ => return Err(CalcError::DimensionError("matrix rows must be [..] lists")),
+                }
+            }
+            rows
+        };
+        Ok(Value::Matrix(Matrix::from_rows(&rows)?))
+    }
+
+    /// Expression that reads back as this value: `[[1,2],[3,4]]` for a
+    /// matrix
+    pub fn to_source(&self) -> String {
+        match self {
+            Value::Number(q) => q.to_source(),
+            Value::Matrix(m) => {
+                let mut buf = String::from("[");
+                for r in 0..m.rows() {
+                    if r > 0 {
+                        buf.push(',');
+                    }
+                    buf.push('[');
+                    for (c, &x) in m.row(r).iter().enumerate() {
+                        if c > 0 {
+                            buf.push(',');
+                        }
+                        buf.push_str(&Quantity::from(x).to_source());
+                    }
+                    buf.push(']');
+                }
+                buf.push(']');
+                buf
+            }
+        }
+    }
+}
+
+extern crate alloc;